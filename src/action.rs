@@ -0,0 +1,374 @@
+use super::*;
+use std::collections::HashSet;
+
+const PDF_NAME_S: &str = "S";
+const PDF_NAME_D: &str = "D";
+const PDF_NAME_F: &str = "F";
+const PDF_NAME_URI: &str = "URI";
+const PDF_NAME_Base: &str = "Base";
+const PDF_NAME_N: &str = "N";
+const PDF_NAME_Next: &str = "Next";
+const PDF_NAME_NewWindow: &str = "NewWindow";
+const PDF_NAME_Win: &str = "Win";
+const PDF_NAME_P: &str = "P";
+const PDF_NAME_Annots: &str = "Annots";
+const PDF_NAME_Subtype: &str = "Subtype";
+const PDF_NAME_A: &str = "A";
+const PDF_NAME_Dest: &str = "Dest";
+const PDF_NAME_Rect: &str = "Rect";
+
+/// An action dictionary (/A), by its /S type.
+#[derive(Debug, Clone)]
+pub enum Action {
+    GOTO(Destination),
+    /// Go to a destination in another file, either an explicit destination
+    /// (page numbers are in the other file) or a named one.
+    GOTOR {
+        file: Option<String>,
+        dest: Option<Destination>,
+        named_dest: Option<String>,
+        new_window: Option<bool>,
+    },
+    /// Absolute URI, relative ones are resolved against the catalog /URI /Base.
+    URI(String),
+    LAUNCH {
+        file: Option<String>,
+        parameters: Option<String>,
+        new_window: Option<bool>,
+    },
+    NAMED(String),
+    // any other action type with its dictionary
+    OTHER(String, Dict),
+}
+
+/// A clickable area of a page.
+#[derive(Debug, Clone)]
+pub struct Link {
+    pub rect: Rect,
+    pub quad_points: Vec<[f64; 8]>,
+    /// The action (or /Dest as a GoTo) followed by its /Next actions in execution order.
+    pub actions: Vec<Action>,
+}
+
+impl Action {
+    pub(crate) fn from_dict(pdf: &Pdf, dict: &Dict) -> Option<Action> {
+        let kind = pdf.lookup(dict, PDF_NAME_S)?.as_name()?;
+        let new_window = pdf.lookup(dict, PDF_NAME_NewWindow).and_then(|v| v.as_bool());
+        let action = match kind {
+            "GoTo" => Action::GOTO(pdf.destination(pdf.lookup(dict, PDF_NAME_D)?)?),
+            "GoToR" => {
+                let d = pdf.lookup(dict, PDF_NAME_D);
+                Action::GOTOR {
+                    file: pdf.lookup(dict, PDF_NAME_F).and_then(|f| pdf.file_spec_name(f)),
+                    dest: d
+                        .and_then(|v| v.as_array())
+                        .and_then(|a| Destination::from_array(pdf, a)),
+                    named_dest: d.and_then(|v| match v {
+                        Value::NAME(n) => Some(n.clone()),
+                        Value::STRING(s) => Some(decode_text(s)),
+                        _ => None,
+                    }),
+                    new_window,
+                }
+            }
+            "URI" => {
+                let uri = pdf.lookup(dict, PDF_NAME_URI)?.as_bytes()?;
+                // URIs are 7-bit ASCII
+                let uri = String::from_utf8_lossy(uri).into_owned();
+                Action::URI(match pdf.uri_base() {
+                    Some(base) => resolve_uri(&base, &uri),
+                    None => uri,
+                })
+            }
+            "Launch" => {
+                // the Windows specific dictionary wins when present
+                let win = pdf.lookup(dict, PDF_NAME_Win).and_then(|v| v.as_dict());
+                let file = win
+                    .and_then(|w| pdf.lookup(w, PDF_NAME_F))
+                    .or_else(|| pdf.lookup(dict, PDF_NAME_F))
+                    .and_then(|f| pdf.file_spec_name(f));
+                Action::LAUNCH {
+                    file,
+                    parameters: win
+                        .and_then(|w| pdf.lookup(w, PDF_NAME_P))
+                        .and_then(|v| v.as_text()),
+                    new_window,
+                }
+            }
+            "Named" => Action::NAMED(pdf.lookup(dict, PDF_NAME_N)?.as_name()?.to_string()),
+            _ => Action::OTHER(kind.to_string(), dict.clone()),
+        };
+        Some(action)
+    }
+}
+
+impl Pdf {
+    /// The action (a dictionary or a reference to one) and its /Next actions,
+    /// depth first as they are executed.
+    pub fn action_chain(&self, action: &Value) -> Vec<Action> {
+        let mut actions = Vec::new();
+        let mut visited = HashSet::new();
+        // a /Next chain may lead back to the first action
+        if let Value::REF(n0, _) = action {
+            visited.insert(*n0);
+        }
+        if let Some(dict) = self.resolve(action).as_dict() {
+            self.collect_actions(dict, &mut actions, &mut visited, 0);
+        }
+        actions
+    }
+    fn collect_actions(
+        &self,
+        dict: &Dict,
+        actions: &mut Vec<Action>,
+        visited: &mut HashSet<i32>,
+        depth: i32,
+    ) {
+        if depth > 32 {
+            return;
+        }
+        if let Some(action) = Action::from_dict(self, dict) {
+            actions.push(action);
+        }
+        let next: Vec<&Value> = match dict.get(PDF_NAME_Next) {
+            Some(v) => match self.resolve(v) {
+                Value::ARRAY(array) => array.iter().collect(),
+                _ => vec![v],
+            },
+            None => Vec::new(),
+        };
+        for v in next {
+            if let Value::REF(n0, _) = v {
+                if !visited.insert(*n0) {
+                    continue;
+                }
+            }
+            if let Some(d) = self.resolve(v).as_dict() {
+                self.collect_actions(d, actions, visited, depth + 1);
+            }
+        }
+    }
+    /// Link annotations of page `no` with their decoded actions.
+    pub fn links(&self, no: i32) -> io::Result<Vec<Link>> {
+        let id = match self.page_id(no) {
+            Some(id) => id,
+            None => return Err(Error::new(ErrorKind::Other, "page out of range")),
+        };
+        let mut links = Vec::new();
+        let page = self.obj_list.get(&id).and_then(|obj| obj.dict());
+        let annots = page
+            .and_then(|p| self.lookup(p, PDF_NAME_Annots))
+            .and_then(|v| v.as_array());
+        for item in annots.into_iter().flatten() {
+            let dict = match self.resolve(item).as_dict() {
+                Some(dict) => dict,
+                None => continue,
+            };
+            if self.lookup(dict, PDF_NAME_Subtype).and_then(|v| v.as_name()) != Some("Link") {
+                continue;
+            }
+            let mut actions = Vec::new();
+            if let Some(a) = dict
+                .get(PDF_NAME_A)
+                .filter(|v| self.resolve(v).as_dict().is_some())
+            {
+                actions = self.action_chain(a);
+            } else if let Some(d) = self
+                .lookup(dict, PDF_NAME_Dest)
+                .and_then(|v| self.destination(v))
+            {
+                actions.push(Action::GOTO(d));
+            }
+            let quad_points = match self.load_annotation(None, dict).kind {
+                AnnotKind::LINK { quad_points, .. } => quad_points,
+                _ => Vec::new(),
+            };
+            links.push(Link {
+                rect: self
+                    .lookup(dict, PDF_NAME_Rect)
+                    .and_then(Rect::from_value)
+                    .unwrap_or(Rect::new(0.0, 0.0, 0.0, 0.0)),
+                quad_points,
+                actions,
+            });
+        }
+        Ok(links)
+    }
+    // catalog /URI /Base
+    fn uri_base(&self) -> Option<String> {
+        let catalog = self.catalog()?;
+        let uri = self.lookup(catalog, PDF_NAME_URI)?.as_dict()?;
+        let base = self.lookup(uri, PDF_NAME_Base)?.as_bytes()?;
+        Some(String::from_utf8_lossy(base).into_owned())
+    }
+    /// File name of a file specification: a string, or a dictionary
+    /// with /UF, /F, /Unix or /DOS.
+    pub fn file_spec_name(&self, spec: &Value) -> Option<String> {
+        match self.resolve(spec) {
+            Value::STRING(s) => Some(decode_text(s)),
+            Value::DICT(dict) => ["UF", "F", "Unix", "DOS", "Mac"]
+                .iter()
+                .find_map(|key| self.lookup(dict, key).and_then(|v| v.as_text())),
+            _ => None,
+        }
+    }
+}
+
+// RFC 3986 style reference resolution, enough for the relative links found in PDFs
+fn resolve_uri(base: &str, uri: &str) -> String {
+    let has_scheme = uri
+        .find(':')
+        .map(|i| {
+            i > 0
+                && uri[..i]
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.')
+        })
+        .unwrap_or(false);
+    if has_scheme || base.is_empty() {
+        return uri.to_string();
+    }
+    // the fragment of the base is never kept, its query only for a reference
+    // that is empty or a fragment
+    let base = base.split('#').next().unwrap_or(base);
+    if uri.is_empty() || uri.starts_with('#') {
+        return format!("{}{}", base, uri);
+    }
+    let authority_end = match base.find("://") {
+        Some(i) => i + 3 + base[i + 3..].find(['/', '?']).unwrap_or(base.len() - i - 3),
+        None => 0,
+    };
+    let path_end = base[authority_end..].find('?').map_or(base.len(), |i| authority_end + i);
+    if uri.starts_with('?') {
+        return format!("{}{}", &base[..path_end], uri);
+    }
+    if let Some(rest) = uri.strip_prefix("//") {
+        let scheme = base.find(':').map(|i| &base[..i + 1]).unwrap_or("");
+        return format!("{}//{}", scheme, rest);
+    }
+    // dot segments are removed from the path only, not from the query
+    let (path, suffix) = uri.split_at(uri.find(['?', '#']).unwrap_or(uri.len()));
+    let base_path = &base[authority_end..path_end];
+    let merged = if path.starts_with('/') {
+        path.to_string()
+    } else if base_path.is_empty() && authority_end > 0 {
+        format!("/{}", path)
+    } else {
+        // drop the last segment of the base path
+        let dir = base_path.rfind('/').map_or("", |i| &base_path[..i + 1]);
+        format!("{}{}", dir, path)
+    };
+    let segments: Vec<&str> = merged.split('/').collect();
+    let mut out: Vec<&str> = Vec::new();
+    for (i, seg) in segments.iter().enumerate() {
+        let last = i + 1 == segments.len();
+        match *seg {
+            "." | ".." => {
+                if *seg == ".." && out.len() > 1 {
+                    out.pop();
+                }
+                // "g/." and "g/.." keep the trailing slash
+                if last {
+                    out.push("");
+                }
+            }
+            seg => out.push(seg),
+        }
+    }
+    format!("{}{}{}", &base[..authority_end], out.join("/"), suffix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relative_uri() {
+        let base = "http://a/b/c/d;p?q";
+        assert_eq!(resolve_uri(base, "g"), "http://a/b/c/g");
+        assert_eq!(resolve_uri(base, "./g"), "http://a/b/c/g");
+        assert_eq!(resolve_uri(base, "g/"), "http://a/b/c/g/");
+        assert_eq!(resolve_uri(base, "../g"), "http://a/b/g");
+        assert_eq!(resolve_uri(base, "../../../g"), "http://a/g");
+        assert_eq!(resolve_uri(base, "/g"), "http://a/g");
+        assert_eq!(resolve_uri(base, "//g"), "http://g");
+        assert_eq!(resolve_uri(base, "mailto:x@y.org"), "mailto:x@y.org");
+        assert_eq!(resolve_uri("http://a", "g"), "http://a/g");
+        assert_eq!(resolve_uri("", "g"), "g");
+    }
+
+    #[test]
+    fn query_and_fragment() {
+        // RFC 3986 5.4.1
+        let base = "http://a/b/c/d;p?q";
+        assert_eq!(resolve_uri(base, ""), "http://a/b/c/d;p?q");
+        assert_eq!(resolve_uri(base, "#s"), "http://a/b/c/d;p?q#s");
+        assert_eq!(resolve_uri(base, "?y"), "http://a/b/c/d;p?y");
+        assert_eq!(resolve_uri(base, "g?y"), "http://a/b/c/g?y");
+        assert_eq!(resolve_uri(base, "g#s"), "http://a/b/c/g#s");
+        assert_eq!(resolve_uri(base, "g?y#s"), "http://a/b/c/g?y#s");
+        assert_eq!(resolve_uri(base, ";x"), "http://a/b/c/;x");
+        assert_eq!(resolve_uri(base, "."), "http://a/b/c/");
+        assert_eq!(resolve_uri(base, ".."), "http://a/b/");
+        assert_eq!(resolve_uri(base, "../.."), "http://a/");
+        // 5.4.2
+        assert_eq!(resolve_uri(base, "g?y/./x"), "http://a/b/c/g?y/./x");
+        assert_eq!(resolve_uri(base, "g#s/../x"), "http://a/b/c/g#s/../x");
+        assert_eq!(resolve_uri(base, "g/./h"), "http://a/b/c/g/h");
+        assert_eq!(resolve_uri("http://a/b/c/d#f", "#g"), "http://a/b/c/d#g");
+        assert_eq!(resolve_uri("http://a/b/c/d", "#frag"), "http://a/b/c/d#frag");
+        assert_eq!(resolve_uri("http://a?x/y", "g"), "http://a/g");
+    }
+
+    // a /URI link resolved against the catalog base, a /Dest link, and a GoToR
+    // whose /Next chain names a Launch action and loops back to itself
+    const LINKS: &str = r"
+1 0 obj << /Type /Catalog /Pages 2 0 R /URI << /Base (http://example.com/docs/) >> >> endobj
+2 0 obj << /Type /Pages /Kids [3 0 R] /Count 1 >> endobj
+3 0 obj << /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] /Annots [4 0 R 5 0 R 6 0 R] >>
+endobj
+4 0 obj << /Type /Annot /Subtype /Link /Rect [10 10 20 20]
+  /A << /S /URI /URI (../img/a.png) >> >> endobj
+5 0 obj << /Type /Annot /Subtype /Link /Rect [0 0 5 5] /Dest [3 0 R /Fit] >> endobj
+6 0 obj << /Type /Annot /Subtype /Link /Rect [0 0 5 5] /A 7 0 R >> endobj
+7 0 obj << /S /GoToR /F (other.pdf) /D (chapter1) /NewWindow true
+  /Next [8 0 R 7 0 R] >> endobj
+8 0 obj << /S /Launch /F << /UF (run.sh) >> /Win << /F (run.exe) /P (-x) >> /Next 7 0 R >>
+endobj
+";
+
+    #[test]
+    fn links() {
+        let pdf = test_pdf(LINKS);
+        let links = pdf.links(0).unwrap();
+        assert_eq!(links.len(), 3);
+        assert_eq!(links[0].rect.urx, 20.0);
+        assert!(matches!(
+            &links[0].actions[..],
+            [Action::URI(u)] if u == "http://example.com/img/a.png"
+        ));
+        assert!(matches!(
+            &links[1].actions[..],
+            [Action::GOTO(Destination { page: Some(0), view: DestView::FIT })]
+        ));
+        match &links[2].actions[..] {
+            [Action::GOTOR {
+                file,
+                dest: None,
+                named_dest,
+                new_window: Some(true),
+            }, Action::LAUNCH {
+                file: launch,
+                parameters,
+                ..
+            }] => {
+                assert_eq!(file.as_deref(), Some("other.pdf"));
+                assert_eq!(named_dest.as_deref(), Some("chapter1"));
+                assert_eq!(launch.as_deref(), Some("run.exe"));
+                assert_eq!(parameters.as_deref(), Some("-x"));
+            }
+            actions => panic!("{:?}", actions),
+        }
+        assert!(pdf.links(1).is_err());
+    }
+}
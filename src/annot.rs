@@ -0,0 +1,523 @@
+use super::*;
+
+const PDF_NAME_Annots: &str = "Annots";
+const PDF_NAME_Subtype: &str = "Subtype";
+const PDF_NAME_Rect: &str = "Rect";
+const PDF_NAME_Contents: &str = "Contents";
+const PDF_NAME_T: &str = "T";
+const PDF_NAME_NM: &str = "NM";
+const PDF_NAME_M: &str = "M";
+const PDF_NAME_C: &str = "C";
+const PDF_NAME_IC: &str = "IC";
+const PDF_NAME_F: &str = "F";
+const PDF_NAME_Subj: &str = "Subj";
+const PDF_NAME_IRT: &str = "IRT";
+const PDF_NAME_RT: &str = "RT";
+const PDF_NAME_Popup: &str = "Popup";
+const PDF_NAME_AP: &str = "AP";
+const PDF_NAME_AS: &str = "AS";
+const PDF_NAME_OC: &str = "OC";
+const PDF_NAME_QuadPoints: &str = "QuadPoints";
+const PDF_NAME_A: &str = "A";
+const PDF_NAME_Dest: &str = "Dest";
+const PDF_NAME_Open: &str = "Open";
+const PDF_NAME_Name: &str = "Name";
+const PDF_NAME_State: &str = "State";
+const PDF_NAME_StateModel: &str = "StateModel";
+const PDF_NAME_DA: &str = "DA";
+const PDF_NAME_Q: &str = "Q";
+const PDF_NAME_InkList: &str = "InkList";
+const PDF_NAME_L: &str = "L";
+const PDF_NAME_LE: &str = "LE";
+const PDF_NAME_Vertices: &str = "Vertices";
+const PDF_NAME_FS: &str = "FS";
+const PDF_NAME_Parent: &str = "Parent";
+
+// annotation flags (/F)
+pub const ANNOT_FLAG_INVISIBLE: i32 = 1;
+pub const ANNOT_FLAG_HIDDEN: i32 = 1 << 1;
+pub const ANNOT_FLAG_PRINT: i32 = 1 << 2;
+pub const ANNOT_FLAG_NO_ZOOM: i32 = 1 << 3;
+pub const ANNOT_FLAG_NO_ROTATE: i32 = 1 << 4;
+pub const ANNOT_FLAG_NO_VIEW: i32 = 1 << 5;
+pub const ANNOT_FLAG_READ_ONLY: i32 = 1 << 6;
+pub const ANNOT_FLAG_LOCKED: i32 = 1 << 7;
+pub const ANNOT_FLAG_TOGGLE_NO_VIEW: i32 = 1 << 8;
+pub const ANNOT_FLAG_LOCKED_CONTENTS: i32 = 1 << 9;
+
+/// Subtype specific data of an annotation.
+#[derive(Debug, Clone)]
+pub enum AnnotKind {
+    LINK {
+        action: Option<Action>,
+        dest: Option<Destination>,
+        quad_points: Vec<[f64; 8]>,
+    },
+    TEXT {
+        open: bool,
+        icon: Option<String>,
+        state: Option<String>,
+        state_model: Option<String>,
+    },
+    FREE_TEXT {
+        default_appearance: Option<String>,
+        // 0 left, 1 centered, 2 right
+        justification: i32,
+    },
+    HIGHLIGHT {
+        quad_points: Vec<[f64; 8]>,
+    },
+    UNDERLINE {
+        quad_points: Vec<[f64; 8]>,
+    },
+    STRIKE_OUT {
+        quad_points: Vec<[f64; 8]>,
+    },
+    SQUIGGLY {
+        quad_points: Vec<[f64; 8]>,
+    },
+    INK {
+        ink_list: Vec<Vec<(f64, f64)>>,
+    },
+    SQUARE {
+        interior_color: Option<Vec<f64>>,
+    },
+    CIRCLE {
+        interior_color: Option<Vec<f64>>,
+    },
+    LINE {
+        start: (f64, f64),
+        end: (f64, f64),
+        line_endings: (String, String),
+        interior_color: Option<Vec<f64>>,
+    },
+    POLYGON {
+        vertices: Vec<(f64, f64)>,
+        interior_color: Option<Vec<f64>>,
+    },
+    POLY_LINE {
+        vertices: Vec<(f64, f64)>,
+    },
+    STAMP {
+        icon: Option<String>,
+    },
+    FILE_ATTACHMENT {
+        file_spec: Option<Value>,
+        icon: Option<String>,
+    },
+    POPUP {
+        parent: Option<ObjectId>,
+        open: bool,
+    },
+    WIDGET {
+        // partial field name when the widget is merged with its field
+        field_name: Option<String>,
+        parent: Option<ObjectId>,
+    },
+    OTHER,
+}
+
+/// Appearance stream(s) for one appearance type: a single stream,
+/// or one per state (checkboxes, radio buttons).
+#[derive(Debug, Clone)]
+pub enum AppearanceEntry {
+    STREAM(ObjectId),
+    STATES(Vec<(String, ObjectId)>),
+}
+
+/// The /AP dictionary.
+#[derive(Debug, Clone)]
+pub struct Appearance {
+    pub normal: Option<AppearanceEntry>,
+    pub rollover: Option<AppearanceEntry>,
+    pub down: Option<AppearanceEntry>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Annotation {
+    /// The annotation dictionary, None for a direct object in /Annots.
+    pub id: Option<ObjectId>,
+    pub subtype: String,
+    pub kind: AnnotKind,
+    pub rect: Rect,
+    pub contents: Option<String>,
+    /// /T of markup annotations.
+    pub author: Option<String>,
+    pub subject: Option<String>,
+    /// Unique name (/NM).
+    pub name: Option<String>,
+    pub modified: Option<DateTime>,
+    /// /C, 0 (transparent), 1 (gray), 3 (RGB) or 4 (CMYK) components.
+    pub color: Option<Vec<f64>>,
+    pub flags: i32,
+    /// The annotation this one replies to (/IRT).
+    pub in_reply_to: Option<ObjectId>,
+    /// /RT, "R" (reply) or "Group".
+    pub reply_type: Option<String>,
+    pub popup: Option<ObjectId>,
+    pub appearance: Option<Appearance>,
+    /// /AS, the selected state in the appearance dictionary.
+    pub appearance_state: Option<String>,
+    /// /OC, the optional content group or membership dictionary, see Pdf::oc_visible.
+    pub optional_content: Option<Value>,
+}
+
+impl Annotation {
+    pub fn has_flag(&self, flag: i32) -> bool {
+        self.flags & flag != 0
+    }
+    /// Markup annotations carry author, reply and popup information.
+    pub fn is_markup(&self) -> bool {
+        !matches!(
+            self.kind,
+            AnnotKind::LINK { .. } | AnnotKind::POPUP { .. } | AnnotKind::WIDGET { .. }
+        ) && self.subtype != "Movie"
+            && self.subtype != "Screen"
+            && self.subtype != "PrinterMark"
+            && self.subtype != "TrapNet"
+            && self.subtype != "Watermark"
+            && self.subtype != "3D"
+    }
+    /// Direct replies to the annotation `id` among `annots`, use it
+    /// repeatedly to walk a whole reply thread.
+    pub fn replies(annots: &[Annotation], id: ObjectId) -> Vec<&Annotation> {
+        annots
+            .iter()
+            .filter(|a| a.in_reply_to == Some(id))
+            .collect()
+    }
+}
+
+impl Pdf {
+    /// All annotations of page `no` in /Annots order.
+    pub fn annotations(&self, no: i32) -> io::Result<Vec<Annotation>> {
+        let id = match self.page_id(no) {
+            Some(id) => id,
+            None => return Err(Error::new(ErrorKind::Other, "page out of range")),
+        };
+        let mut annots = Vec::new();
+        let page = self.obj_list.get(&id).and_then(|obj| obj.dict());
+        if let Some(list) = page
+            .and_then(|p| self.lookup(p, PDF_NAME_Annots))
+            .and_then(|v| v.as_array())
+        {
+            for item in list {
+                if let Some(dict) = self.resolve(item).as_dict() {
+                    annots.push(self.load_annotation(item.as_object_id(), dict));
+                }
+            }
+        }
+        Ok(annots)
+    }
+    pub(crate) fn load_annotation(&self, id: Option<ObjectId>, dict: &Dict) -> Annotation {
+        let text = |key: &str| self.lookup(dict, key).and_then(|v| v.as_text());
+        let name = |key: &str| {
+            self.lookup(dict, key)
+                .and_then(|v| v.as_name())
+                .map(String::from)
+        };
+        let int = |key: &str| self.lookup(dict, key).and_then(|v| v.as_int());
+        let reference = |key: &str| dict.get(key).and_then(|v| v.as_object_id());
+        let numbers = |key: &str| -> Option<Vec<f64>> {
+            self.lookup(dict, key)?
+                .as_array()?
+                .iter()
+                .map(|v| self.resolve(v).as_f64())
+                .collect()
+        };
+        let quad_points = || -> Vec<[f64; 8]> {
+            numbers(PDF_NAME_QuadPoints)
+                .unwrap_or_default()
+                .chunks_exact(8)
+                .map(|q| {
+                    let mut quad = [0.0; 8];
+                    quad.copy_from_slice(q);
+                    quad
+                })
+                .collect()
+        };
+        let points = |v: Vec<f64>| -> Vec<(f64, f64)> {
+            v.chunks_exact(2).map(|p| (p[0], p[1])).collect()
+        };
+        let subtype = name(PDF_NAME_Subtype).unwrap_or_default();
+        let kind = match subtype.as_str() {
+            "Link" => AnnotKind::LINK {
+                action: self
+                    .lookup(dict, PDF_NAME_A)
+                    .and_then(|v| v.as_dict())
+                    .and_then(|a| Action::from_dict(self, a)),
+                dest: self
+                    .lookup(dict, PDF_NAME_Dest)
+                    .and_then(|v| self.destination(v)),
+                quad_points: quad_points(),
+            },
+            "Text" => AnnotKind::TEXT {
+                open: self
+                    .lookup(dict, PDF_NAME_Open)
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(false),
+                icon: name(PDF_NAME_Name),
+                state: text(PDF_NAME_State),
+                state_model: text(PDF_NAME_StateModel),
+            },
+            "FreeText" => AnnotKind::FREE_TEXT {
+                default_appearance: text(PDF_NAME_DA),
+                justification: int(PDF_NAME_Q).unwrap_or(0),
+            },
+            "Highlight" => AnnotKind::HIGHLIGHT {
+                quad_points: quad_points(),
+            },
+            "Underline" => AnnotKind::UNDERLINE {
+                quad_points: quad_points(),
+            },
+            "StrikeOut" => AnnotKind::STRIKE_OUT {
+                quad_points: quad_points(),
+            },
+            "Squiggly" => AnnotKind::SQUIGGLY {
+                quad_points: quad_points(),
+            },
+            "Ink" => AnnotKind::INK {
+                ink_list: self
+                    .lookup(dict, PDF_NAME_InkList)
+                    .and_then(|v| v.as_array())
+                    .map(|paths| {
+                        paths
+                            .iter()
+                            .filter_map(|p| self.resolve(p).as_array())
+                            .map(|p| {
+                                points(p.iter().filter_map(|v| self.resolve(v).as_f64()).collect())
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+            },
+            "Square" => AnnotKind::SQUARE {
+                interior_color: numbers(PDF_NAME_IC),
+            },
+            "Circle" => AnnotKind::CIRCLE {
+                interior_color: numbers(PDF_NAME_IC),
+            },
+            "Line" => {
+                let l = numbers(PDF_NAME_L).unwrap_or_default();
+                let le: Vec<String> = self
+                    .lookup(dict, PDF_NAME_LE)
+                    .and_then(|v| v.as_array())
+                    .map(|a| {
+                        a.iter()
+                            .filter_map(|v| v.as_name().map(String::from))
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                let ending = |i: usize| le.get(i).cloned().unwrap_or_else(|| "None".to_string());
+                AnnotKind::LINE {
+                    start: (*l.first().unwrap_or(&0.0), *l.get(1).unwrap_or(&0.0)),
+                    end: (*l.get(2).unwrap_or(&0.0), *l.get(3).unwrap_or(&0.0)),
+                    line_endings: (ending(0), ending(1)),
+                    interior_color: numbers(PDF_NAME_IC),
+                }
+            }
+            "Polygon" => AnnotKind::POLYGON {
+                vertices: points(numbers(PDF_NAME_Vertices).unwrap_or_default()),
+                interior_color: numbers(PDF_NAME_IC),
+            },
+            "PolyLine" => AnnotKind::POLY_LINE {
+                vertices: points(numbers(PDF_NAME_Vertices).unwrap_or_default()),
+            },
+            "Stamp" => AnnotKind::STAMP {
+                icon: name(PDF_NAME_Name),
+            },
+            "FileAttachment" => AnnotKind::FILE_ATTACHMENT {
+                file_spec: dict.get(PDF_NAME_FS).cloned(),
+                icon: name(PDF_NAME_Name),
+            },
+            "Popup" => AnnotKind::POPUP {
+                parent: reference(PDF_NAME_Parent),
+                open: self
+                    .lookup(dict, PDF_NAME_Open)
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(false),
+            },
+            "Widget" => AnnotKind::WIDGET {
+                field_name: text(PDF_NAME_T),
+                parent: reference(PDF_NAME_Parent),
+            },
+            _ => AnnotKind::OTHER,
+        };
+        let mut annot = Annotation {
+            id,
+            subtype,
+            kind,
+            rect: self
+                .lookup(dict, PDF_NAME_Rect)
+                .and_then(Rect::from_value)
+                .unwrap_or(Rect::new(0.0, 0.0, 0.0, 0.0)),
+            contents: text(PDF_NAME_Contents),
+            author: None,
+            subject: None,
+            name: text(PDF_NAME_NM),
+            modified: text(PDF_NAME_M).and_then(|m| DateTime::parse(&m)),
+            color: numbers(PDF_NAME_C),
+            flags: int(PDF_NAME_F).unwrap_or(0),
+            in_reply_to: None,
+            reply_type: None,
+            popup: None,
+            appearance: self
+                .lookup(dict, PDF_NAME_AP)
+                .and_then(|v| v.as_dict())
+                .map(|ap| Appearance {
+                    normal: self.appearance_entry(ap, "N"),
+                    rollover: self.appearance_entry(ap, "R"),
+                    down: self.appearance_entry(ap, "D"),
+                }),
+            appearance_state: name(PDF_NAME_AS),
+            optional_content: dict.get(PDF_NAME_OC).cloned(),
+        };
+        if annot.is_markup() {
+            annot.author = text(PDF_NAME_T);
+            annot.subject = text(PDF_NAME_Subj);
+            annot.in_reply_to = reference(PDF_NAME_IRT);
+            annot.reply_type = name(PDF_NAME_RT).or_else(|| {
+                annot.in_reply_to.map(|_| "R".to_string())
+            });
+            annot.popup = reference(PDF_NAME_Popup);
+        }
+        annot
+    }
+    fn appearance_entry(&self, ap: &Dict, key: &str) -> Option<AppearanceEntry> {
+        let val = ap.get(key)?;
+        if let Value::REF(n0, n1) = val {
+            if let Some(Value::STREAM(_)) = self.get_object(ObjectId::new(*n0, *n1)) {
+                return Some(AppearanceEntry::STREAM(ObjectId::new(*n0, *n1)));
+            }
+        }
+        let states = self.resolve(val).as_dict()?;
+        Some(AppearanceEntry::STATES(
+            states
+                .iter()
+                .filter_map(|(state, v)| v.as_object_id().map(|id| (state.to_string(), id)))
+                .collect(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a note with a popup and a reply, markup of several kinds, a checkbox widget
+    // and a link
+    const ANNOTS: &str = r"
+1 0 obj << /Type /Catalog /Pages 2 0 R >> endobj
+2 0 obj << /Type /Pages /Kids [3 0 R] /Count 1 >> endobj
+3 0 obj << /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792]
+  /Annots [10 0 R 11 0 R 12 0 R 13 0 R 14 0 R 15 0 R 16 0 R
+  << /Type /Annot /Subtype /Link /Rect [0 0 1 1] /Dest [3 0 R /Fit] /T (x) >>] >> endobj
+10 0 obj << /Type /Annot /Subtype /Text /Rect [100 100 120 120] /Contents (Note) /T (Ann)
+  /Subj (Comment) /NM (n1) /M (D:20240102030405Z) /C [1 1 0] /F 28 /Open true /Name /Comment
+  /Popup 11 0 R /State (Accepted) /StateModel (Review) >> endobj
+11 0 obj << /Type /Annot /Subtype /Popup /Rect [120 100 220 160] /Parent 10 0 R /T (Ann) >>
+endobj
+12 0 obj << /Type /Annot /Subtype /Text /Rect [100 100 120 120] /Contents (Reply) /T (Bob)
+  /IRT 10 0 R >> endobj
+13 0 obj << /Type /Annot /Subtype /Highlight /Rect [0 0 50 20]
+  /QuadPoints [0 20 50 20 0 0 50 0 1 2 3] >> endobj
+14 0 obj << /Type /Annot /Subtype /Ink /Rect [0 0 50 50] /InkList [[0 0 10 10 20] [5 5]] >>
+endobj
+15 0 obj << /Type /Annot /Subtype /Line /Rect [0 0 50 50] /L [0 0 50 50] /LE [/OpenArrow]
+  /IC [0 0 1] >> endobj
+16 0 obj << /Type /Annot /Subtype /Widget /Rect [0 0 10 10] /T (agree) /AS /Yes
+  /AP << /N << /Yes 17 0 R /Off 18 0 R >> /D 17 0 R >> >> endobj
+17 0 obj << /BBox [0 0 10 10] >> stream
+0 0 m 10 10 l S
+endstream endobj
+18 0 obj << /BBox [0 0 10 10] >> stream
+endstream endobj
+";
+
+    #[test]
+    fn annotations() {
+        let pdf = test_pdf(ANNOTS);
+        let annots = pdf.annotations(0).unwrap();
+        assert_eq!(annots.len(), 8);
+        let note = &annots[0];
+        assert_eq!(note.id, Some(ObjectId::new(10, 0)));
+        assert_eq!(note.rect, Rect::new(100.0, 100.0, 120.0, 120.0));
+        assert_eq!(note.contents.as_deref(), Some("Note"));
+        assert_eq!(note.author.as_deref(), Some("Ann"));
+        assert_eq!(note.subject.as_deref(), Some("Comment"));
+        assert_eq!(note.name.as_deref(), Some("n1"));
+        assert_eq!(note.modified.map(|m| m.day), Some(2));
+        assert_eq!(note.color.as_deref(), Some(&[1.0, 1.0, 0.0][..]));
+        assert!(note.has_flag(ANNOT_FLAG_PRINT) && note.has_flag(ANNOT_FLAG_NO_ROTATE));
+        assert!(!note.has_flag(ANNOT_FLAG_HIDDEN));
+        assert_eq!(note.popup, Some(ObjectId::new(11, 0)));
+        assert!(matches!(
+            &note.kind,
+            AnnotKind::TEXT { open: true, icon: Some(i), state: Some(s), .. }
+                if i == "Comment" && s == "Accepted"
+        ));
+        // popups are not markup, their /T is not an author
+        let popup = &annots[1];
+        assert!(!popup.is_markup() && popup.author.is_none());
+        assert!(matches!(popup.kind, AnnotKind::POPUP { parent: Some(_), open: false }));
+        let replies = Annotation::replies(&annots, ObjectId::new(10, 0));
+        assert_eq!(replies.len(), 1);
+        assert_eq!(replies[0].reply_type.as_deref(), Some("R"));
+        match &annots[3].kind {
+            AnnotKind::HIGHLIGHT { quad_points } => assert_eq!(quad_points.len(), 1),
+            kind => panic!("{:?}", kind),
+        }
+        match &annots[4].kind {
+            AnnotKind::INK { ink_list } => {
+                assert_eq!(ink_list, &[vec![(0.0, 0.0), (10.0, 10.0)], vec![(5.0, 5.0)]])
+            }
+            kind => panic!("{:?}", kind),
+        }
+        match &annots[5].kind {
+            AnnotKind::LINE { end, line_endings, interior_color, .. } => {
+                assert_eq!(*end, (50.0, 50.0));
+                assert_eq!(line_endings, &("OpenArrow".to_string(), "None".to_string()));
+                assert_eq!(interior_color.as_deref(), Some(&[0.0, 0.0, 1.0][..]));
+            }
+            kind => panic!("{:?}", kind),
+        }
+        let widget = &annots[6];
+        assert!(matches!(
+            &widget.kind,
+            AnnotKind::WIDGET { field_name: Some(n), .. } if n == "agree"
+        ));
+        assert_eq!(widget.appearance_state.as_deref(), Some("Yes"));
+        let appearance = widget.appearance.as_ref().unwrap();
+        match &appearance.normal {
+            Some(AppearanceEntry::STATES(states)) => assert_eq!(states.len(), 2),
+            entry => panic!("{:?}", entry),
+        }
+        assert!(matches!(appearance.down, Some(AppearanceEntry::STREAM(id)) if id.id == 17));
+        assert!(appearance.rollover.is_none());
+        let link = &annots[7];
+        assert!(link.id.is_none() && !link.is_markup() && link.author.is_none());
+        assert!(matches!(&link.kind, AnnotKind::LINK { dest: Some(_), .. }));
+        assert!(pdf.annotations(1).is_err());
+    }
+
+    #[test]
+    fn indirect_annots() {
+        // /Annots as an indirect array, entries that are not dictionaries skipped and
+        // a /Rect given by its other corners
+        let pdf = test_pdf(
+            r"
+1 0 obj << /Type /Catalog /Pages 2 0 R >> endobj
+2 0 obj << /Type /Pages /Kids [3 0 R] /Count 1 >> endobj
+3 0 obj << /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] /Annots 4 0 R >> endobj
+4 0 obj [5 0 R 9 0 R null 6 0 R] endobj
+5 0 obj << /Type /Annot /Subtype /Square /Rect [50 60 10 20] >> endobj
+6 0 obj << /Type /Annot /Subtype /Circle /Rect [0 0 10 10] /T (Ann) >> endobj
+",
+        );
+        let annots = pdf.annotations(0).unwrap();
+        assert_eq!(annots.len(), 2);
+        assert_eq!(annots[0].id, Some(ObjectId::new(5, 0)));
+        assert_eq!(annots[0].rect, Rect::new(10.0, 20.0, 50.0, 60.0));
+        assert_eq!(annots[1].author.as_deref(), Some("Ann"));
+    }
+}
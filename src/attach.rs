@@ -0,0 +1,481 @@
+use super::*;
+use std::collections::HashSet;
+
+const PDF_NAME_Names: &str = "Names";
+const PDF_NAME_EmbeddedFiles: &str = "EmbeddedFiles";
+const PDF_NAME_AF: &str = "AF";
+const PDF_NAME_EF: &str = "EF";
+const PDF_NAME_UF: &str = "UF";
+const PDF_NAME_F: &str = "F";
+const PDF_NAME_Desc: &str = "Desc";
+const PDF_NAME_Subtype: &str = "Subtype";
+const PDF_NAME_Params: &str = "Params";
+const PDF_NAME_Size: &str = "Size";
+const PDF_NAME_CheckSum: &str = "CheckSum";
+const PDF_NAME_CreationDate: &str = "CreationDate";
+const PDF_NAME_ModDate: &str = "ModDate";
+const PDF_NAME_AFRelationship: &str = "AFRelationship";
+const PDF_NAME_Type: &str = "Type";
+const PDF_NAME_Annots: &str = "Annots";
+
+/// A file specification dictionary, with the embedded file stream if it has one.
+#[derive(Debug, Clone, Default)]
+pub struct EmbeddedFile {
+    /// The file specification, None for a direct dictionary.
+    pub id: Option<ObjectId>,
+    pub name: Option<String>,
+    /// Key in the /EmbeddedFiles name tree.
+    pub key: Option<String>,
+    /// Listed in the catalog or a page /AF array.
+    pub associated: bool,
+    /// Page of the FileAttachment annotation pointing to it.
+    pub page: Option<i32>,
+    pub description: Option<String>,
+    /// MIME type from the stream /Subtype, e.g. "text/xml".
+    pub mime_type: Option<String>,
+    /// /Params /Size, the uncompressed length as written by the producer.
+    pub size: Option<i32>,
+    /// /Params /CheckSum, the MD5 digest of the file.
+    pub checksum: Option<Vec<u8>>,
+    pub created: Option<DateTime>,
+    pub modified: Option<DateTime>,
+    /// /AFRelationship: Source, Data, Alternative, Supplement, EncryptedPayload,
+    /// FormData, Schema or Unspecified.
+    pub relationship: Option<String>,
+    /// The /EF stream, None for references to external files.
+    pub stream: Option<ObjectId>,
+}
+
+impl EmbeddedFile {
+    /// A file to attach under `name`, see Pdf::add_embedded_file.
+    pub fn new(name: &str) -> EmbeddedFile {
+        EmbeddedFile {
+            name: Some(name.to_string()),
+            ..Default::default()
+        }
+    }
+}
+
+impl Pdf {
+    /// File specifications reachable from the /EmbeddedFiles name tree, /AF arrays of
+    /// the catalog and pages, and FileAttachment annotations, each listed once.
+    pub fn embedded_files(&self) -> Vec<EmbeddedFile> {
+        let mut files: Vec<EmbeddedFile> = Vec::new();
+        let mut seen = HashSet::new();
+        // the same specification often appears in several places; name tree values
+        // come resolved, so specifications are told apart by their stream first
+        let identity = |f: &EmbeddedFile| f.stream.or(f.id);
+        let mut add = |files: &mut Vec<EmbeddedFile>, file: EmbeddedFile| match identity(&file) {
+            Some(id) if !seen.insert(id) => {
+                if let Some(f) = files.iter_mut().find(|f| identity(f) == Some(id)) {
+                    f.id = f.id.or(file.id);
+                    f.key = f.key.take().or(file.key);
+                    f.associated |= file.associated;
+                    f.page = f.page.or(file.page);
+                }
+            }
+            _ => files.push(file),
+        };
+        if let Some(tree) = self.embedded_files_tree() {
+            for (key, spec) in self.name_tree(tree) {
+                if let Some(mut file) = self.load_file_spec(spec) {
+                    file.key = Some(decode_text(&key));
+                    add(&mut files, file);
+                }
+            }
+        }
+        let catalog_af = self.catalog().and_then(|c| self.lookup(c, PDF_NAME_AF));
+        let page_afs = self.pages.iter().map(|id| inherited_attr(self, *id, PDF_NAME_AF));
+        for af in std::iter::once(catalog_af).chain(page_afs).flatten() {
+            for spec in af.as_array().into_iter().flatten() {
+                if let Some(mut file) = self.load_file_spec(spec) {
+                    file.associated = true;
+                    add(&mut files, file);
+                }
+            }
+        }
+        for no in 0..self.page_count() {
+            for annot in self.annotations(no).unwrap_or_default() {
+                if let AnnotKind::FILE_ATTACHMENT {
+                    file_spec: Some(spec),
+                    ..
+                } = &annot.kind
+                {
+                    if let Some(mut file) = self.load_file_spec(spec) {
+                        file.page = Some(no);
+                        add(&mut files, file);
+                    }
+                }
+            }
+        }
+        files
+    }
+    /// The decoded contents of an embedded file.
+    pub fn embedded_file_data(&self, file: &EmbeddedFile) -> io::Result<&[u8]> {
+        let stream = file
+            .stream
+            .and_then(|id| self.get_object(id))
+            .and_then(|v| v.as_stream())
+            .ok_or_else(|| Error::new(ErrorKind::Other, "file is not embedded"))?;
+        decoded_data(stream)
+    }
+    /// Embeds `data` as described by `file`: name, key (the name if None), description,
+    /// MIME type, dates and relationship. Size and MD5 checksum are computed and the
+    /// modification date defaults to now. The specification goes into the
+    /// /EmbeddedFiles name tree, replacing a file with the same key, and into the
+    /// catalog /AF array when `file.associated` is set.
+    pub fn add_embedded_file(
+        &mut self,
+        file: &EmbeddedFile,
+        data: Vec<u8>,
+    ) -> io::Result<EmbeddedFile> {
+        let name = file
+            .name
+            .clone()
+            .ok_or_else(|| Error::new(ErrorKind::Other, "file name missing"))?;
+        if self.catalog().is_none() {
+            return Err(Error::new(ErrorKind::Other, "catalog not found"));
+        }
+        let key = file.key.clone().unwrap_or_else(|| name.clone());
+        let replaced = self
+            .embedded_files()
+            .into_iter()
+            .find(|f| f.key.as_deref() == Some(key.as_str()));
+        if let Some(old) = replaced {
+            self.remove_embedded_file(&old)?;
+        }
+        let date = |d: DateTime| Value::STRING(d.to_pdf_string().into_bytes());
+        let mut params = Dict::new();
+        params.push(PDF_NAME_Size.to_string(), Value::INTEGER(data.len() as i32));
+        if let Some(created) = file.created {
+            params.push(PDF_NAME_CreationDate.to_string(), date(created));
+        }
+        params.push(
+            PDF_NAME_ModDate.to_string(),
+            date(file.modified.unwrap_or_else(DateTime::now)),
+        );
+        params.push(PDF_NAME_CheckSum.to_string(), Value::STRING(md5(&data).to_vec()));
+        let mut dict = Dict::new();
+        dict.push(PDF_NAME_Type.to_string(), Value::NAME("EmbeddedFile".to_string()));
+        if let Some(mime_type) = &file.mime_type {
+            dict.push(PDF_NAME_Subtype.to_string(), Value::NAME(mime_type.clone()));
+        }
+        dict.push(PDF_NAME_Params.to_string(), Value::DICT(params));
+        let stream = self.add_object(Value::STREAM(Stream { dict, data }));
+        let stream_ref = Value::REF(stream.id, stream.genid);
+        let mut ef = Dict::new();
+        ef.push(PDF_NAME_F.to_string(), stream_ref.clone());
+        ef.push(PDF_NAME_UF.to_string(), stream_ref);
+        // /F is meant for old readers, keep it ASCII
+        let ascii: String = name
+            .chars()
+            .map(|c| if c.is_ascii() && !c.is_ascii_control() { c } else { '_' })
+            .collect();
+        let mut spec = Dict::new();
+        spec.push(PDF_NAME_Type.to_string(), Value::NAME("Filespec".to_string()));
+        spec.push(PDF_NAME_F.to_string(), Value::STRING(ascii.into_bytes()));
+        spec.push(PDF_NAME_UF.to_string(), Value::STRING(encode_text(&name)));
+        if let Some(desc) = &file.description {
+            spec.push(PDF_NAME_Desc.to_string(), Value::STRING(encode_text(desc)));
+        }
+        if let Some(relationship) = &file.relationship {
+            spec.push(PDF_NAME_AFRelationship.to_string(), Value::NAME(relationship.clone()));
+        }
+        spec.push(PDF_NAME_EF.to_string(), Value::DICT(ef));
+        let spec = self.add_object(Value::DICT(spec));
+        let spec_ref = Value::REF(spec.id, spec.genid);
+        self.set_name_tree_entry(
+            &[PDF_NAME_Names, PDF_NAME_EmbeddedFiles],
+            &encode_text(&key),
+            Some(spec_ref.clone()),
+        );
+        if file.associated {
+            let root = self.object_id(self.root_id);
+            self.push_array_item(root, PDF_NAME_AF, spec_ref)?;
+        }
+        self.embedded_files()
+            .into_iter()
+            .find(|f| f.stream == Some(stream))
+            .ok_or_else(|| Error::new(ErrorKind::Other, "file is not embedded"))
+    }
+    /// Removes a file from the /EmbeddedFiles name tree, the /AF arrays and the page
+    /// annotations attaching it, then deletes its specification and stream.
+    pub fn remove_embedded_file(&mut self, file: &EmbeddedFile) -> io::Result<()> {
+        let same = |f: &EmbeddedFile| {
+            (file.id.is_some() && f.id == file.id) || (file.stream.is_some() && f.stream == file.stream)
+        };
+        let mut found = false;
+        let keys: Vec<Vec<u8>> = match self.embedded_files_tree() {
+            Some(tree) => self
+                .name_tree(tree)
+                .into_iter()
+                .filter(|(key, spec)| {
+                    file.key.as_deref() == Some(decode_text(key).as_str())
+                        || self.load_file_spec(spec).is_some_and(|f| same(&f))
+                })
+                .map(|(key, _)| key)
+                .collect(),
+            None => Vec::new(),
+        };
+        let mut targets: Vec<ObjectId> = file.id.into_iter().chain(file.stream).collect();
+        for key in keys {
+            let old = self.set_name_tree_entry(&[PDF_NAME_Names, PDF_NAME_EmbeddedFiles], &key, None);
+            if let Some(id) = old.as_ref().and_then(|v| v.as_object_id()) {
+                targets.push(id);
+            }
+            found = true;
+        }
+        for no in 0..self.page_count() {
+            for annot in self.annotations(no).unwrap_or_default() {
+                if let AnnotKind::FILE_ATTACHMENT {
+                    file_spec: Some(spec),
+                    ..
+                } = &annot.kind
+                {
+                    if self.load_file_spec(spec).is_some_and(|f| same(&f)) {
+                        targets.extend(annot.id.into_iter().chain(annot.popup));
+                    }
+                }
+            }
+        }
+        let root = self.object_id(self.root_id);
+        let holders: Vec<ObjectId> = self.pages.iter().map(|id| self.object_id(*id)).collect();
+        found |= self.remove_array_items(root, PDF_NAME_AF, &targets);
+        for page in holders {
+            found |= self.remove_array_items(page, PDF_NAME_AF, &targets);
+            found |= self.remove_array_items(page, PDF_NAME_Annots, &targets);
+        }
+        if !found {
+            return Err(Error::new(ErrorKind::Other, "embedded file not found"));
+        }
+        for id in targets {
+            self.remove_object(id);
+        }
+        Ok(())
+    }
+    // appends to the array under `key` of object `holder`, which may be indirect
+    fn push_array_item(&mut self, holder: ObjectId, key: &str, item: Value) -> io::Result<()> {
+        let dict = self
+            .get_object(holder)
+            .and_then(|v| v.as_dict())
+            .ok_or_else(|| Error::new(ErrorKind::Other, "object not found"))?;
+        if let Some(Value::REF(n0, n1)) = dict.get(key) {
+            if let Some(Value::ARRAY(array)) = self.get_object_mut(ObjectId::new(*n0, *n1)) {
+                array.push(item);
+                return Ok(());
+            }
+        }
+        let dict = self
+            .dict_path_mut(holder, &[])
+            .ok_or_else(|| Error::new(ErrorKind::Other, "object not found"))?;
+        match dict.get_mut(key) {
+            Some(Value::ARRAY(array)) => array.push(item),
+            _ => dict.push(key.to_string(), Value::ARRAY(vec![item])),
+        }
+        Ok(())
+    }
+    // drops the items of the array under `key` that reference one of `targets`, or are
+    // direct file specifications of a targeted stream. returns whether any was dropped
+    fn remove_array_items(&mut self, holder: ObjectId, key: &str, targets: &[ObjectId]) -> bool {
+        let array = self
+            .get_object(holder)
+            .and_then(|v| v.as_dict())
+            .and_then(|d| self.lookup(d, key))
+            .and_then(|v| v.as_array());
+        let array = match array {
+            Some(array) => array,
+            None => return false,
+        };
+        let kept: Vec<Value> = array
+            .iter()
+            .filter(|v| match v.as_object_id() {
+                Some(id) => !targets.contains(&id),
+                None => !self
+                    .load_file_spec(v)
+                    .and_then(|f| f.stream)
+                    .is_some_and(|id| targets.contains(&id)),
+            })
+            .cloned()
+            .collect();
+        if kept.len() == array.len() {
+            return false;
+        }
+        if let Some(dict) = self.dict_path_mut(holder, &[]) {
+            if kept.is_empty() {
+                dict.remove(key);
+            } else {
+                dict.push(key.to_string(), Value::ARRAY(kept));
+            }
+        }
+        true
+    }
+    fn embedded_files_tree(&self) -> Option<&Value> {
+        let catalog = self.catalog()?;
+        let names = self.lookup(catalog, PDF_NAME_Names)?.as_dict()?;
+        self.lookup(names, PDF_NAME_EmbeddedFiles)
+    }
+    fn load_file_spec(&self, spec: &Value) -> Option<EmbeddedFile> {
+        let dict = self.resolve(spec).as_dict()?;
+        let ef = self.lookup(dict, PDF_NAME_EF).and_then(|v| v.as_dict());
+        let stream_ref = ef.and_then(|ef| ef.get(PDF_NAME_UF).or_else(|| ef.get(PDF_NAME_F)));
+        let stream = stream_ref.and_then(|v| self.resolve(v).as_dict());
+        let params = stream
+            .and_then(|s| self.lookup(s, PDF_NAME_Params))
+            .and_then(|v| v.as_dict());
+        let param = |key: &str| params.and_then(|p| self.lookup(p, key));
+        let date = |key: &str| {
+            param(key)
+                .and_then(|v| v.as_text())
+                .and_then(|s| DateTime::parse(&s))
+        };
+        Some(EmbeddedFile {
+            id: spec.as_object_id(),
+            name: self.file_spec_name(spec),
+            key: None,
+            associated: false,
+            page: None,
+            description: self.lookup(dict, PDF_NAME_Desc).and_then(|v| v.as_text()),
+            mime_type: stream
+                .and_then(|s| self.lookup(s, PDF_NAME_Subtype))
+                .and_then(|v| v.as_name())
+                .map(String::from),
+            size: param(PDF_NAME_Size).and_then(|v| v.as_int()),
+            checksum: param(PDF_NAME_CheckSum)
+                .and_then(|v| v.as_bytes())
+                .map(|b| b.to_vec()),
+            created: date(PDF_NAME_CreationDate),
+            modified: date(PDF_NAME_ModDate),
+            relationship: self
+                .lookup(dict, PDF_NAME_AFRelationship)
+                .and_then(|v| v.as_name())
+                .map(String::from),
+            stream: stream_ref.and_then(|v| v.as_object_id()),
+        })
+    }
+}
+
+// RFC 1321, for the /CheckSum of embedded files
+fn md5(data: &[u8]) -> [u8; 16] {
+    const S: [u32; 64] = [
+        7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 5, 9, 14, 20, 5, 9, 14, 20, 5, 9,
+        14, 20, 5, 9, 14, 20, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 6, 10, 15,
+        21, 6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15, 21,
+    ];
+    // floor(abs(sin(i + 1)) * 2^32)
+    let k: Vec<u32> = (0..64)
+        .map(|i| ((i as f64 + 1.0).sin().abs() * 4294967296.0) as u32)
+        .collect();
+    let mut state: [u32; 4] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476];
+    let mut msg = data.to_vec();
+    msg.push(0x80);
+    while msg.len() % 64 != 56 {
+        msg.push(0);
+    }
+    msg.extend_from_slice(&((data.len() as u64).wrapping_mul(8)).to_le_bytes());
+    for block in msg.chunks(64) {
+        let m: Vec<u32> = block
+            .chunks(4)
+            .map(|w| u32::from_le_bytes([w[0], w[1], w[2], w[3]]))
+            .collect();
+        let [mut a, mut b, mut c, mut d] = state;
+        for i in 0..64 {
+            let (f, g) = match i / 16 {
+                0 => ((b & c) | (!b & d), i),
+                1 => ((d & b) | (!d & c), (5 * i + 1) % 16),
+                2 => (b ^ c ^ d, (3 * i + 5) % 16),
+                _ => (c ^ (b | !d), (7 * i) % 16),
+            };
+            let f = f.wrapping_add(a).wrapping_add(k[i]).wrapping_add(m[g]);
+            a = d;
+            d = c;
+            c = b;
+            b = b.wrapping_add(f.rotate_left(S[i]));
+        }
+        for (s, v) in state.iter_mut().zip([a, b, c, d]) {
+            *s = s.wrapping_add(v);
+        }
+    }
+    let mut digest = [0; 16];
+    for (i, s) in state.iter().enumerate() {
+        digest[i * 4..i * 4 + 4].copy_from_slice(&s.to_le_bytes());
+    }
+    digest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn md5_digest() {
+        // RFC 1321 test suite
+        let hex = |d: [u8; 16]| d.iter().map(|b| format!("{:02x}", b)).collect::<String>();
+        assert_eq!(hex(md5(b"")), "d41d8cd98f00b204e9800998ecf8427e");
+        assert_eq!(hex(md5(b"a")), "0cc175b9c0f1b6a831c399e269772661");
+        assert_eq!(hex(md5(b"abc")), "900150983cd24fb0d6963f7d28e17f72");
+        assert_eq!(hex(md5(b"message digest")), "f96b697d7cb7938d525a2f31aaf161d0");
+        assert_eq!(
+            hex(md5(b"abcdefghijklmnopqrstuvwxyz")),
+            "c3fcd3d76192e4007dfb496cca67e13b"
+        );
+        assert_eq!(
+            hex(md5(b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789")),
+            "d174ab98d277d9f5a5611c2c9f419d9f"
+        );
+        assert_eq!(
+            hex(md5("1234567890".repeat(8).as_bytes())),
+            "57edf4a22be3c955ac49da2e2107b67a"
+        );
+    }
+
+    const ATTACHED: &str = r"
+1 0 obj << /Type /Catalog /Pages 2 0 R >> endobj
+2 0 obj << /Type /Pages /Kids [3 0 R] /Count 1 >> endobj
+3 0 obj << /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] /Annots [4 0 R] >> endobj
+4 0 obj << /Type /Annot /Subtype /FileAttachment /Rect [0 0 10 10] /FS 5 0 R >> endobj
+5 0 obj << /Type /Filespec /F (notes.txt) /EF << /F 6 0 R >> >> endobj
+6 0 obj << /Type /EmbeddedFile /Params << /Size 5 >> >> stream
+hello
+endstream endobj
+";
+
+    #[test]
+    fn embedded_files() {
+        let mut pdf = test_pdf(ATTACHED);
+        let files = pdf.embedded_files();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].name.as_deref(), Some("notes.txt"));
+        assert_eq!(files[0].page, Some(0));
+        assert_eq!(files[0].size, Some(5));
+        assert_eq!(pdf.embedded_file_data(&files[0]).unwrap(), b"hello");
+
+        let mut file = EmbeddedFile::new("data.xml");
+        file.mime_type = Some("text/xml".to_string());
+        file.relationship = Some("Data".to_string());
+        file.associated = true;
+        let added = pdf.add_embedded_file(&file, b"<a/>".to_vec()).unwrap();
+        assert_eq!(added.key.as_deref(), Some("data.xml"));
+        assert!(added.associated);
+        assert_eq!(added.mime_type.as_deref(), Some("text/xml"));
+        assert_eq!(added.checksum.as_deref(), Some(&md5(b"<a/>")[..]));
+        assert_eq!(pdf.embedded_file_data(&added).unwrap(), b"<a/>");
+        // same key again replaces the file
+        let added = pdf.add_embedded_file(&file, b"<b/>".to_vec()).unwrap();
+        assert_eq!(pdf.embedded_files().len(), 2);
+
+        pdf.remove_embedded_file(&files[0]).unwrap();
+        assert!(pdf.annotations(0).unwrap().is_empty());
+        pdf.remove_embedded_file(&added).unwrap();
+        assert!(pdf.embedded_files().is_empty());
+        assert!(pdf.remove_embedded_file(&added).is_err());
+    }
+
+    #[test]
+    fn unsupported_filter() {
+        let filtered = ATTACHED.replace("/EmbeddedFile", "/EmbeddedFile /Filter [/JBIG2Decode]");
+        let pdf = test_pdf(&filtered);
+        let files = pdf.embedded_files();
+        let err = pdf.embedded_file_data(&files[0]).unwrap_err();
+        assert_eq!(err.to_string(), "unsupported filter JBIG2Decode");
+    }
+}
@@ -0,0 +1,543 @@
+use super::*;
+use std::collections::BTreeMap;
+use std::io::Cursor;
+
+// ranges longer than this are cut short
+const MAX_RANGE: u32 = 0x10000;
+
+// low and high bytes of code space ranges
+type CodeRanges = &'static [(&'static [u8], &'static [u8])];
+
+// how the codes of a predefined CMap read as text
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CodeText {
+    UTF16,
+    UTF32,
+    UTF8,
+    // JIS X 0208 as EUC-JP and as its 7 bit row and cell
+    EUC_JP,
+    JIS,
+    CHARSET(Charset),
+}
+
+/// A CMap: the code space ranges that split strings into codes of one to four
+/// bytes, and what each code maps to, a CID for the encoding of a Type 0 font,
+/// Unicode text for /ToUnicode.
+#[derive(Debug, Clone, Default)]
+pub struct CMap {
+    /// /CMapName, if given.
+    pub name: String,
+    /// /WMode, 1 for vertical writing.
+    pub wmode: i32,
+    // low and high bytes of each range, of equal length
+    codespace: Vec<(Vec<u8>, Vec<u8>)>,
+    unicode: HashMap<u32, String>,
+    // low code of each range to its high code and first CID
+    cids: BTreeMap<u32, (u32, u32)>,
+    identity: bool,
+    code_text: Option<CodeText>,
+    // for the Japanese CMaps, the Adobe-Japan1 CID of the space
+    japan1: Option<u32>,
+}
+
+impl CMap {
+    /// Parses CMap data. Malformed entries are skipped.
+    pub fn parse(data: &[u8]) -> CMap {
+        let mut cmap = CMap::default();
+        let mut reader = BufReader::new(Cursor::new(data));
+        // operands since the last keyword
+        let mut operands: Vec<Value> = Vec::new();
+        while let Some(token) = next_token(&mut reader, data.len()) {
+            let keyword = match token {
+                Token::KEYWORD(k) => k,
+                Token::STRING(s) => {
+                    operands.push(Value::STRING(s));
+                    continue;
+                }
+                Token::NAME(n) => {
+                    operands.push(Value::NAME(n));
+                    continue;
+                }
+                Token::INTEGER(n) => {
+                    operands.push(Value::INTEGER(n));
+                    continue;
+                }
+                Token::ARRAY_BEGIN => {
+                    let mut items = Vec::new();
+                    loop {
+                        match next_token(&mut reader, data.len()) {
+                            Some(Token::STRING(s)) => items.push(Value::STRING(s)),
+                            Some(Token::NAME(n)) => items.push(Value::NAME(n)),
+                            _ => break,
+                        }
+                    }
+                    operands.push(Value::ARRAY(items));
+                    continue;
+                }
+                _ => continue,
+            };
+            match keyword.as_str() {
+                "def" => match operands.as_slice() {
+                    [Value::NAME(key), Value::NAME(name)] if key == "CMapName" => {
+                        cmap.name = name.clone()
+                    }
+                    [Value::NAME(key), Value::INTEGER(n)] if key == "WMode" => cmap.wmode = *n,
+                    _ => (),
+                },
+                "usecmap" => {
+                    if let Some(parent) = operands.last().and_then(|v| v.as_name()) {
+                        if let Some(parent) = CMap::predefined(parent) {
+                            cmap.use_cmap(&parent);
+                        }
+                    }
+                }
+                "endcodespacerange" => {
+                    for pair in operands.chunks_exact(2) {
+                        if let [Value::STRING(low), Value::STRING(high)] = pair {
+                            if low.len() == high.len() && !low.is_empty() && low.len() <= 4 {
+                                cmap.codespace.push((low.clone(), high.clone()));
+                            }
+                        }
+                    }
+                }
+                "endbfchar" => {
+                    for pair in operands.chunks_exact(2) {
+                        if let (Some(code), Some(text)) = (code_of(&pair[0]), text_of(&pair[1])) {
+                            cmap.unicode.insert(code, text);
+                        }
+                    }
+                }
+                "endbfrange" => {
+                    for entry in operands.chunks_exact(3) {
+                        cmap.add_range(entry);
+                    }
+                }
+                "endcidchar" => {
+                    for pair in operands.chunks_exact(2) {
+                        if let (Some(code), Value::INTEGER(cid)) = (code_of(&pair[0]), &pair[1]) {
+                            if *cid >= 0 {
+                                cmap.cids.insert(code, (code, *cid as u32));
+                            }
+                        }
+                    }
+                }
+                "endcidrange" => {
+                    for entry in operands.chunks_exact(3) {
+                        if let (Some(low), Some(high), Value::INTEGER(cid)) =
+                            (code_of(&entry[0]), code_of(&entry[1]), &entry[2])
+                        {
+                            if low <= high && *cid >= 0 {
+                                cmap.cids.insert(low, (high, *cid as u32));
+                            }
+                        }
+                    }
+                }
+                _ => (),
+            }
+            operands.clear();
+        }
+        cmap
+    }
+    // <low> <high> <first text> or <low> <high> [<text> ...]
+    fn add_range(&mut self, entry: &[Value]) {
+        let (low, high) = match (code_of(&entry[0]), code_of(&entry[1])) {
+            (Some(low), Some(high)) if low <= high => {
+                (low, high.min(low.saturating_add(MAX_RANGE - 1)))
+            }
+            _ => return,
+        };
+        match &entry[2] {
+            Value::ARRAY(items) => {
+                for (code, item) in (low..=high).zip(items) {
+                    if let Some(text) = text_of(item) {
+                        self.unicode.insert(code, text);
+                    }
+                }
+            }
+            Value::STRING(first) => {
+                // the last UTF-16 unit is incremented for each code
+                let mut units = utf16_units(first);
+                let last = match units.pop() {
+                    Some(last) => last,
+                    None => return,
+                };
+                for (i, code) in (low..=high).enumerate() {
+                    let unit = match (last as usize).checked_add(i) {
+                        Some(unit) if unit <= 0xFFFF => unit as u16,
+                        _ => break,
+                    };
+                    let mut text = units.clone();
+                    text.push(unit);
+                    self.unicode.insert(code, String::from_utf16_lossy(&text));
+                }
+            }
+            _ => (),
+        }
+    }
+    /// One of the predefined CMaps: Identity-H and Identity-V, the Unicode
+    /// CMaps (UniGB-UCS2-H, UniJIS-UTF16-V, ...) and the CMaps of the Shift-JIS,
+    /// EUC, GBK, Big Five and UHC encodings (90ms-RKSJ-H, GBK-EUC-H, B5pc-H,
+    /// KSCms-UHC-H, ...). Their codes map to Unicode. Codes map to CIDs for
+    /// Identity and to Adobe-Japan1 CIDs for the Japanese CMaps (90ms-RKSJ-H, EUC-H,
+    /// H, UniJIS-UTF16-H, ...) as far as JIS X 0208 goes. The Chinese and Korean
+    /// CMaps map only their single byte ASCII codes to CIDs: the Adobe-GB1, CNS1 and
+    /// Korea1 collections are not part of the crate.
+    pub fn predefined(name: &str) -> Option<CMap> {
+        let mut cmap = CMap {
+            name: name.to_string(),
+            wmode: if name.ends_with("-V") || name == "V" { 1 } else { 0 },
+            ..CMap::default()
+        };
+        let (text, ranges): (Option<CodeText>, CodeRanges) = match name {
+            "Identity-H" | "Identity-V" => (None, &[(&[0x00, 0x00], &[0xFF, 0xFF])]),
+            n if n.starts_with("Uni") && n.contains("-UCS2-") => {
+                (Some(CodeText::UTF16), &[(&[0x00, 0x00], &[0xFF, 0xFF])])
+            }
+            n if n.starts_with("Uni") && n.contains("-UTF16-") => (
+                Some(CodeText::UTF16),
+                &[
+                    (&[0x00, 0x00], &[0xD7, 0xFF]),
+                    (&[0xD8, 0x00, 0xDC, 0x00], &[0xDB, 0xFF, 0xDF, 0xFF]),
+                    (&[0xE0, 0x00], &[0xFF, 0xFF]),
+                ],
+            ),
+            n if n.starts_with("Uni") && n.contains("-UTF32-") => {
+                (Some(CodeText::UTF32), &[(&[0x00, 0x00, 0x00, 0x00], &[0x00, 0x10, 0xFF, 0xFF])])
+            }
+            n if n.starts_with("Uni") && n.contains("-UTF8-") => (
+                Some(CodeText::UTF8),
+                &[
+                    (&[0x00], &[0x7F]),
+                    (&[0xC2, 0x80], &[0xDF, 0xBF]),
+                    (&[0xE0, 0x80, 0x80], &[0xEF, 0xBF, 0xBF]),
+                    (&[0xF0, 0x80, 0x80, 0x80], &[0xF4, 0xBF, 0xBF, 0xBF]),
+                ],
+            ),
+            n if n.contains("-RKSJ-") => (
+                Some(CodeText::CHARSET(Charset::SHIFT_JIS)),
+                &[
+                    (&[0x00], &[0x80]),
+                    (&[0x81, 0x40], &[0x9F, 0xFC]),
+                    (&[0xA0], &[0xDF]),
+                    (&[0xE0, 0x40], &[0xFC, 0xFC]),
+                ],
+            ),
+            "EUC-H" | "EUC-V" => (
+                Some(CodeText::EUC_JP),
+                &[
+                    (&[0x00], &[0x80]),
+                    (&[0x8E, 0xA0], &[0x8E, 0xDF]),
+                    (&[0xA1, 0xA1], &[0xFE, 0xFE]),
+                ],
+            ),
+            "H" | "V" => (Some(CodeText::JIS), &[(&[0x21, 0x21], &[0x7E, 0x7E])]),
+            n if n.starts_with("GB") && !n.starts_with("GBT") => (
+                Some(CodeText::CHARSET(Charset::GBK)),
+                &[(&[0x00], &[0x80]), (&[0x81, 0x40], &[0xFE, 0xFE])],
+            ),
+            n if n.starts_with("B5") || n.starts_with("ETen") || n.starts_with("HKscs") => (
+                Some(CodeText::CHARSET(Charset::BIG5)),
+                &[(&[0x00], &[0x80]), (&[0x81, 0x40], &[0xFE, 0xFE])],
+            ),
+            n if n.starts_with("KSC") && !n.starts_with("KSC-Johab") => (
+                Some(CodeText::CHARSET(Charset::UHC)),
+                &[(&[0x00], &[0x80]), (&[0x81, 0x41], &[0xFE, 0xFE])],
+            ),
+            _ => return None,
+        };
+        cmap.identity = text.is_none();
+        cmap.code_text = text;
+        let japanese = name.contains("-RKSJ-")
+            || name.starts_with("UniJIS")
+            || matches!(name, "EUC-H" | "EUC-V" | "H" | "V");
+        if japanese {
+            // proportional or half width ASCII glyphs
+            let proportional = name.contains("msp-") || name.contains("pv-")
+                || (name.starts_with("UniJIS") && !name.contains("-HW-"));
+            cmap.japan1 = Some(if proportional {
+                JAPAN1_PROPORTIONAL_ASCII
+            } else {
+                JAPAN1_HALF_WIDTH_ASCII
+            });
+        }
+        cmap.codespace = ranges.iter().map(|(low, high)| (low.to_vec(), high.to_vec())).collect();
+        Some(cmap)
+    }
+    /// Takes the code space, mappings and kind of `parent` where this CMap
+    /// has none of its own, the /UseCMap or usecmap of a CMap.
+    pub fn use_cmap(&mut self, parent: &CMap) {
+        if self.codespace.is_empty() {
+            self.codespace = parent.codespace.clone();
+        }
+        for (code, text) in &parent.unicode {
+            self.unicode.entry(*code).or_insert_with(|| text.clone());
+        }
+        for (low, range) in &parent.cids {
+            self.cids.entry(*low).or_insert(*range);
+        }
+        self.identity |= parent.identity;
+        self.code_text = self.code_text.or(parent.code_text);
+        self.japan1 = self.japan1.or(parent.japan1);
+    }
+    /// The CID a code selects.
+    pub fn cid(&self, code: u32) -> Option<u32> {
+        if let Some((low, (high, cid))) = self.cids.range(..=code).next_back() {
+            if code <= *high {
+                return cid.checked_add(code - low);
+            }
+        }
+        if self.identity {
+            return Some(code);
+        }
+        if let Some(ascii) = self.japan1 {
+            return self.japan1_cid(code, ascii);
+        }
+        // the proportional ASCII glyphs come first in all the CJK collections
+        match self.code_text {
+            Some(_) if (0x20..0x7F).contains(&code) => Some(code - 0x1F),
+            _ => None,
+        }
+    }
+    fn japan1_cid(&self, code: u32, ascii: u32) -> Option<u32> {
+        let (hi, lo) = ((code >> 8) as u8, code as u8);
+        match self.code_text? {
+            _ if code < 0x80 => japan1_cid(char::from(code as u8), ascii),
+            CodeText::CHARSET(_) if code <= 0xFF => japan1_katakana_cid(code),
+            CodeText::CHARSET(_) if code <= 0xFFFF => {
+                sjis_to_jis(hi, lo).and_then(|(j1, j2)| japan1_jis_cid(j1, j2))
+            }
+            CodeText::EUC_JP if (0x8EA0..=0x8EDF).contains(&code) => {
+                japan1_katakana_cid(code & 0xFF)
+            }
+            CodeText::EUC_JP if (0xA1A1..=0xFEFE).contains(&code) => {
+                japan1_jis_cid(hi - 0x80, lo - 0x80)
+            }
+            CodeText::JIS => japan1_jis_cid(hi, lo),
+            CodeText::UTF16 | CodeText::UTF32 | CodeText::UTF8 => {
+                let text = self.unicode(code)?;
+                let mut chars = text.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => japan1_cid(c, ascii),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+    /// Splits `bytes` into codes by the code space ranges, with the number of
+    /// bytes each takes. A byte sequence matching no range is taken as a code of
+    /// the shortest length; without ranges codes are single bytes.
+    pub fn decode(&self, bytes: &[u8]) -> Vec<(u32, usize)> {
+        let shortest = self.codespace.iter().map(|(low, _)| low.len()).min().unwrap_or(1);
+        let mut codes = Vec::new();
+        let mut i = 0;
+        while i < bytes.len() {
+            let rest = &bytes[i..];
+            let len = self
+                .codespace
+                .iter()
+                .find(|(low, high)| {
+                    low.len() <= rest.len()
+                        && (0..low.len()).all(|j| low[j] <= rest[j] && rest[j] <= high[j])
+                })
+                .map_or(shortest.min(rest.len()), |(low, _)| low.len());
+            codes.push((code_value(&rest[..len]), len));
+            i += len;
+        }
+        codes
+    }
+    /// The Unicode text of a code, if the CMap maps it.
+    pub fn unicode(&self, code: u32) -> Option<String> {
+        if let Some(text) = self.unicode.get(&code) {
+            return Some(text.clone());
+        }
+        let c = match self.code_text? {
+            CodeText::UTF16 if code > 0xFFFF => {
+                let units = [(code >> 16) as u16, code as u16];
+                return String::from_utf16(&units).ok();
+            }
+            CodeText::UTF16 | CodeText::UTF32 => char::from_u32(code),
+            CodeText::UTF8 => {
+                let bytes = code.to_be_bytes();
+                let start = bytes.iter().position(|b| *b != 0).unwrap_or(3);
+                return String::from_utf8(bytes[start..].to_vec()).ok();
+            }
+            _ if code < 0x80 => char::from_u32(code),
+            CodeText::CHARSET(Charset::SHIFT_JIS) if (0xA1..=0xDF).contains(&code) => {
+                // half width katakana
+                char::from_u32(0xFF61 + code - 0xA1)
+            }
+            CodeText::EUC_JP if (0x8EA1..=0x8EDF).contains(&code) => {
+                char::from_u32(0xFF61 + (code & 0xFF) - 0xA1)
+            }
+            CodeText::EUC_JP if code >= 0x8080 => jis_char(code - 0x8080),
+            CodeText::JIS => jis_char(code),
+            CodeText::CHARSET(charset) if code <= 0xFFFF => {
+                charset_char(charset, (code >> 8) as u8, code as u8)
+            }
+            _ => None,
+        };
+        c.map(|c| c.to_string())
+    }
+    /// Whether the CMap maps no code to text.
+    pub fn is_empty(&self) -> bool {
+        self.unicode.is_empty() && self.code_text.is_none()
+    }
+    pub fn is_vertical(&self) -> bool {
+        self.wmode == 1
+    }
+}
+
+// a JIS X 0208 row and cell, through its Shift-JIS code
+fn jis_char(code: u32) -> Option<char> {
+    let (j1, j2) = ((code >> 8) as u8, code as u8);
+    if !(0x21..=0x7E).contains(&j1) || !(0x21..=0x7E).contains(&j2) {
+        return None;
+    }
+    let (s1, s2) = jis_to_sjis(j1, j2);
+    charset_char(Charset::SHIFT_JIS, s1, s2)
+}
+
+fn code_value(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0, |code, b| (code << 8) | *b as u32)
+}
+
+fn code_of(val: &Value) -> Option<u32> {
+    match val {
+        Value::STRING(s) if !s.is_empty() && s.len() <= 4 => Some(code_value(s)),
+        _ => None,
+    }
+}
+
+// UTF-16BE, a single byte is taken as it is
+fn utf16_units(bytes: &[u8]) -> Vec<u16> {
+    if bytes.len() == 1 {
+        return vec![bytes[0] as u16];
+    }
+    bytes
+        .chunks_exact(2)
+        .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+        .collect()
+}
+
+// a destination: UTF-16BE text, several units for surrogate pairs and
+// ligatures, or a glyph name
+fn text_of(val: &Value) -> Option<String> {
+    match val {
+        Value::STRING(s) => Some(String::from_utf16_lossy(&utf16_units(s))),
+        Value::NAME(name) => glyph_unicode(name),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CMAP: &[u8] = b"
+/CIDInit /ProcSet findresource begin
+12 dict begin
+begincmap
+/CMapName /Test def
+3 begincodespacerange
+<00> <7F>
+<8140> <9FFC>
+<E04040> <E0FFFF>
+endcodespacerange
+4 beginbfchar
+<41> <0061>
+<42> <D835DC9C>
+<43> <00660066>
+<44> /Aring
+endbfchar
+2 beginbfrange
+<8140> <8142> [<3042> <3044> <30460308>]
+<8150> <8152> <FF5E>
+endbfrange
+2 begincidchar
+<45> 100
+<46> -5
+endcidchar
+2 begincidrange
+<9000> <90FF> 500
+<9100> <91FF> -1
+endcidrange
+endcmap
+";
+
+    #[test]
+    fn bf_mappings() {
+        let cmap = CMap::parse(CMAP);
+        assert_eq!(cmap.name, "Test");
+        assert_eq!(cmap.unicode(0x41).as_deref(), Some("a"));
+        // surrogate pair and ligature
+        assert_eq!(cmap.unicode(0x42).as_deref(), Some("\u{1D49C}"));
+        assert_eq!(cmap.unicode(0x43).as_deref(), Some("ff"));
+        assert_eq!(cmap.unicode(0x44).as_deref(), Some("\u{C5}"));
+        // a range with an array of destinations
+        assert_eq!(cmap.unicode(0x8141).as_deref(), Some("\u{3044}"));
+        assert_eq!(cmap.unicode(0x8142).as_deref(), Some("\u{3046}\u{308}"));
+        // a range incrementing the last unit
+        assert_eq!(cmap.unicode(0x8152).as_deref(), Some("\u{FF60}"));
+        assert_eq!(cmap.unicode(0x8153), None);
+    }
+
+    #[test]
+    fn codespace() {
+        let cmap = CMap::parse(CMAP);
+        let codes = cmap.decode(b"A\x81\x40\xE0\x40\x41B");
+        assert_eq!(codes, [(0x41, 1), (0x8140, 2), (0xE04041, 3), (0x42, 1)]);
+        // unmatched bytes are codes of the shortest length
+        assert_eq!(cmap.decode(b"\xFF\x81"), [(0xFF, 1), (0x81, 1)]);
+    }
+
+    #[test]
+    fn cid_mappings() {
+        let cmap = CMap::parse(CMAP);
+        assert_eq!(cmap.cid(0x45), Some(100));
+        assert_eq!(cmap.cid(0x9005), Some(505));
+        // negative CIDs are skipped
+        assert_eq!(cmap.cid(0x46), None);
+        assert_eq!(cmap.cid(0x9105), None);
+        let cmap = CMap::parse(b"1 begincidrange <00> <FF> -1 endcidrange");
+        assert_eq!(cmap.cid(5), None);
+        let cmap = CMap::parse(b"1 begincidrange <00> <FF> 2147483647 endcidrange");
+        assert_eq!(cmap.cid(5), Some(2147483652));
+    }
+
+    #[test]
+    fn predefined() {
+        let cmap = CMap::predefined("Identity-V").unwrap();
+        assert!(cmap.is_vertical());
+        assert_eq!(cmap.decode(b"\x12\x34"), [(0x1234, 2)]);
+        assert_eq!(cmap.cid(0x1234), Some(0x1234));
+        let cmap = CMap::predefined("UniJIS-UTF16-H").unwrap();
+        assert_eq!(cmap.decode(b"\xD8\x35\xDC\x9C"), [(0xD835DC9C, 4)]);
+        assert_eq!(cmap.unicode(0xD835DC9C).as_deref(), Some("\u{1D49C}"));
+        let cmap = CMap::predefined("90ms-RKSJ-H").unwrap();
+        assert_eq!(cmap.unicode(0xB1).as_deref(), Some("\u{FF71}"));
+        assert!(CMap::predefined("Unknown-H").is_none());
+    }
+
+    #[test]
+    fn japan1() {
+        let cmap = CMap::predefined("90ms-RKSJ-H").unwrap();
+        assert_eq!(cmap.cid(0x41), Some(264));
+        assert_eq!(cmap.cid(0xA1), Some(327));
+        assert_eq!(cmap.cid(0x8260), Some(790));
+        assert_eq!(cmap.cid(0x889F), Some(1125));
+        assert_eq!(cmap.cid(0xEAA2), Some(7477));
+        assert_eq!(cmap.cid(0x849F), Some(7479));
+        let cmap = CMap::predefined("90msp-RKSJ-H").unwrap();
+        assert_eq!(cmap.cid(0x41), Some(34));
+        assert_eq!(cmap.cid(0x889F), Some(1125));
+        assert_eq!(CMap::predefined("EUC-H").unwrap().cid(0xB0A1), Some(1125));
+        assert_eq!(CMap::predefined("H").unwrap().cid(0x3021), Some(1125));
+        let cmap = CMap::predefined("UniJIS-UTF16-H").unwrap();
+        assert_eq!(cmap.cid(0x4E9C), Some(1125));
+        assert_eq!(cmap.cid(0x41), Some(34));
+        assert_eq!(cmap.cid(0xD835DC9C), None);
+        let cmap = CMap::predefined("GBK-EUC-H").unwrap();
+        assert_eq!(cmap.cid(0xB0A1), None);
+    }
+}
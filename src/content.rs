@@ -0,0 +1,283 @@
+use super::*;
+use std::io::Cursor;
+
+// PDF 32000-1 Annex A, Table A.1
+const CONTENT_OPERATORS: [&str; 73] = [
+    "b", "B", "b*", "B*", "BDC", "BI", "BMC", "BT", "BX", "c", "cm", "CS", "cs", "d", "d0", "d1",
+    "Do", "DP", "EI", "EMC", "ET", "EX", "f", "F", "f*", "G", "g", "gs", "h", "i", "ID", "j", "J",
+    "K", "k", "l", "m", "M", "MP", "n", "q", "Q", "re", "RG", "rg", "ri", "s", "S", "SC", "sc",
+    "SCN", "scn", "sh", "T*", "Tc", "Td", "TD", "Tf", "Tj", "TJ", "TL", "Tm", "Tr", "Ts", "Tw",
+    "Tz", "v", "w", "W", "W*", "y", "'", "\"",
+];
+
+/// An operator of a content stream with the operands preceding it.
+#[derive(Debug, Clone)]
+pub struct Operation {
+    pub operator: String,
+    pub operands: Vec<Value>,
+}
+
+impl Operation {
+    /// Whether the operator is one of the content stream operators of the spec.
+    pub fn is_known(&self) -> bool {
+        CONTENT_OPERATORS.contains(&self.operator.as_str())
+    }
+}
+
+/// An element of a PostScript calculator program (Type 4 function).
+#[derive(Debug, Clone)]
+pub enum PsObject {
+    INTEGER(i32),
+    REAL(f64),
+    BOOL(bool),
+    OPERATOR(String),
+    /// A { } block, the operand of if and ifelse.
+    PROC(Vec<PsObject>),
+}
+
+/// Splits content stream data into operations. An inline image comes out as a single
+/// BI operation whose operand is a stream: the image dictionary, keys as written, and
+/// the data found between ID and EI.
+pub fn parse_content(data: &[u8]) -> Vec<Operation> {
+    let mut reader = BufReader::new(Cursor::new(data));
+    let mut ops = Vec::new();
+    let mut operands = Vec::new();
+    while let Some(token) = next_token(&mut reader, data.len()) {
+        match token {
+            Token::KEYWORD(op) if op == "BI" => {
+                operands.clear();
+                match read_inline_image(&mut reader, data) {
+                    Some(image) => ops.push(Operation {
+                        operator: op,
+                        operands: vec![Value::STREAM(image)],
+                    }),
+                    None => break,
+                }
+            }
+            // braces only belong in calculator functions
+            Token::KEYWORD(op) if op == "{" || op == "}" => (),
+            Token::KEYWORD(op) => ops.push(Operation {
+                operator: op,
+                operands: std::mem::take(&mut operands),
+            }),
+            token => {
+                if let Some(v) = read_operand(&mut reader, data.len(), token, 0) {
+                    operands.push(v);
+                }
+            }
+        }
+    }
+    ops
+}
+
+/// Parses the program of a Type 4 function, `{ ... }`.
+pub fn parse_ps_program(data: &[u8]) -> io::Result<Vec<PsObject>> {
+    fn read_proc(
+        reader: &mut BufReader<Cursor<&[u8]>>,
+        len: usize,
+        depth: i32,
+    ) -> Option<Vec<PsObject>> {
+        let mut items = Vec::new();
+        if depth > 64 {
+            return None;
+        }
+        loop {
+            let item = match next_token(reader, len)? {
+                Token::KEYWORD(k) if k == "}" => return Some(items),
+                Token::KEYWORD(k) if k == "{" => PsObject::PROC(read_proc(reader, len, depth + 1)?),
+                Token::KEYWORD(k) => PsObject::OPERATOR(k),
+                Token::INTEGER(n) => PsObject::INTEGER(n),
+                Token::FLOAT(f) => PsObject::REAL(f),
+                Token::BOOL(b) => PsObject::BOOL(b),
+                _ => return None,
+            };
+            items.push(item);
+        }
+    }
+    let mut reader = BufReader::new(Cursor::new(data));
+    match next_token(&mut reader, data.len()) {
+        Some(Token::KEYWORD(k)) if k == "{" => read_proc(&mut reader, data.len(), 0),
+        _ => None,
+    }
+    .ok_or_else(|| Error::new(ErrorKind::Other, "bad calculator function"))
+}
+
+impl Pdf {
+    /// The operations of the content streams of page `no`.
+    pub fn page_operations(&self, no: i32) -> io::Result<Vec<Operation>> {
+        match self.page_id(no) {
+            Some(id) => Ok(parse_content(&self.page_contents(id))),
+            None => Err(Error::new(ErrorKind::Other, "page out of range")),
+        }
+    }
+}
+
+// the next token before the end of data, lexer errors (a stray ')', a malformed
+// number) are skipped
+pub(crate) fn next_token(reader: &mut BufReader<Cursor<&[u8]>>, len: usize) -> Option<Token> {
+    loop {
+        let pos = match reader.stream_position() {
+            Ok(pos) if pos < len as u64 => pos,
+            _ => return None,
+        };
+        match read_token(reader) {
+            Token::ERROR(_) => {
+                if reader.stream_position().map_or(true, |p| p <= pos) {
+                    return None;
+                }
+            }
+            token => return Some(token),
+        }
+    }
+}
+
+// an operand starting with `token`, arrays and dictionaries read to their end
+fn read_operand(
+    reader: &mut BufReader<Cursor<&[u8]>>,
+    len: usize,
+    token: Token,
+    depth: i32,
+) -> Option<Value> {
+    if depth > 32 {
+        return None;
+    }
+    match token {
+        Token::INTEGER(n) => Some(Value::INTEGER(n)),
+        Token::FLOAT(f) => Some(Value::FLOAT(f)),
+        Token::BOOL(b) => Some(Value::BOOL(b)),
+        Token::NULL => Some(Value::NULL),
+        Token::NAME(n) => Some(Value::NAME(n)),
+        Token::STRING(s) => Some(Value::STRING(s)),
+        Token::ARRAY_BEGIN => {
+            let mut items = Vec::new();
+            loop {
+                match next_token(reader, len)? {
+                    Token::ARRAY_END => return Some(Value::ARRAY(items)),
+                    token => items.extend(read_operand(reader, len, token, depth + 1)),
+                }
+            }
+        }
+        Token::DICT_BEGIN => {
+            let mut dict = Dict::new();
+            loop {
+                match next_token(reader, len)? {
+                    Token::DICT_END => return Some(Value::DICT(dict)),
+                    Token::NAME(key) => {
+                        let token = next_token(reader, len)?;
+                        if let Some(v) = read_operand(reader, len, token, depth + 1) {
+                            dict.push(key, v);
+                        }
+                    }
+                    _ => (),
+                }
+            }
+        }
+        _ => None,
+    }
+}
+
+// BI <key value pairs> ID <data> EI, the reader is past BI
+fn read_inline_image(reader: &mut BufReader<Cursor<&[u8]>>, data: &[u8]) -> Option<Stream> {
+    let mut dict = Dict::new();
+    loop {
+        match next_token(reader, data.len())? {
+            Token::KEYWORD(k) if k == "ID" => break,
+            Token::NAME(key) => {
+                let token = next_token(reader, data.len())?;
+                if let Some(v) = read_operand(reader, data.len(), token, 1) {
+                    dict.push(key, v);
+                }
+            }
+            _ => (),
+        }
+    }
+    // the single white space separating ID from the data was read with the keyword
+    let start = (reader.stream_position().ok()? as usize).min(data.len());
+    let length = ["L", "Length"]
+        .iter()
+        .find_map(|k| dict.get(k).and_then(|v| v.as_int()))
+        .filter(|n| *n >= 0 && start + *n as usize <= data.len());
+    let (end, next) = match length {
+        Some(n) => {
+            let end = start + n as usize;
+            let ei = data[end..].windows(2).position(|w| w == b"EI");
+            (end, ei.map_or(data.len(), |i| end + i + 2))
+        }
+        // EI between white space, or at the end of the data
+        None => {
+            let found = data[start..].windows(3).enumerate().find(|(i, w)| {
+                is_white(w[0])
+                    && &w[1..] == b"EI"
+                    && data.get(start + i + 3).map_or(true, |c| is_white(*c) || is_delimiter(*c))
+            });
+            match found {
+                Some((i, _)) => (start + i, start + i + 3),
+                None => (data.len(), data.len()),
+            }
+        }
+    };
+    reader.seek(SeekFrom::Start(next as u64)).ok()?;
+    Some(Stream {
+        dict,
+        data: data[start..end].to_vec(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn operations() {
+        let ops = parse_content(b"q 1 0 0 1 72.5 -3 cm /F1 12 Tf [(a) -250 (b)] TJ ) \
+            /P << /MCID 0 /Nested << /A [1 2] >> >> BDC { foo Q");
+        let names: Vec<&str> = ops.iter().map(|o| o.operator.as_str()).collect();
+        assert_eq!(names, ["q", "cm", "Tf", "TJ", "BDC", "foo", "Q"]);
+        assert!(ops[1].is_known() && !ops[5].is_known());
+        assert!(matches!(ops[1].operands[4], Value::FLOAT(f) if f == 72.5));
+        assert!(matches!(ops[1].operands[5], Value::INTEGER(-3)));
+        assert!(matches!(&ops[2].operands[0], Value::NAME(n) if n == "F1"));
+        assert_eq!(ops[3].operands[0].as_array().map(|a| a.len()), Some(3));
+        let props = ops[4].operands[1].as_dict().unwrap();
+        assert_eq!(props.get("MCID").and_then(|v| v.as_int()), Some(0));
+        assert!(props.get("Nested").and_then(|v| v.as_dict()).is_some());
+        // an unterminated array ends the stream
+        let ops = parse_content(b"q [1 2");
+        assert_eq!(ops.len(), 1);
+    }
+
+    #[test]
+    fn inline_images() {
+        let ops = parse_content(b"BI /W 2 /H 1 /BPC 8 /CS /G ID \x00EI\xff EI Q");
+        assert_eq!(ops.len(), 2);
+        let image = ops[0].operands[0].as_stream().unwrap();
+        assert_eq!(image.dict.get("W").and_then(|v| v.as_int()), Some(2));
+        assert_eq!(image.data, b"\x00EI\xff");
+        assert_eq!(ops[1].operator, "Q");
+        // a /L length wins over scanning for EI
+        let ops = parse_content(b"BI /L 4 ID a EIbEI n");
+        assert_eq!(ops[0].operands[0].as_stream().unwrap().data, b"a EI");
+        assert_eq!(ops[1].operator, "n");
+        let ops = parse_content(b"BI /W 1 ID \x01\x02");
+        assert_eq!(ops[0].operands[0].as_stream().unwrap().data, b"\x01\x02");
+        assert!(parse_content(b"BI /W 1").is_empty());
+    }
+
+    #[test]
+    fn ps_program() {
+        let program = parse_ps_program(b"{ 2 index 0.5 gt { pop true } { false } ifelse }");
+        let program = program.unwrap();
+        assert_eq!(program.len(), 7);
+        assert!(matches!(program[0], PsObject::INTEGER(2)));
+        assert!(matches!(&program[1], PsObject::OPERATOR(o) if o == "index"));
+        assert!(matches!(program[2], PsObject::REAL(r) if r == 0.5));
+        assert!(matches!(&program[4], PsObject::PROC(p) if p.len() == 2));
+        assert!(matches!(&program[5], PsObject::PROC(p) if p.len() == 1));
+        assert!(matches!(&program[6], PsObject::OPERATOR(o) if o == "ifelse"));
+        assert!(parse_ps_program(b"2 add").is_err());
+        assert!(parse_ps_program(b"{ 1 (s) }").is_err());
+        assert!(parse_ps_program(b"{ 1 { 2 }").is_err());
+        let nested = format!("{}{}", "{".repeat(100), "}".repeat(100));
+        assert!(parse_ps_program(nested.as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,217 @@
+use super::*;
+
+const PDF_NAME_Dests: &str = "Dests";
+const PDF_NAME_Names: &str = "Names";
+
+/// How the target page is displayed, coordinates are in default user space.
+/// None means "keep the current value".
+#[derive(Debug, Clone, PartialEq)]
+pub enum DestView {
+    XYZ {
+        left: Option<f64>,
+        top: Option<f64>,
+        zoom: Option<f64>,
+    },
+    FIT,
+    FIT_H {
+        top: Option<f64>,
+    },
+    FIT_V {
+        left: Option<f64>,
+    },
+    FIT_R {
+        left: f64,
+        bottom: f64,
+        right: f64,
+        top: f64,
+    },
+    FIT_B,
+    FIT_BH {
+        top: Option<f64>,
+    },
+    FIT_BV {
+        left: Option<f64>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Destination {
+    /// Zero based page index. For remote destinations this is the page number
+    /// in the other file; None when the page reference could not be resolved.
+    pub page: Option<i32>,
+    pub view: DestView,
+}
+
+impl Destination {
+    // [page /XYZ left top zoom] etc.
+    pub(crate) fn from_array(pdf: &Pdf, array: &[Value]) -> Option<Destination> {
+        let page = match array.first()? {
+            Value::REF(n0, _) => pdf.page_index(*n0),
+            Value::INTEGER(n) => Some(*n),
+            _ => None,
+        };
+        let num = |i: usize| array.get(i).map(|v| pdf.resolve(v)).and_then(|v| v.as_f64());
+        let view = match pdf.resolve(array.get(1)?).as_name()? {
+            "XYZ" => DestView::XYZ {
+                left: num(2),
+                top: num(3),
+                // 0 and null both mean unchanged
+                zoom: num(4).filter(|z| *z != 0.0),
+            },
+            "Fit" => DestView::FIT,
+            "FitH" => DestView::FIT_H { top: num(2) },
+            "FitV" => DestView::FIT_V { left: num(2) },
+            "FitR" => DestView::FIT_R {
+                left: num(2).unwrap_or(0.0),
+                bottom: num(3).unwrap_or(0.0),
+                right: num(4).unwrap_or(0.0),
+                top: num(5).unwrap_or(0.0),
+            },
+            "FitB" => DestView::FIT_B,
+            "FitBH" => DestView::FIT_BH { top: num(2) },
+            "FitBV" => DestView::FIT_BV { left: num(2) },
+            _ => return None,
+        };
+        Some(Destination { page, view })
+    }
+}
+
+impl Pdf {
+    /// Index of the page whose dictionary is object `id`.
+    pub fn page_index(&self, id: i32) -> Option<i32> {
+        self.pages.iter().position(|p| *p == id).map(|i| i as i32)
+    }
+    /// Resolves any form of destination: an explicit array, a dictionary with /D,
+    /// a name in the catalog /Dests dictionary or a string in the /Names /Dests tree.
+    pub fn destination(&self, val: &Value) -> Option<Destination> {
+        self.destination_at_depth(val, 0)
+    }
+    fn destination_at_depth(&self, val: &Value, depth: i32) -> Option<Destination> {
+        // a named destination may point to another name
+        if depth > 8 {
+            return None;
+        }
+        let target = match self.resolve(val) {
+            Value::ARRAY(array) => return Destination::from_array(self, array),
+            Value::DICT(dict) => dict.get("D")?,
+            Value::NAME(name) => self
+                .lookup_dests_dict(name.as_bytes())
+                .or_else(|| self.lookup_dests_tree(name.as_bytes()))?,
+            Value::STRING(name) => self
+                .lookup_dests_tree(name)
+                .or_else(|| self.lookup_dests_dict(name))?,
+            _ => return None,
+        };
+        self.destination_at_depth(target, depth + 1)
+    }
+    /// Looks up a named destination by name.
+    pub fn named_destination(&self, name: &[u8]) -> Option<Destination> {
+        self.destination(&Value::STRING(name.to_vec()))
+    }
+    /// All named destinations from the /Dests dictionary and the /Names /Dests tree.
+    pub fn named_destinations(&self) -> Vec<(String, Destination)> {
+        let mut dests = Vec::new();
+        if let Some(dict) = self.dests_dict() {
+            for (key, val) in dict.iter() {
+                if let Some(d) = self.destination(val) {
+                    dests.push((key.to_string(), d));
+                }
+            }
+        }
+        if let Some(root) = self.dests_tree() {
+            for (key, val) in self.name_tree(root) {
+                if let Some(d) = self.destination(val) {
+                    dests.push((decode_text(&key), d));
+                }
+            }
+        }
+        dests
+    }
+    // PDF 1.1 style catalog /Dests dictionary
+    fn dests_dict(&self) -> Option<&Dict> {
+        let catalog = self.catalog()?;
+        self.lookup(catalog, PDF_NAME_Dests)?.as_dict()
+    }
+    fn dests_tree(&self) -> Option<&Value> {
+        let catalog = self.catalog()?;
+        let names = self.lookup(catalog, PDF_NAME_Names)?.as_dict()?;
+        self.lookup(names, PDF_NAME_Dests)
+    }
+    fn lookup_dests_dict(&self, name: &[u8]) -> Option<&Value> {
+        let dict = self.dests_dict()?;
+        self.lookup(dict, str::from_utf8(name).ok()?)
+    }
+    fn lookup_dests_tree(&self, name: &[u8]) -> Option<&Value> {
+        self.name_tree_lookup(self.dests_tree()?, name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // destinations in the PDF 1.1 /Dests dictionary and in the /Names tree,
+    // including names pointing to each other
+    const DESTS: &str = r"
+1 0 obj << /Type /Catalog /Pages 2 0 R /Dests 5 0 R /Names << /Dests 6 0 R >> >> endobj
+2 0 obj << /Type /Pages /Kids [3 0 R 4 0 R] /Count 2 >> endobj
+3 0 obj << /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] >> endobj
+4 0 obj << /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] >> endobj
+5 0 obj << /intro [3 0 R /XYZ 0 792 0] /alias (chapter) /loop /loop >> endobj
+6 0 obj << /Names [(chapter) << /D [4 0 R /FitH 700] >> (remote) [7 /FitR 1 2 3 4]] >> endobj
+";
+
+    #[test]
+    fn destinations() {
+        let pdf = test_pdf(DESTS);
+        let xyz = DestView::XYZ {
+            left: Some(0.0),
+            top: Some(792.0),
+            zoom: None,
+        };
+        let fit_h = DestView::FIT_H { top: Some(700.0) };
+        assert_eq!(
+            pdf.destination(&Value::NAME("intro".to_string())),
+            Some(Destination { page: Some(0), view: xyz })
+        );
+        let chapter = Some(Destination { page: Some(1), view: fit_h });
+        assert_eq!(pdf.named_destination(b"chapter"), chapter);
+        assert_eq!(pdf.destination(&Value::NAME("alias".to_string())), chapter);
+        assert_eq!(
+            pdf.named_destination(b"remote").map(|d| d.page),
+            Some(Some(7))
+        );
+        assert_eq!(pdf.destination(&Value::NAME("loop".to_string())), None);
+        assert_eq!(pdf.named_destination(b"missing"), None);
+        let mut names: Vec<String> = pdf.named_destinations().into_iter().map(|d| d.0).collect();
+        names.sort();
+        assert_eq!(names, ["alias", "chapter", "intro", "remote"]);
+    }
+
+    #[test]
+    fn views() {
+        let pdf = test_pdf(DESTS);
+        let view = |array: &str| {
+            let value = fdf::read_fdf_objects(format!("1 0 obj {} endobj", array).as_bytes())
+                .into_iter()
+                .next()
+                .map(|(_, v)| v)?;
+            Destination::from_array(&pdf, value.as_array()?).map(|d| d.view)
+        };
+        assert_eq!(view("[0 /Fit]"), Some(DestView::FIT));
+        assert_eq!(view("[0 /FitB]"), Some(DestView::FIT_B));
+        assert_eq!(view("[0 /FitV null]"), Some(DestView::FIT_V { left: None }));
+        assert_eq!(view("[0 /FitBH 5]"), Some(DestView::FIT_BH { top: Some(5.0) }));
+        assert_eq!(view("[0 /FitBV 5]"), Some(DestView::FIT_BV { left: Some(5.0) }));
+        assert_eq!(
+            view("[0 /XYZ null 10 2]"),
+            Some(DestView::XYZ {
+                left: None,
+                top: Some(10.0),
+                zoom: Some(2.0)
+            })
+        );
+        assert_eq!(view("[0 /Zoom]"), None);
+        assert_eq!(view("[]"), None);
+    }
+}
@@ -0,0 +1,287 @@
+use super::*;
+use std::io::Cursor;
+
+const PDF_NAME_FDF: &str = "FDF";
+const PDF_NAME_Fields: &str = "Fields";
+const PDF_NAME_F: &str = "F";
+const PDF_NAME_T: &str = "T";
+const PDF_NAME_V: &str = "V";
+
+// exported field values arranged by their partial names
+pub(crate) struct FieldNode {
+    pub(crate) name: String,
+    pub(crate) value: Option<FieldValue>,
+    pub(crate) kids: Vec<FieldNode>,
+}
+
+impl FieldNode {
+    fn insert(nodes: &mut Vec<FieldNode>, path: &[&str], value: FieldValue) {
+        let (first, rest) = match path.split_first() {
+            Some(split) => split,
+            None => return,
+        };
+        let i = match nodes.iter().position(|n| n.name == *first) {
+            Some(i) => i,
+            None => {
+                nodes.push(FieldNode {
+                    name: first.to_string(),
+                    value: None,
+                    kids: Vec::new(),
+                });
+                nodes.len() - 1
+            }
+        };
+        if rest.is_empty() {
+            nodes[i].value = Some(value);
+        } else {
+            FieldNode::insert(&mut nodes[i].kids, rest, value);
+        }
+    }
+}
+
+impl Pdf {
+    // field values worth exchanging, as a tree of partial names
+    pub(crate) fn field_value_tree(&self) -> Vec<FieldNode> {
+        let mut nodes = Vec::new();
+        for field in self.form_fields() {
+            let skip = matches!(field.field_type, FieldType::PUSH_BUTTON | FieldType::SIGNATURE)
+                || field.has_flag(FIELD_FLAG_NO_EXPORT)
+                || field.name.is_empty();
+            if !skip {
+                let path: Vec<&str> = field.name.split('.').collect();
+                FieldNode::insert(&mut nodes, &path, field.value.clone());
+            }
+        }
+        nodes
+    }
+    /// Form field values as an FDF file, `file` names the PDF the data belongs to.
+    pub fn export_fdf(&self, file: Option<&str>) -> Vec<u8> {
+        fn to_dict(node: &FieldNode) -> Value {
+            let mut dict = Dict::new();
+            dict.push(PDF_NAME_T.to_string(), Value::STRING(encode_text(&node.name)));
+            if let Some(v) = node.value.as_ref().and_then(|v| v.to_value()) {
+                dict.push(PDF_NAME_V.to_string(), v);
+            }
+            if !node.kids.is_empty() {
+                dict.push(
+                    PDF_NAME_Kids.to_string(),
+                    Value::ARRAY(node.kids.iter().map(to_dict).collect()),
+                );
+            }
+            Value::DICT(dict)
+        }
+        let mut fdf = Dict::new();
+        fdf.push(
+            PDF_NAME_Fields.to_string(),
+            Value::ARRAY(self.field_value_tree().iter().map(to_dict).collect()),
+        );
+        if let Some(file) = file {
+            fdf.push(PDF_NAME_F.to_string(), Value::STRING(encode_text(file)));
+        }
+        let mut catalog = Dict::new();
+        catalog.push(PDF_NAME_FDF.to_string(), Value::DICT(fdf));
+        let mut out = b"%FDF-1.2\n%\xe2\xe3\xcf\xd3\n1 0 obj\n".to_vec();
+        write_value(&mut out, &Value::DICT(catalog));
+        out.extend_from_slice(b"\nendobj\ntrailer\n<</Root 1 0 R>>\n%%EOF\n");
+        out
+    }
+    /// Sets the fields listed in an FDF file. Fields missing from the document or
+    /// holding a value of another type are skipped.
+    pub fn import_fdf(&mut self, data: &[u8]) -> io::Result<()> {
+        if !data.starts_with(b"%FDF-") {
+            return Err(Error::new(ErrorKind::Other, "not a FDF file"));
+        }
+        let objects = read_fdf_objects(data);
+        let resolve = |val: &Value| -> Value {
+            let mut val = val.clone();
+            for _ in 0..32 {
+                match val {
+                    Value::REF(n0, _) => val = objects.get(&n0).cloned().unwrap_or(Value::NULL),
+                    _ => break,
+                }
+            }
+            val
+        };
+        let mut ids: Vec<&i32> = objects.keys().collect();
+        ids.sort_unstable();
+        let fdf = ids
+            .into_iter()
+            .filter_map(|id| objects[id].as_dict())
+            .find_map(|dict| dict.get(PDF_NAME_FDF))
+            .map(&resolve)
+            .ok_or_else(|| Error::new(ErrorKind::Other, "FDF dictionary not found"))?;
+        let mut values = Vec::new();
+        let mut stack: Vec<(Value, Option<String>, i32)> = fdf
+            .as_dict()
+            .and_then(|d| d.get(PDF_NAME_Fields))
+            .map(&resolve)
+            .and_then(|v| v.as_array().cloned())
+            .unwrap_or_default()
+            .into_iter()
+            .rev()
+            .map(|v| (v, None, 0))
+            .collect();
+        while let Some((node, prefix, depth)) = stack.pop() {
+            let node = resolve(&node);
+            let dict = match node.as_dict() {
+                Some(dict) => dict,
+                None => continue,
+            };
+            let name = match (dict.get(PDF_NAME_T).and_then(|v| resolve(v).as_text()), prefix) {
+                (Some(t), Some(p)) => Some(format!("{}.{}", p, t)),
+                (Some(t), None) => Some(t),
+                (None, p) => p,
+            };
+            if let (Some(name), Some(v)) = (&name, dict.get(PDF_NAME_V)) {
+                let v = match resolve(v) {
+                    Value::ARRAY(items) => Value::ARRAY(items.iter().map(&resolve).collect()),
+                    v => v,
+                };
+                values.push((name.clone(), FieldValue::from_value(&v)));
+            }
+            if depth < 32 {
+                let kids = dict
+                    .get(PDF_NAME_Kids)
+                    .map(&resolve)
+                    .and_then(|v| v.as_array().cloned())
+                    .unwrap_or_default();
+                for kid in kids.into_iter().rev() {
+                    stack.push((kid, name.clone(), depth + 1));
+                }
+            }
+        }
+        self.import_values(values)
+    }
+    // applies imported values, converting between text and button states as needed
+    pub(crate) fn import_values(&mut self, values: Vec<(String, FieldValue)>) -> io::Result<()> {
+        let fields: HashMap<String, FormField> = self
+            .form_fields()
+            .into_iter()
+            .map(|f| (f.name.clone(), f))
+            .collect();
+        for (name, value) in values {
+            let field = match fields.get(&name) {
+                Some(field) => field,
+                None => continue,
+            };
+            let multi = field.has_flag(FIELD_FLAG_MULTI_SELECT);
+            let value = match (field.field_type, value) {
+                (FieldType::CHECKBOX, FieldValue::TEXT(s)) | (FieldType::RADIO, FieldValue::TEXT(s)) => {
+                    FieldValue::STATE(s)
+                }
+                (FieldType::TEXT, FieldValue::STATE(s))
+                | (FieldType::COMBO, FieldValue::STATE(s))
+                | (FieldType::LIST, FieldValue::STATE(s)) => FieldValue::TEXT(s),
+                (FieldType::LIST, FieldValue::LIST(mut items)) if !multi && items.len() == 1 => {
+                    FieldValue::TEXT(items.remove(0))
+                }
+                (_, value) => value,
+            };
+            if let Ok(value) = field.check_value(value) {
+                self.apply_field_value(field.clone(), value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FieldValue {
+    // value of an exported /V entry, array items already resolved
+    pub(crate) fn from_value(val: &Value) -> FieldValue {
+        match val {
+            Value::STRING(s) => FieldValue::TEXT(decode_text(s)),
+            Value::NAME(n) => FieldValue::STATE(n.clone()),
+            Value::STREAM(s) => FieldValue::TEXT(decode_text(&s.data)),
+            Value::ARRAY(items) => FieldValue::LIST(items.iter().filter_map(|v| v.as_text()).collect()),
+            _ => FieldValue::NONE,
+        }
+    }
+}
+
+// all "n g obj ... endobj" bodies of a FDF file by object number
+pub(crate) fn read_fdf_objects(data: &[u8]) -> HashMap<i32, Value> {
+    let mut reader = BufReader::new(Cursor::new(data));
+    let mut objects = HashMap::new();
+    // start position and integer value of the last two tokens
+    let mut recent: Vec<(u64, Option<i32>)> = Vec::new();
+    loop {
+        let pos = match reader.stream_position() {
+            Ok(pos) if pos < data.len() as u64 => pos,
+            _ => break,
+        };
+        let token = read_token(&mut reader);
+        match token {
+            Token::OBJ_BEGIN if recent.len() == 2 => {
+                if let (Some(id), Some(_)) = (recent[0].1, recent[1].1) {
+                    if reader.seek(SeekFrom::Start(recent[0].0)).is_err() {
+                        break;
+                    }
+                    let mut obj = Obj::new(id, recent[0].0 as i32, 0, true);
+                    if read_object(&mut reader, &mut obj).is_ok() {
+                        if let Some(value) = obj.value {
+                            objects.insert(id, value);
+                        }
+                    }
+                }
+                recent.clear();
+                continue;
+            }
+            Token::INTEGER(n) => recent.push((pos, Some(n))),
+            _ => recent.push((pos, None)),
+        }
+        if recent.len() > 2 {
+            recent.remove(0);
+        }
+    }
+    objects
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fdf(body: &str) -> Vec<u8> {
+        let trailer = "trailer\n<</Root 1 0 R>>\n%%EOF\n";
+        format!("%FDF-1.2\n1 0 obj\n{}\nendobj\n{}", body, trailer).into_bytes()
+    }
+
+    #[test]
+    fn reads_objects() {
+        let objects = read_fdf_objects(&fdf("<< /FDF << /Fields [2 0 R] >> >>"));
+        let fdf = objects[&1].as_dict().and_then(|d| d.get(PDF_NAME_FDF));
+        let fields = fdf.and_then(|v| v.as_dict()).and_then(|d| d.get(PDF_NAME_Fields));
+        assert!(matches!(
+            fields.and_then(|v| v.as_array()).map(|a| &a[..]),
+            Some([Value::REF(2, 0)])
+        ));
+    }
+
+    #[test]
+    fn malformed_reference() {
+        assert!(read_fdf_objects(&fdf("<< /FDF << /Fields [1 R] >> >>")).is_empty());
+        assert!(read_fdf_objects(&fdf("[R]")).is_empty());
+        assert!(Pdf::new().import_fdf(&fdf("<< /FDF << /Fields [1 R] >> >>")).is_err());
+    }
+
+    #[test]
+    fn dict_end_in_array() {
+        assert!(read_fdf_objects(&fdf("<< /FDF << /Fields [1 >> ] >> >>")).is_empty());
+        assert!(read_fdf_objects(&fdf("[ >> ]")).is_empty());
+    }
+
+    #[test]
+    fn bad_stream_length() {
+        let data = fdf("<< /Length -1 >>\nstream\nabc\nendstream");
+        assert!(read_fdf_objects(&data).is_empty());
+        let data = fdf("<< /Length 2147483647 >>\nstream\nabc\nendstream");
+        assert!(read_fdf_objects(&data).is_empty());
+        let data = fdf("<< /Length 3 >>\nstream\nabc\nendstream");
+        assert_eq!(read_fdf_objects(&data)[&1].as_stream().map(|s| &s.data[..]), Some(&b"abc"[..]));
+    }
+
+    #[test]
+    fn truncated() {
+        assert!(read_fdf_objects(b"%FDF-1.2\n1 0 obj\n<< /FDF [1 0").is_empty());
+        assert!(read_fdf_objects(b"%FDF-1.2\n1 0 obj\n<< /FDF << /T (a").is_empty());
+    }
+}
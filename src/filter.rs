@@ -3,15 +3,390 @@ use std::io;
 use std::io::{Error, ErrorKind};
 
 extern crate inflate;
-pub fn decode_deflate(data :&[u8], name :&String)->io::Result<Vec<u8>>{
-    if name == "FlateDecode"{
-        // if let Ok(d) = inflate::inflate_bytes_zlib(data){
-        //     return Ok(d);
-        // }
-        return inflate::inflate_bytes_zlib(data).map_err(|err|Error::new(ErrorKind::Other,""));
-    }
-    Err(Error::new(ErrorKind::Other,"not support decode name"))
-    // if let Ok(mut decoded) = inflate::inflate_bytes_zlib(&stream.data[..]){
-// 
-    // }
-}
\ No newline at end of file
+
+// /DecodeParms entries used by the predictor functions
+pub struct DecodeParms {
+    pub predictor: i32,
+    pub colors: i32,
+    pub bits_per_component: i32,
+    pub columns: i32,
+    pub early_change: i32,
+}
+impl Default for DecodeParms {
+    fn default() -> Self {
+        DecodeParms {
+            predictor: 1,
+            colors: 1,
+            bits_per_component: 8,
+            columns: 1,
+            early_change: 1,
+        }
+    }
+}
+
+/// Applies one stream filter, `Err` for filters we can't decode (images like DCTDecode).
+pub fn decode(data: &[u8], name: &str, parms: &DecodeParms) -> io::Result<Vec<u8>> {
+    match name {
+        "FlateDecode" | "Fl" => {
+            let decoded = match inflate::inflate_bytes_zlib(data) {
+                Ok(d) => d,
+                // some writers omit the zlib header
+                Err(_) => inflate::inflate_bytes(data)
+                    .map_err(|err| Error::new(ErrorKind::Other, err))?,
+            };
+            unpredict(decoded, parms)
+        }
+        "LZWDecode" | "LZW" => unpredict(decode_lzw(data, parms.early_change)?, parms),
+        "ASCIIHexDecode" | "AHx" => {
+            let end = data.iter().position(|c| *c == b'>').unwrap_or(data.len());
+            Ok(super::parse::decode_hex(&data[..end]))
+        }
+        "ASCII85Decode" | "A85" => decode_ascii85(data),
+        "RunLengthDecode" | "RL" => Ok(decode_run_length(data)),
+        _ => Err(Error::new(
+            ErrorKind::Other,
+            format!("unsupported filter {}", name),
+        )),
+    }
+}
+
+fn decode_ascii85(data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut out = Vec::with_capacity(data.len() * 4 / 5);
+    let mut group: [u32; 5] = [0; 5];
+    let mut n = 0;
+    for &c in data {
+        match c {
+            b'~' => break,
+            b'z' if n == 0 => out.extend_from_slice(&[0, 0, 0, 0]),
+            b'!'..=b'u' => {
+                group[n] = (c - b'!') as u32;
+                n += 1;
+                if n == 5 {
+                    let v = group.iter().fold(0u32, |acc, d| acc.wrapping_mul(85).wrapping_add(*d));
+                    out.extend_from_slice(&v.to_be_bytes());
+                    n = 0;
+                }
+            }
+            c if super::parse::is_white(c) => (),
+            _ => return Err(Error::new(ErrorKind::InvalidData, "bad ASCII85 data")),
+        }
+    }
+    if n > 1 {
+        // partial group, pad with 'u'
+        for d in group.iter_mut().skip(n) {
+            *d = 84;
+        }
+        let v = group.iter().fold(0u32, |acc, d| acc.wrapping_mul(85).wrapping_add(*d));
+        out.extend_from_slice(&v.to_be_bytes()[..n - 1]);
+    }
+    Ok(out)
+}
+
+fn decode_run_length(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < data.len() {
+        let len = data[i] as usize;
+        i += 1;
+        if len < 128 {
+            let end = (i + len + 1).min(data.len());
+            out.extend_from_slice(&data[i..end]);
+            i = end;
+        } else if len > 128 {
+            if i < data.len() {
+                out.extend(std::iter::repeat(data[i]).take(257 - len));
+            }
+            i += 1;
+        } else {
+            break;
+        }
+    }
+    out
+}
+
+fn decode_lzw(data: &[u8], early_change: i32) -> io::Result<Vec<u8>> {
+    let mut out = Vec::new();
+    let mut table: Vec<Vec<u8>> = Vec::new();
+    let reset = |table: &mut Vec<Vec<u8>>| {
+        table.clear();
+        for i in 0..256 {
+            table.push(vec![i as u8]);
+        }
+        // 256 clear table, 257 end of data
+        table.push(Vec::new());
+        table.push(Vec::new());
+    };
+    reset(&mut table);
+    let mut code_len = 9;
+    let mut bits: u32 = 0;
+    let mut nbits = 0;
+    let mut prev: Option<usize> = None;
+    for &b in data {
+        bits = (bits << 8) | b as u32;
+        nbits += 8;
+        while nbits >= code_len {
+            let code = ((bits >> (nbits - code_len)) & ((1 << code_len) - 1)) as usize;
+            nbits -= code_len;
+            if code == 256 {
+                reset(&mut table);
+                code_len = 9;
+                prev = None;
+                continue;
+            }
+            if code == 257 {
+                return Ok(out);
+            }
+            let entry = if code < table.len() {
+                table[code].clone()
+            } else if let Some(p) = prev {
+                let mut e = table[p].clone();
+                e.push(table[p][0]);
+                e
+            } else {
+                return Err(Error::new(ErrorKind::InvalidData, "bad LZW code"));
+            };
+            if let Some(p) = prev {
+                let mut e = table[p].clone();
+                e.push(entry[0]);
+                table.push(e);
+            }
+            out.extend_from_slice(&entry);
+            prev = Some(code);
+            let size = table.len() as i32 + early_change;
+            code_len = match size {
+                s if s >= 2048 => 12,
+                s if s >= 1024 => 11,
+                s if s >= 512 => 10,
+                _ => 9,
+            };
+        }
+    }
+    Ok(out)
+}
+
+// TIFF predictor 2 and the PNG predictors (10..15)
+/// Compresses data into a zlib stream for FlateDecode, one block with the fixed Huffman codes.
+pub fn encode_flate(data: &[u8]) -> Vec<u8> {
+    const LEN_BASE: [usize; 29] = [
+        3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115,
+        131, 163, 195, 227, 258,
+    ];
+    const LEN_EXTRA: [u32; 29] = [
+        0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+    ];
+    const DIST_BASE: [usize; 30] = [
+        1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+        2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+    ];
+    const WINDOW: usize = 32768;
+    const NONE: usize = usize::MAX;
+
+    let mut bits = BitWriter {
+        out: vec![0x78, 0x9c],
+        acc: 0,
+        count: 0,
+    };
+    // final block, fixed codes
+    bits.write(1, 1);
+    bits.write(1, 2);
+    let hash = |i: usize| {
+        ((data[i] as usize) << 10 ^ (data[i + 1] as usize) << 5 ^ data[i + 2] as usize) & 0x7fff
+    };
+    let mut head = vec![NONE; 1 << 15];
+    let mut prev = vec![NONE; data.len()];
+    let mut i = 0;
+    while i < data.len() {
+        // longest earlier match, following a bounded hash chain
+        let (mut best_len, mut best_dist) = (0, 0);
+        if i + 3 <= data.len() {
+            let max = (data.len() - i).min(258);
+            let mut candidate = head[hash(i)];
+            let mut chain = 0;
+            while candidate != NONE && i - candidate <= WINDOW && chain < 64 {
+                let len = (0..max)
+                    .take_while(|k| data[candidate + k] == data[i + k])
+                    .count();
+                if len > best_len {
+                    best_len = len;
+                    best_dist = i - candidate;
+                    if len == max {
+                        break;
+                    }
+                }
+                candidate = prev[candidate];
+                chain += 1;
+            }
+        }
+        let step = if best_len >= 3 {
+            let code = LEN_BASE.iter().rposition(|b| *b <= best_len).unwrap_or(0);
+            bits.symbol(257 + code as u32);
+            bits.write((best_len - LEN_BASE[code]) as u32, LEN_EXTRA[code]);
+            let code = DIST_BASE.iter().rposition(|b| *b <= best_dist).unwrap_or(0);
+            bits.code(code as u32, 5);
+            // distance codes 4 and up carry (code - 2) / 2 extra bits
+            let extra = if code < 4 { 0 } else { (code as u32 - 2) / 2 };
+            bits.write((best_dist - DIST_BASE[code]) as u32, extra);
+            best_len
+        } else {
+            bits.symbol(data[i] as u32);
+            1
+        };
+        for j in (i..i + step).filter(|j| j + 3 <= data.len()) {
+            let h = hash(j);
+            prev[j] = head[h];
+            head[h] = j;
+        }
+        i += step;
+    }
+    // end of block
+    bits.symbol(256);
+    if bits.count > 0 {
+        bits.out.push(bits.acc as u8);
+    }
+    let mut out = bits.out;
+    let (mut a, mut b) = (1u32, 0u32);
+    for &c in data {
+        a = (a + c as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    out.extend_from_slice(&(b << 16 | a).to_be_bytes());
+    out
+}
+
+// deflate packs bits starting from the least significant one
+struct BitWriter {
+    out: Vec<u8>,
+    acc: u32,
+    count: u32,
+}
+impl BitWriter {
+    fn write(&mut self, value: u32, len: u32) {
+        for n in 0..len {
+            self.acc |= (value >> n & 1) << self.count;
+            self.count += 1;
+            if self.count == 8 {
+                self.out.push(self.acc as u8);
+                self.acc = 0;
+                self.count = 0;
+            }
+        }
+    }
+    // huffman codes go most significant bit first
+    fn code(&mut self, code: u32, len: u32) {
+        for n in (0..len).rev() {
+            self.write(code >> n & 1, 1);
+        }
+    }
+    // literal/length symbol in the fixed code
+    fn symbol(&mut self, sym: u32) {
+        match sym {
+            0..=143 => self.code(0x30 + sym, 8),
+            144..=255 => self.code(0x190 + sym - 144, 9),
+            256..=279 => self.code(sym - 256, 7),
+            _ => self.code(0xc0 + sym - 280, 8),
+        }
+    }
+}
+
+fn unpredict(data: Vec<u8>, parms: &DecodeParms) -> io::Result<Vec<u8>> {
+    if parms.predictor < 2 {
+        return Ok(data);
+    }
+    let bpp = ((parms.colors * parms.bits_per_component + 7) / 8).max(1) as usize;
+    let row_len = ((parms.colors * parms.bits_per_component * parms.columns + 7) / 8) as usize;
+    if row_len == 0 {
+        return Ok(data);
+    }
+    if parms.predictor == 2 {
+        let mut out = data;
+        if parms.bits_per_component == 8 {
+            for row in out.chunks_mut(row_len) {
+                for i in bpp..row.len() {
+                    row[i] = row[i].wrapping_add(row[i - bpp]);
+                }
+            }
+        }
+        return Ok(out);
+    }
+    let mut out = Vec::with_capacity(data.len());
+    let mut prev_row = vec![0u8; row_len];
+    for chunk in data.chunks(row_len + 1) {
+        if chunk.len() < 2 {
+            break;
+        }
+        let kind = chunk[0];
+        let mut row = chunk[1..].to_vec();
+        row.resize(row_len, 0);
+        for i in 0..row_len {
+            let left = if i >= bpp { row[i - bpp] } else { 0 };
+            let up = prev_row[i];
+            let up_left = if i >= bpp { prev_row[i - bpp] } else { 0 };
+            row[i] = match kind {
+                0 => row[i],
+                1 => row[i].wrapping_add(left),
+                2 => row[i].wrapping_add(up),
+                3 => row[i].wrapping_add(((left as u16 + up as u16) / 2) as u8),
+                4 => row[i].wrapping_add(paeth(left, up, up_left)),
+                _ => return Err(Error::new(ErrorKind::InvalidData, "bad png predictor")),
+            };
+        }
+        out.extend_from_slice(&row);
+        prev_row = row;
+    }
+    Ok(out)
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let pa = (p - a as i16).abs();
+    let pb = (p - b as i16).abs();
+    let pc = (p - c as i16).abs();
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flate() {
+        let parms = DecodeParms::default();
+        let zlib = [0x78, 0x9c, 0xcb, 0x48, 0xcd, 0xc9, 0xc9, 0x07, 0x00, 0x06, 0x2c, 0x02, 0x15];
+        assert_eq!(decode(&zlib, "FlateDecode", &parms).unwrap(), b"hello");
+        // raw deflate data without the zlib header
+        assert_eq!(decode(&zlib[2..9], "Fl", &parms).unwrap(), b"hello");
+    }
+
+    #[test]
+    fn flate_encode() {
+        let parms = DecodeParms::default();
+        let round_trip = |data: &[u8]| decode(&encode_flate(data), "FlateDecode", &parms).unwrap();
+        let zlib = [0x78, 0x9c, 0xcb, 0x48, 0xcd, 0xc9, 0xc9, 0x07, 0x00, 0x06, 0x2c, 0x02, 0x15];
+        assert_eq!(encode_flate(b"hello"), zlib);
+        assert!(round_trip(b"").is_empty());
+        let text: Vec<u8> = (0..2000)
+            .flat_map(|i| format!("{} 0 Td (row) Tj\n", i % 37).into_bytes())
+            .collect();
+        assert_eq!(round_trip(&text), text);
+        assert!(encode_flate(&text).len() < text.len() / 4);
+        // long runs, overlapping matches and every byte value
+        let mut mixed = vec![0u8; 70000];
+        mixed.extend((0..=255u8).cycle().take(5000));
+        mixed.extend((0..40000u32).map(|i| (i.wrapping_mul(2654435761) >> 24) as u8));
+        assert_eq!(round_trip(&mixed), mixed);
+    }
+
+    #[test]
+    fn flate_error_message() {
+        let err = decode(&[0xff; 8], "FlateDecode", &DecodeParms::default()).unwrap_err();
+        assert!(!err.to_string().is_empty());
+        let err = decode(b"", "DCTDecode", &DecodeParms::default()).unwrap_err();
+        assert_eq!(err.to_string(), "unsupported filter DCTDecode");
+    }
+}
@@ -0,0 +1,989 @@
+use super::*;
+use std::collections::HashSet;
+
+const PDF_NAME_Subtype: &str = "Subtype";
+const PDF_NAME_BaseFont: &str = "BaseFont";
+const PDF_NAME_FirstChar: &str = "FirstChar";
+const PDF_NAME_Widths: &str = "Widths";
+const PDF_NAME_FontDescriptor: &str = "FontDescriptor";
+const PDF_NAME_MissingWidth: &str = "MissingWidth";
+const PDF_NAME_Ascent: &str = "Ascent";
+const PDF_NAME_Descent: &str = "Descent";
+const PDF_NAME_FontMatrix: &str = "FontMatrix";
+const PDF_NAME_FontBBox: &str = "FontBBox";
+const PDF_NAME_Encoding: &str = "Encoding";
+const PDF_NAME_BaseEncoding: &str = "BaseEncoding";
+const PDF_NAME_Differences: &str = "Differences";
+const PDF_NAME_ToUnicode: &str = "ToUnicode";
+const PDF_NAME_DescendantFonts: &str = "DescendantFonts";
+const PDF_NAME_UseCMap: &str = "UseCMap";
+const PDF_NAME_WMode: &str = "WMode";
+const PDF_NAME_W: &str = "W";
+const PDF_NAME_W2: &str = "W2";
+const PDF_NAME_DW: &str = "DW";
+const PDF_NAME_DW2: &str = "DW2";
+const PDF_NAME_CIDToGIDMap: &str = "CIDToGIDMap";
+const PDF_NAME_CIDSystemInfo: &str = "CIDSystemInfo";
+const PDF_NAME_Ordering: &str = "Ordering";
+const PDF_NAME_FontFile: &str = "FontFile";
+const PDF_NAME_FontFile2: &str = "FontFile2";
+const PDF_NAME_FontFile3: &str = "FontFile3";
+const PDF_NAME_FontName: &str = "FontName";
+const PDF_NAME_FontFamily: &str = "FontFamily";
+const PDF_NAME_FontWeight: &str = "FontWeight";
+const PDF_NAME_Flags: &str = "Flags";
+const PDF_NAME_ItalicAngle: &str = "ItalicAngle";
+const PDF_NAME_Leading: &str = "Leading";
+const PDF_NAME_CapHeight: &str = "CapHeight";
+const PDF_NAME_XHeight: &str = "XHeight";
+const PDF_NAME_StemV: &str = "StemV";
+const PDF_NAME_StemH: &str = "StemH";
+const PDF_NAME_AvgWidth: &str = "AvgWidth";
+const PDF_NAME_MaxWidth: &str = "MaxWidth";
+const PDF_NAME_Length1: &str = "Length1";
+const PDF_NAME_Length2: &str = "Length2";
+const PDF_NAME_Length3: &str = "Length3";
+const PDF_NAME_Resources: &str = "Resources";
+const PDF_NAME_Font: &str = "Font";
+const PDF_NAME_XObject: &str = "XObject";
+
+/// A font resource as text extraction sees it: how to split strings into codes, the
+/// Unicode text and width of each code.
+#[derive(Debug, Clone)]
+pub struct Font {
+    /// The font dictionary, None for a direct object.
+    pub id: Option<ObjectId>,
+    /// /Subtype: Type1, MMType1, TrueType, Type3 or Type0.
+    pub subtype: String,
+    /// /BaseFont, with the subset tag if any.
+    pub base_font: String,
+    // glyph names by code of a simple font, "" where undefined
+    glyph_names: Vec<String>,
+    dingbats: bool,
+    to_unicode: Option<CMap>,
+    // the encoding of a Type 0 font, codes to CIDs
+    cmap: Option<CMap>,
+    first_char: i32,
+    // glyph space widths from /Widths
+    widths: Vec<f64>,
+    // AFM metrics of a standard font without /Widths
+    standard: Option<&'static StandardFont>,
+    // /MissingWidth, /DW of a CIDFont
+    missing_width: f64,
+    // /W and /W2 of a CIDFont: first and last CID of each range, sorted
+    cid_widths: Vec<(u32, u32, f64)>,
+    cid_vertical: Vec<(u32, u32, [f64; 3])>,
+    // /DW2, vy and w1y
+    vertical_default: [f64; 2],
+    // from the cmap table of an embedded TrueType program
+    cid_unicode: HashMap<u32, char>,
+    // the CIDs are those of the Adobe-Japan1 collection
+    japan1: bool,
+    // glyph space to text space, 1/1000 except for Type 3 fonts
+    matrix: [f64; 6],
+    /// Maximum height above the baseline, in text space units of the font size.
+    pub ascent: f64,
+    /// Maximum depth below the baseline, negative, in text space units.
+    pub descent: f64,
+}
+
+impl Font {
+    /// Character codes of a shown string with the number of bytes each takes,
+    /// single bytes except for Type 0 fonts whose CMap splits the string.
+    pub fn decode(&self, bytes: &[u8]) -> Vec<(u32, usize)> {
+        match &self.cmap {
+            Some(cmap) => cmap.decode(bytes),
+            None => bytes.iter().map(|c| (*c as u32, 1)).collect(),
+        }
+    }
+    /// The CID a code of a Type 0 font selects.
+    pub fn cid(&self, code: u32) -> Option<u32> {
+        self.cmap.as_ref().and_then(|cmap| cmap.cid(code))
+    }
+    /// Whether a Type 0 font writes vertically.
+    pub fn is_vertical(&self) -> bool {
+        self.cmap.as_ref().is_some_and(|cmap| cmap.is_vertical())
+    }
+    /// The glyph name a simple font's encoding gives to a code.
+    pub fn glyph_name(&self, code: u32) -> Option<&str> {
+        self.glyph_names
+            .get(code as usize)
+            .map(|n| n.as_str())
+            .filter(|n| !n.is_empty())
+    }
+    /// The Unicode text of a code, empty when unknown. /ToUnicode comes first,
+    /// then the glyph name of the code, for a Type 0 font its CMap, the
+    /// embedded TrueType program and the Adobe-Japan1 character collection.
+    /// CIDs of the Adobe-GB1, CNS1 and Korea1 collections have no text of their own.
+    pub fn unicode(&self, code: u32) -> String {
+        if let Some(text) = self.to_unicode.as_ref().and_then(|m| m.unicode(code)) {
+            return text;
+        }
+        if let Some(cmap) = &self.cmap {
+            return cmap
+                .unicode(code)
+                .or_else(|| {
+                    let cid = cmap.cid(code)?;
+                    let c = self.cid_unicode.get(&cid).copied();
+                    c.or_else(|| japan1_char(cid).filter(|_| self.japan1))
+                        .map(|c| c.to_string())
+                })
+                .unwrap_or_default();
+        }
+        if self.glyph_names.is_empty() {
+            return match code {
+                0 => String::new(),
+                code => pdf_doc_char(code as u8).to_string(),
+            };
+        }
+        let name = match self.glyph_name(code) {
+            Some(name) => name,
+            None => return String::new(),
+        };
+        let dingbat = if self.dingbats {
+            dingbat_unicode(name)
+        } else {
+            None
+        };
+        dingbat.or_else(|| glyph_unicode(name)).unwrap_or_default()
+    }
+    /// Horizontal displacement of a code in text space, for a font size of 1.
+    pub fn width(&self, code: u32) -> f64 {
+        if self.cmap.is_some() {
+            let w = self.cid(code).and_then(|cid| find_range(&self.cid_widths, cid));
+            return w.copied().unwrap_or(self.missing_width) * 0.001;
+        }
+        let w = match code as i32 - self.first_char {
+            i if i >= 0 => self.widths.get(i as usize).copied(),
+            _ => None,
+        };
+        let w = w.or_else(|| self.standard?.width(self.glyph_name(code)?));
+        w.unwrap_or(self.missing_width) * self.matrix[0]
+    }
+    /// Vertical metrics of a code in text space for a font size of 1: the
+    /// vertical displacement w1y, negative downwards, and the position vector
+    /// (vx, vy) from the horizontal to the vertical origin.
+    pub fn vertical_metrics(&self, code: u32) -> (f64, f64, f64) {
+        let metrics = self.cid(code).and_then(|cid| find_range(&self.cid_vertical, cid));
+        match metrics {
+            Some([w1y, vx, vy]) => (w1y * 0.001, vx * 0.001, vy * 0.001),
+            None => {
+                let [vy, w1y] = self.vertical_default;
+                (w1y * 0.001, self.width(code) / 2.0, vy * 0.001)
+            }
+        }
+    }
+    /// Whether word spacing applies, to the single byte code 32.
+    pub fn is_space(&self, code: u32, len: usize) -> bool {
+        code == 32 && len == 1
+    }
+}
+
+/// The format of an embedded font program.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FontProgramKind {
+    /// /FontFile, a Type 1 font.
+    TYPE1,
+    /// /FontFile2, a TrueType font.
+    TRUETYPE,
+    /// /FontFile3 with /Subtype /Type1C, a bare CFF font.
+    CFF,
+    /// /FontFile3 with /Subtype /CIDFontType0C, a bare CID-keyed CFF font.
+    CID_CFF,
+    /// /FontFile3 with /Subtype /OpenType.
+    OPENTYPE,
+}
+
+impl FontProgramKind {
+    /// The usual extension of a file holding the program as Pdf::font_program
+    /// returns it.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            FontProgramKind::TYPE1 => "pfb",
+            FontProgramKind::TRUETYPE => "ttf",
+            FontProgramKind::CFF | FontProgramKind::CID_CFF => "cff",
+            FontProgramKind::OPENTYPE => "otf",
+        }
+    }
+}
+
+/// A /FontDescriptor, lengths in glyph space units (1000 to the em).
+#[derive(Debug, Clone, Default)]
+pub struct FontDescriptor {
+    /// The descriptor dictionary, None for a direct object.
+    pub id: Option<ObjectId>,
+    pub font_name: String,
+    pub font_family: Option<String>,
+    /// 100 to 900, 400 being normal and 700 bold.
+    pub font_weight: Option<f64>,
+    /// Bit 1 FixedPitch, 2 Serif, 3 Symbolic, 4 Script, 6 Nonsymbolic, 7 Italic,
+    /// 17 AllCap, 18 SmallCap, 19 ForceBold.
+    pub flags: i32,
+    pub font_bbox: Option<Rect>,
+    /// Degrees counterclockwise from vertical, negative for fonts leaning right.
+    pub italic_angle: f64,
+    pub ascent: f64,
+    pub descent: f64,
+    pub leading: f64,
+    pub cap_height: f64,
+    pub x_height: f64,
+    pub stem_v: f64,
+    pub stem_h: f64,
+    pub avg_width: f64,
+    pub max_width: f64,
+    pub missing_width: f64,
+}
+
+/// A font used by a page, as listed by Pdf::page_fonts.
+#[derive(Debug, Clone)]
+pub struct FontInfo {
+    /// The font dictionary, None for a direct object.
+    pub id: Option<ObjectId>,
+    /// The resource name the font is first found under, e.g. "F1".
+    pub name: String,
+    /// /Subtype: Type1, MMType1, TrueType, Type3 or Type0.
+    pub subtype: String,
+    /// /Subtype of the descendant font of a Type 0 font, CIDFontType0 or
+    /// CIDFontType2.
+    pub cid_subtype: Option<String>,
+    /// /BaseFont, with the subset tag if any.
+    pub base_font: String,
+    /// The six uppercase letters and '+' a subset font's name starts with,
+    /// without the '+'.
+    pub subset_tag: Option<String>,
+    /// Whether the font program is in the file: an embedded font file, or the
+    /// glyph procedures of a Type 3 font.
+    pub embedded: bool,
+    /// The descriptor, of the descendant font for a Type 0 font.
+    pub descriptor: Option<FontDescriptor>,
+    /// The format of the embedded font file.
+    pub program_kind: Option<FontProgramKind>,
+    /// The stream holding the font file.
+    pub program: Option<ObjectId>,
+}
+
+impl Pdf {
+    /// Loads the font dictionary `font`, a reference or a direct dictionary.
+    pub fn load_font(&self, font: &Value) -> Font {
+        let empty = Dict::new();
+        let dict = self.resolve(font).as_dict().unwrap_or(&empty);
+        let name = |key: &str| {
+            self.lookup(dict, key)
+                .and_then(|v| v.as_name())
+                .unwrap_or_default()
+                .to_string()
+        };
+        let numbers = |d: &Dict, key: &str| -> Vec<f64> {
+            self.lookup(d, key)
+                .and_then(|v| v.as_array())
+                .into_iter()
+                .flatten()
+                .map(|v| self.resolve(v).as_f64().unwrap_or(0.0))
+                .collect()
+        };
+        let subtype = name(PDF_NAME_Subtype);
+        // a Type 0 font takes its metrics from its descendant CIDFont
+        let cid_font = match subtype.as_str() {
+            "Type0" => self
+                .lookup(dict, PDF_NAME_DescendantFonts)
+                .and_then(|v| v.as_array())
+                .and_then(|fonts| fonts.first())
+                .and_then(|v| self.resolve(v).as_dict()),
+            _ => None,
+        };
+        let descriptor = self
+            .lookup(cid_font.unwrap_or(dict), PDF_NAME_FontDescriptor)
+            .and_then(|v| v.as_dict());
+        let metric = |key: &str| {
+            descriptor
+                .and_then(|d| self.lookup(d, key))
+                .and_then(|v| v.as_f64())
+        };
+        let mut matrix = [0.001, 0.0, 0.0, 0.001, 0.0, 0.0];
+        if subtype == "Type3" {
+            let m = numbers(dict, PDF_NAME_FontMatrix);
+            if m.len() == 6 {
+                matrix.copy_from_slice(&m);
+            }
+        }
+        let base_font = name(PDF_NAME_BaseFont);
+        let standard = match subtype.as_str() {
+            "Type0" | "Type3" => None,
+            _ => standard_font(&base_font),
+        };
+        // Type 3 fonts have no descriptor metrics, their bounding box gives the extent
+        let bbox = numbers(dict, PDF_NAME_FontBBox);
+        let extent = (metric(PDF_NAME_Ascent), metric(PDF_NAME_Descent), standard);
+        let (ascent, descent) = match extent {
+            (Some(a), Some(d), _) if a > 0.0 => (a * 0.001, d.min(0.0) * 0.001),
+            (_, _, Some(font)) => (font.ascent * 0.001, font.descent * 0.001),
+            // the font matrix may flip the box
+            _ if bbox.len() == 4 && bbox[3] != bbox[1] => {
+                let (y0, y1) = (bbox[1] * matrix[3], bbox[3] * matrix[3]);
+                (y0.max(y1), y0.min(y1))
+            }
+            _ => (0.75, -0.25),
+        };
+        // fonts without an /Encoding use their built-in one, for the fonts other
+        // than Symbol and ZapfDingbats taken to be the standard encoding
+        let standard_name = standard.map(|f| f.name);
+        let dingbats = standard_name == Some("ZapfDingbats");
+        let builtin = match (subtype.as_str(), standard_name) {
+            ("Type0", _) | ("Type3", _) => None,
+            (_, Some("Symbol")) => builtin_encoding("SymbolEncoding"),
+            (_, Some("ZapfDingbats")) => builtin_encoding("ZapfDingbatsEncoding"),
+            _ => builtin_encoding("StandardEncoding"),
+        };
+        let mut glyph_names: Vec<String> = Vec::new();
+        if subtype != "Type0" {
+            let encoding = self.lookup(dict, PDF_NAME_Encoding);
+            let base = match encoding {
+                Some(Value::NAME(n)) => builtin_encoding(n),
+                Some(Value::DICT(d)) => self
+                    .lookup(d, PDF_NAME_BaseEncoding)
+                    .and_then(|v| v.as_name())
+                    .and_then(builtin_encoding),
+                _ => None,
+            };
+            glyph_names = match base.or(builtin) {
+                Some(table) => table.iter().map(|n| n.to_string()).collect(),
+                None => vec![String::new(); 256],
+            };
+            let differences = encoding
+                .and_then(|v| v.as_dict())
+                .and_then(|d| self.lookup(d, PDF_NAME_Differences))
+                .and_then(|v| v.as_array());
+            // [code name name ... code name ...]
+            let mut code = 0;
+            for item in differences.into_iter().flatten() {
+                match self.resolve(item) {
+                    Value::INTEGER(n) => code = *n,
+                    Value::NAME(n) => {
+                        if let Some(slot) = glyph_names.get_mut(code as usize) {
+                            *slot = n.clone();
+                        }
+                        code += 1;
+                    }
+                    _ => (),
+                }
+            }
+        }
+        let widths = numbers(dict, PDF_NAME_Widths);
+        // fonts without /Widths get an average width where no AFM width applies
+        let mut missing_width = metric(PDF_NAME_MissingWidth)
+            .unwrap_or(if widths.is_empty() { 500.0 } else { 0.0 });
+        let to_unicode = self
+            .lookup(dict, PDF_NAME_ToUnicode)
+            .and_then(|v| v.as_stream())
+            .map(|s| CMap::parse(&s.data));
+        let mut cmap = None;
+        let mut cid_widths = Vec::new();
+        let mut cid_vertical = Vec::new();
+        let mut vertical_default = [880.0, -1000.0];
+        let mut cid_unicode = HashMap::new();
+        let mut japan1 = false;
+        if subtype == "Type0" {
+            cmap = Some(self.encoding_cmap(self.lookup(dict, PDF_NAME_Encoding), 0));
+            let cid_font = cid_font.unwrap_or(&empty);
+            missing_width = self
+                .lookup(cid_font, PDF_NAME_DW)
+                .and_then(|v| v.as_f64())
+                .unwrap_or(1000.0);
+            if let [vy, w1y] = numbers(cid_font, PDF_NAME_DW2).as_slice() {
+                vertical_default = [*vy, *w1y];
+            }
+            let w = self.lookup(cid_font, PDF_NAME_W).and_then(|v| v.as_array());
+            for (first, last, values) in self.cid_metrics(w, 1) {
+                cid_widths.push((first, last, values[0]));
+            }
+            let w2 = self.lookup(cid_font, PDF_NAME_W2).and_then(|v| v.as_array());
+            for (first, last, values) in self.cid_metrics(w2, 3) {
+                cid_vertical.push((first, last, [values[0], values[1], values[2]]));
+            }
+            cid_widths.sort_by_key(|r| r.0);
+            cid_vertical.sort_by_key(|r| r.0);
+            if to_unicode.is_none() {
+                cid_unicode = self.truetype_cid_unicode(cid_font, descriptor);
+            }
+            let ordering = self
+                .lookup(cid_font, PDF_NAME_CIDSystemInfo)
+                .and_then(|v| v.as_dict())
+                .and_then(|info| self.lookup(info, PDF_NAME_Ordering))
+                .and_then(|v| v.as_text());
+            japan1 = ordering.as_deref() == Some("Japan1");
+        }
+        Font {
+            id: font.as_object_id(),
+            base_font,
+            glyph_names,
+            dingbats,
+            to_unicode,
+            cmap,
+            first_char: self
+                .lookup(dict, PDF_NAME_FirstChar)
+                .and_then(|v| v.as_int())
+                .unwrap_or(0),
+            standard: if widths.is_empty() { standard } else { None },
+            widths,
+            missing_width,
+            cid_widths,
+            cid_vertical,
+            vertical_default,
+            cid_unicode,
+            japan1,
+            matrix,
+            ascent,
+            descent,
+            subtype,
+        }
+    }
+}
+
+impl Pdf {
+    // the /Encoding of a Type 0 font, a predefined CMap by name or an embedded
+    // one; an unknown name is taken as Identity-H
+    fn encoding_cmap(&self, encoding: Option<&Value>, depth: i32) -> CMap {
+        let identity = || CMap::predefined("Identity-H").unwrap_or_default();
+        match encoding {
+            Some(Value::NAME(name)) => CMap::predefined(name).unwrap_or_else(identity),
+            Some(Value::STREAM(stream)) if depth < 8 => {
+                let mut cmap = CMap::parse(&stream.data);
+                let wmode = self.lookup(&stream.dict, PDF_NAME_WMode);
+                if let Some(n) = wmode.and_then(|v| v.as_int()) {
+                    cmap.wmode = n;
+                }
+                if let Some(parent) = self.lookup(&stream.dict, PDF_NAME_UseCMap) {
+                    cmap.use_cmap(&self.encoding_cmap(Some(parent), depth + 1));
+                }
+                cmap
+            }
+            _ => identity(),
+        }
+    }
+    // /W or /W2 with `n` numbers per CID: c [n numbers of c, c + 1, ...] or
+    // c_first c_last n numbers
+    fn cid_metrics(&self, array: Option<&Vec<Value>>, n: usize) -> Vec<(u32, u32, Vec<f64>)> {
+        let items: Vec<&Value> = array.into_iter().flatten().map(|v| self.resolve(v)).collect();
+        let mut ranges = Vec::new();
+        let mut i = 0;
+        while i + 1 < items.len() {
+            let first = match items[i].as_int() {
+                Some(c) if c >= 0 => c as u32,
+                _ => break,
+            };
+            if let Value::ARRAY(values) = items[i + 1] {
+                let values: Vec<f64> = values
+                    .iter()
+                    .map(|v| self.resolve(v).as_f64().unwrap_or(0.0))
+                    .collect();
+                for (j, chunk) in values.chunks_exact(n).enumerate() {
+                    let cid = first + j as u32;
+                    ranges.push((cid, cid, chunk.to_vec()));
+                }
+                i += 2;
+                continue;
+            }
+            let last = items[i + 1].as_int().map(|c| c as u32);
+            let values: Option<Vec<f64>> = items.get(i + 2..i + 2 + n).map(|vs| {
+                vs.iter().map(|v| v.as_f64().unwrap_or(0.0)).collect()
+            });
+            match (last, values) {
+                (Some(last), Some(values)) if last >= first => ranges.push((first, last, values)),
+                (Some(_), Some(_)) => (),
+                _ => break,
+            }
+            i += 2 + n;
+        }
+        ranges
+    }
+    // CIDs to Unicode through /CIDToGIDMap and the cmap table of the embedded
+    // TrueType program of a CIDFontType2
+    fn truetype_cid_unicode(
+        &self,
+        cid_font: &Dict,
+        descriptor: Option<&Dict>,
+    ) -> HashMap<u32, char> {
+        let program = descriptor
+            .and_then(|d| self.lookup(d, PDF_NAME_FontFile2))
+            .and_then(|v| v.as_stream());
+        let gid_unicode = match program {
+            Some(program) => truetype_unicode(&program.data),
+            None => return HashMap::new(),
+        };
+        match self.lookup(cid_font, PDF_NAME_CIDToGIDMap) {
+            // a stream of two byte glyph ids by CID
+            Some(Value::STREAM(map)) => map
+                .data
+                .chunks_exact(2)
+                .enumerate()
+                .filter_map(|(cid, gid)| {
+                    let gid = u16::from_be_bytes([gid[0], gid[1]]);
+                    gid_unicode.get(&gid).map(|c| (cid as u32, *c))
+                })
+                .collect(),
+            _ => gid_unicode.into_iter().map(|(gid, c)| (gid as u32, c)).collect(),
+        }
+    }
+}
+
+impl Pdf {
+    /// The fonts of page `no`: those of its resources, of the forms it draws and of
+    /// the glyph procedures of its Type 3 fonts, each listed once.
+    pub fn page_fonts(&self, no: i32) -> io::Result<Vec<FontInfo>> {
+        let id = self
+            .page_id(no)
+            .ok_or_else(|| Error::new(ErrorKind::Other, "page out of range"))?;
+        let resources = inherited_attr(self, id, PDF_NAME_Resources).and_then(|v| v.as_dict());
+        let mut fonts = Vec::new();
+        let mut visited = HashSet::new();
+        if let Some(resources) = resources {
+            self.collect_fonts(resources, &mut fonts, &mut visited, 0);
+        }
+        Ok(fonts)
+    }
+    /// Information on the font dictionary `font`, a reference or a direct dictionary.
+    pub fn font_info(&self, name: &str, font: &Value) -> FontInfo {
+        let empty = Dict::new();
+        let dict = self.resolve(font).as_dict().unwrap_or(&empty);
+        let name_of = |d: &Dict, key: &str| {
+            self.lookup(d, key)
+                .and_then(|v| v.as_name())
+                .map(|n| n.to_string())
+        };
+        let subtype = name_of(dict, PDF_NAME_Subtype).unwrap_or_default();
+        let cid_font = match subtype.as_str() {
+            "Type0" => self
+                .lookup(dict, PDF_NAME_DescendantFonts)
+                .and_then(|v| v.as_array())
+                .and_then(|fonts| fonts.first())
+                .and_then(|v| self.resolve(v).as_dict()),
+            _ => None,
+        };
+        let base_font = name_of(dict, PDF_NAME_BaseFont).unwrap_or_default();
+        let subset_tag = match base_font.split_once('+') {
+            Some((tag, _)) if tag.len() == 6 && tag.bytes().all(|c| c.is_ascii_uppercase()) => {
+                Some(tag.to_string())
+            }
+            _ => None,
+        };
+        let descriptor_value = cid_font
+            .unwrap_or(dict)
+            .get(PDF_NAME_FontDescriptor)
+            .filter(|v| self.resolve(v).as_dict().is_some());
+        let descriptor = descriptor_value.and_then(|v| self.resolve(v).as_dict());
+        let mut program_kind = None;
+        let mut program = None;
+        for key in [PDF_NAME_FontFile, PDF_NAME_FontFile2, PDF_NAME_FontFile3].iter() {
+            let file = descriptor.and_then(|d| d.get(key)).and_then(|v| v.as_object_id());
+            let stream = file.and_then(|id| self.get_object(id)).and_then(|v| v.as_stream());
+            let stream = match stream {
+                Some(stream) => stream,
+                None => continue,
+            };
+            program_kind = match *key {
+                PDF_NAME_FontFile => Some(FontProgramKind::TYPE1),
+                PDF_NAME_FontFile2 => Some(FontProgramKind::TRUETYPE),
+                _ => match name_of(&stream.dict, PDF_NAME_Subtype).as_deref() {
+                    Some("Type1C") => Some(FontProgramKind::CFF),
+                    Some("CIDFontType0C") => Some(FontProgramKind::CID_CFF),
+                    Some("OpenType") => Some(FontProgramKind::OPENTYPE),
+                    _ => None,
+                },
+            };
+            program = file;
+            break;
+        }
+        let descriptor = descriptor.map(|d| {
+            let number = |key: &str| self.lookup(d, key).and_then(|v| v.as_f64());
+            FontDescriptor {
+                id: descriptor_value.and_then(|v| v.as_object_id()),
+                font_name: name_of(d, PDF_NAME_FontName).unwrap_or_default(),
+                font_family: self
+                    .lookup(d, PDF_NAME_FontFamily)
+                    .and_then(|v| match v {
+                        Value::STRING(s) => Some(decode_text(s)),
+                        _ => None,
+                    }),
+                font_weight: number(PDF_NAME_FontWeight),
+                flags: self
+                    .lookup(d, PDF_NAME_Flags)
+                    .and_then(|v| v.as_int())
+                    .unwrap_or(0),
+                font_bbox: self.lookup(d, PDF_NAME_FontBBox).and_then(Rect::from_value),
+                italic_angle: number(PDF_NAME_ItalicAngle).unwrap_or(0.0),
+                ascent: number(PDF_NAME_Ascent).unwrap_or(0.0),
+                descent: number(PDF_NAME_Descent).unwrap_or(0.0),
+                leading: number(PDF_NAME_Leading).unwrap_or(0.0),
+                cap_height: number(PDF_NAME_CapHeight).unwrap_or(0.0),
+                x_height: number(PDF_NAME_XHeight).unwrap_or(0.0),
+                stem_v: number(PDF_NAME_StemV).unwrap_or(0.0),
+                stem_h: number(PDF_NAME_StemH).unwrap_or(0.0),
+                avg_width: number(PDF_NAME_AvgWidth).unwrap_or(0.0),
+                max_width: number(PDF_NAME_MaxWidth).unwrap_or(0.0),
+                missing_width: number(PDF_NAME_MissingWidth).unwrap_or(0.0),
+            }
+        });
+        FontInfo {
+            id: font.as_object_id(),
+            name: name.to_string(),
+            embedded: program.is_some() || subtype == "Type3",
+            subtype,
+            cid_subtype: cid_font.and_then(|d| name_of(d, PDF_NAME_Subtype)),
+            base_font,
+            subset_tag,
+            descriptor,
+            program_kind,
+            program,
+        }
+    }
+    /// The decoded program of an embedded font, to be saved as a standalone font
+    /// file: a Type 1 font in PFB form, TrueType, bare CFF or OpenType data.
+    pub fn font_program(&self, font: &FontInfo) -> io::Result<Vec<u8>> {
+        let stream = font
+            .program
+            .and_then(|id| self.get_object(id))
+            .and_then(|v| v.as_stream())
+            .ok_or_else(|| Error::new(ErrorKind::Other, "font is not embedded"))?;
+        let data = decoded_data(stream)?;
+        if font.program_kind != Some(FontProgramKind::TYPE1) {
+            return Ok(data.to_vec());
+        }
+        // the clear text, binary and trailer parts of a Type 1 font each become a
+        // PFB segment
+        let length = |key: &str| {
+            self.lookup(&stream.dict, key)
+                .and_then(|v| v.as_int())
+                .filter(|n| *n >= 0)
+                .map(|n| n as usize)
+        };
+        let (clear, binary) = match (length(PDF_NAME_Length1), length(PDF_NAME_Length2)) {
+            (Some(l1), Some(l2)) if l1 + l2 <= data.len() => (l1, l2),
+            _ => return Ok(data.to_vec()),
+        };
+        let rest = &data[clear + binary..];
+        let trailer = match length(PDF_NAME_Length3).unwrap_or(rest.len()).min(rest.len()) {
+            // the trailer is often left out
+            0 => {
+                let mut t = "0".repeat(64).as_bytes().to_vec();
+                t.push(b'\n');
+                t = t.repeat(8);
+                t.extend_from_slice(b"cleartomark\n");
+                t
+            }
+            n => rest[..n].to_vec(),
+        };
+        let mut pfb = Vec::with_capacity(data.len() + trailer.len() + 20);
+        let segments = [
+            (1u8, &data[..clear]),
+            (2, &data[clear..clear + binary]),
+            (1, &trailer[..]),
+        ];
+        for (kind, segment) in segments.iter() {
+            pfb.extend_from_slice(&[0x80, *kind]);
+            pfb.extend_from_slice(&(segment.len() as u32).to_le_bytes());
+            pfb.extend_from_slice(segment);
+        }
+        pfb.extend_from_slice(&[0x80, 3]);
+        Ok(pfb)
+    }
+    // the fonts of a resource dictionary, then those of the forms and Type 3 fonts
+    // it holds, objects seen before are skipped
+    fn collect_fonts(
+        &self,
+        resources: &Dict,
+        fonts: &mut Vec<FontInfo>,
+        visited: &mut HashSet<ObjectId>,
+        depth: i32,
+    ) {
+        if depth > 16 {
+            return;
+        }
+        let mut nested = Vec::new();
+        let font_dict = self.lookup(resources, PDF_NAME_Font).and_then(|v| v.as_dict());
+        for (name, font) in font_dict.into_iter().flat_map(|d| d.iter()) {
+            if font.as_object_id().is_some_and(|id| !visited.insert(id)) {
+                continue;
+            }
+            let info = self.font_info(name, font);
+            if info.subtype == "Type3" {
+                let dict = self.resolve(font).as_dict();
+                nested.extend(dict.and_then(|d| self.lookup(d, PDF_NAME_Resources)));
+            }
+            fonts.push(info);
+        }
+        let xobjects = self.lookup(resources, PDF_NAME_XObject).and_then(|v| v.as_dict());
+        for (_, xobject) in xobjects.into_iter().flat_map(|d| d.iter()) {
+            if xobject.as_object_id().is_some_and(|id| !visited.insert(id)) {
+                continue;
+            }
+            let form = match self.resolve(xobject).as_stream() {
+                Some(form) => form,
+                None => continue,
+            };
+            nested.extend(self.lookup(&form.dict, PDF_NAME_Resources));
+        }
+        for resources in nested {
+            if let Some(resources) = resources.as_dict() {
+                self.collect_fonts(resources, fonts, visited, depth + 1);
+            }
+        }
+    }
+}
+
+// the entry of sorted ranges (first, last, value) holding `cid`
+fn find_range<T>(ranges: &[(u32, u32, T)], cid: u32) -> Option<&T> {
+    let i = ranges.partition_point(|r| r.0 <= cid);
+    ranges[..i].iter().rev().take(8).find(|r| cid <= r.1).map(|r| &r.2)
+}
+
+// glyph ids to Unicode from the cmap table of a TrueType program, by its
+// Unicode subtable: format 4, or format 12 beyond the BMP
+fn truetype_unicode(data: &[u8]) -> HashMap<u16, char> {
+    let u16_at = |pos: usize| data.get(pos..pos + 2).map(|b| u16::from_be_bytes([b[0], b[1]]));
+    let u32_at = |pos: usize| {
+        data.get(pos..pos + 4)
+            .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    };
+    let mut map = HashMap::new();
+    let num_tables = u16_at(4).unwrap_or(0) as usize;
+    let cmap = (0..num_tables)
+        .map(|i| 12 + i * 16)
+        .find(|pos| data.get(*pos..*pos + 4) == Some(b"cmap"))
+        .and_then(|pos| u32_at(pos + 8));
+    let cmap = match cmap {
+        Some(offset) => offset as usize,
+        None => return map,
+    };
+    // Windows Unicode full repertoire, Windows Unicode BMP, Unicode platform
+    let mut subtables: Vec<(u16, u16, usize)> = (0..u16_at(cmap + 2).unwrap_or(0) as usize)
+        .filter_map(|i| {
+            let pos = cmap + 4 + i * 8;
+            Some((u16_at(pos)?, u16_at(pos + 2)?, cmap + u32_at(pos + 4)? as usize))
+        })
+        .filter(|(platform, encoding, _)| {
+            *platform == 0 || (*platform == 3 && matches!(encoding, 1 | 10))
+        })
+        .collect();
+    subtables.sort_by_key(|(platform, encoding, _)| (*encoding != 10, *platform == 0));
+    let offset = match subtables.first() {
+        Some((_, _, offset)) => *offset,
+        None => return map,
+    };
+    match u16_at(offset) {
+        Some(4) => {
+            let seg_count = u16_at(offset + 6).unwrap_or(0) as usize / 2;
+            let ends = offset + 14;
+            let starts = ends + seg_count * 2 + 2;
+            let deltas = starts + seg_count * 2;
+            let range_offsets = deltas + seg_count * 2;
+            for seg in 0..seg_count {
+                let (end, start, delta, range_offset) = match (
+                    u16_at(ends + seg * 2),
+                    u16_at(starts + seg * 2),
+                    u16_at(deltas + seg * 2),
+                    u16_at(range_offsets + seg * 2),
+                ) {
+                    (Some(e), Some(s), Some(d), Some(r)) => (e, s, d, r),
+                    _ => break,
+                };
+                for c in start..=end.min(0xFFFE) {
+                    let gid = if range_offset == 0 {
+                        c.wrapping_add(delta)
+                    } else {
+                        let pos = range_offsets
+                            + seg * 2
+                            + range_offset as usize
+                            + (c - start) as usize * 2;
+                        match u16_at(pos) {
+                            Some(0) | None => continue,
+                            Some(g) => g.wrapping_add(delta),
+                        }
+                    };
+                    if let Some(c) = char::from_u32(c as u32) {
+                        map.entry(gid).or_insert(c);
+                    }
+                }
+            }
+        }
+        Some(12) => {
+            let groups = u32_at(offset + 12).unwrap_or(0) as usize;
+            for i in 0..groups {
+                let pos = offset + 16 + i * 12;
+                let (start, end, gid) = match (u32_at(pos), u32_at(pos + 4), u32_at(pos + 8)) {
+                    (Some(s), Some(e), Some(g)) if s <= e => (s, e, g),
+                    _ => break,
+                };
+                for c in start..=end.min(start.saturating_add(0xFFFF)) {
+                    let gid = gid.saturating_add(c - start);
+                    if let (Some(ch), true) = (char::from_u32(c), gid <= 0xFFFF) {
+                        map.entry(gid as u16).or_insert(ch);
+                    }
+                }
+            }
+        }
+        _ => (),
+    }
+    map
+}
+
+// the font name without the tag of a subset, "ABCDEF+Name"
+pub(crate) fn strip_subset(name: &str) -> &str {
+    match name.split_once('+') {
+        Some((tag, rest)) if tag.len() == 6 && tag.bytes().all(|b| b.is_ascii_uppercase()) => rest,
+        _ => name,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a standard font with /Differences, one with /Widths and a Type 0 font on
+    // a Japanese CMap without /ToUnicode, whose program is an embedded Type 1 font
+    const FONTS: &str = r"
+1 0 obj << /Type /Catalog /Pages 2 0 R >> endobj
+2 0 obj << /Type /Pages /Kids [3 0 R] /Count 1 >> endobj
+3 0 obj << /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792]
+  /Resources << /Font << /F1 10 0 R /F2 11 0 R /F3 20 0 R >> /XObject << /X1 30 0 R >> >> >>
+  endobj
+10 0 obj << /Type /Font /Subtype /Type1 /BaseFont /Helvetica
+  /Encoding << /BaseEncoding /WinAnsiEncoding /Differences [65 /Aring /fi] >> >> endobj
+11 0 obj << /Type /Font /Subtype /TrueType /BaseFont /ABCDEF+Arial /FirstChar 32
+  /Widths [250 300] /FontDescriptor << /FontName /ABCDEF+Arial /Flags 32 /Ascent 900
+  /Descent -200 /MissingWidth 100 /FontFile2 12 0 R >> >> endobj
+12 0 obj << >> stream
+true
+endstream endobj
+20 0 obj << /Type /Font /Subtype /Type0 /BaseFont /Mincho /Encoding /90ms-RKSJ-V
+  /DescendantFonts [21 0 R] >> endobj
+21 0 obj << /Type /Font /Subtype /CIDFontType0 /BaseFont /Mincho /DW 900
+  /CIDSystemInfo << /Registry (Adobe) /Ordering (Japan1) /Supplement 2 >>
+  /W [264 [500 600] 1125 1200 950] /W2 [1125 [-800 500 860]] /FontDescriptor 22 0 R >>
+  endobj
+22 0 obj << /Type /FontDescriptor /FontName /Mincho /FontFile 23 0 R >> endobj
+23 0 obj << /Length1 4 /Length2 3 /Length3 0 >> stream
+abcdxyz
+endstream endobj
+30 0 obj << /Type /XObject /Subtype /Form /BBox [0 0 1 1]
+  /Resources << /Font << /F4 10 0 R /F5 << /Subtype /Type3 /FontMatrix [0.01 0 0 0.01 0 0]
+  /FontBBox [0 -20 80 90] /Widths [50] /FirstChar 1 >> >> >> >> stream
+endstream endobj
+";
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn simple_fonts() {
+        let pdf = test_pdf(FONTS);
+        let helvetica = pdf.load_font(&Value::REF(10, 0));
+        assert_eq!(helvetica.decode(b"Ab"), [(65, 1), (98, 1)]);
+        assert_eq!(helvetica.glyph_name(66), Some("fi"));
+        let text: String = [65, 66, 0x80, 0xe9].iter().map(|c| helvetica.unicode(*c)).collect();
+        assert_eq!(text, "\u{c5}\u{fb01}\u{20ac}\u{e9}");
+        // AFM widths by glyph name, Aring and fi
+        assert_eq!((helvetica.width(65), helvetica.width(66)), (0.667, 0.5));
+        assert!(close(helvetica.ascent, 0.718) && close(helvetica.descent, -0.207));
+        let arial = pdf.load_font(&Value::REF(11, 0));
+        let widths: Vec<f64> = [31, 32, 33, 34].iter().map(|c| arial.width(*c)).collect();
+        assert_eq!(widths, [0.1, 0.25, 0.3, 0.1]);
+        assert_eq!((arial.ascent, arial.descent), (0.9, -0.2));
+        assert!(arial.is_space(32, 1) && !arial.is_space(32, 2));
+
+        let resources = pdf.get_object(ObjectId::new(30, 0)).and_then(|v| v.as_stream());
+        let fonts = resources
+            .and_then(|form| form.dict.get("Resources"))
+            .and_then(|v| v.as_dict())
+            .and_then(|r| r.get("Font"))
+            .and_then(|v| v.as_dict())
+            .unwrap();
+        let type3 = pdf.load_font(fonts.get("F5").unwrap());
+        assert!(close(type3.width(1), 0.5));
+        assert!(close(type3.ascent, 0.9) && close(type3.descent, -0.2));
+        // no /Encoding and no built-in one
+        assert_eq!(type3.unicode(65), "");
+    }
+
+    #[test]
+    fn cid_fonts() {
+        let pdf = test_pdf(FONTS);
+        let mincho = pdf.load_font(&Value::REF(20, 0));
+        assert!(mincho.is_vertical());
+        assert_eq!(mincho.decode(b"A\x88\x9f"), [(0x41, 1), (0x889f, 2)]);
+        assert_eq!((mincho.cid(0x41), mincho.cid(0x889f)), (Some(264), Some(1125)));
+        // no /ToUnicode, the CIDs are those of Adobe-Japan1
+        assert_eq!(mincho.unicode(0x41) + &mincho.unicode(0x889f), "A亜");
+        assert_eq!((mincho.width(0x41), mincho.width(0x42)), (0.5, 0.6));
+        assert!(close(mincho.width(0x889f), 0.95) && close(mincho.width(0x43), 0.9));
+        assert_eq!(mincho.vertical_metrics(0x889f), (-0.8, 0.5, 0.86));
+        assert_eq!(mincho.vertical_metrics(0x41), (-1.0, 0.25, 0.88));
+        // a one byte code 32 of a Type 0 font takes word spacing too
+        assert!(mincho.is_space(32, 1) && !mincho.is_space(32, 2));
+    }
+
+    #[test]
+    fn chinese_and_korean_cmaps() {
+        let font = |encoding: &str, ordering: &str| {
+            test_pdf(&format!(
+                "1 0 obj << /Type /Catalog >> endobj
+                10 0 obj << /Type /Font /Subtype /Type0 /BaseFont /Song /Encoding /{}
+                  /DescendantFonts [<< /Type /Font /Subtype /CIDFontType0 /BaseFont /Song
+                  /DW 1000 /W [34 [500] 814 [600]] /CIDSystemInfo << /Registry (Adobe)
+                  /Ordering ({}) /Supplement 0 >> >>] >> endobj",
+                encoding, ordering
+            ))
+            .load_font(&Value::REF(10, 0))
+        };
+        // text comes from the character set, CIDs only for ASCII
+        let song = font("GBK-EUC-H", "GB1");
+        assert_eq!(song.unicode(0x41) + &song.unicode(0xB0A1), "A啊");
+        assert_eq!((song.cid(0x41), song.cid(0xB0A1)), (Some(34), None));
+        assert_eq!((song.width(0x41), song.width(0xB0A1)), (0.5, 1.0));
+        let ming = font("B5pc-H", "CNS1");
+        assert_eq!(ming.unicode(0xA440), "一");
+        assert_eq!(ming.width(0xA440), 1.0);
+        let batang = font("KSCms-UHC-H", "Korea1");
+        assert_eq!(batang.unicode(0xB0A1), "가");
+        assert_eq!(batang.width(0xB0A1), 1.0);
+        // without /ToUnicode the CIDs of other collections than Japan1 have no text
+        let song = font("Identity-H", "GB1");
+        assert_eq!((song.unicode(814), song.width(814)), (String::new(), 0.6));
+    }
+
+    #[test]
+    fn page_fonts() {
+        let pdf = test_pdf(FONTS);
+        let fonts = pdf.page_fonts(0).unwrap();
+        let names: Vec<&str> = fonts.iter().map(|f| f.name.as_str()).collect();
+        // F4 is F1 again
+        assert_eq!(names, ["F1", "F2", "F3", "F5"]);
+        let arial = &fonts[1];
+        assert_eq!(arial.subset_tag.as_deref(), Some("ABCDEF"));
+        assert!(arial.embedded);
+        assert_eq!(arial.program_kind, Some(FontProgramKind::TRUETYPE));
+        let descriptor = arial.descriptor.as_ref().unwrap();
+        assert_eq!((descriptor.flags, descriptor.ascent), (32, 900.0));
+        assert_eq!(pdf.font_program(arial).unwrap(), b"true");
+        assert!(!fonts[0].embedded && pdf.font_program(&fonts[0]).is_err());
+        let mincho = &fonts[2];
+        assert_eq!(mincho.cid_subtype.as_deref(), Some("CIDFontType0"));
+        assert_eq!(mincho.descriptor.as_ref().unwrap().id, Some(ObjectId::new(22, 0)));
+        assert_eq!(mincho.program_kind.unwrap().extension(), "pfb");
+        // a PFB with the standard trailer added
+        let pfb = pdf.font_program(mincho).unwrap();
+        assert_eq!(&pfb[..10], b"\x80\x01\x04\x00\x00\x00abcd");
+        assert_eq!(&pfb[10..19], b"\x80\x02\x03\x00\x00\x00xyz");
+        assert_eq!(&pfb[19..25], b"\x80\x01\x14\x02\x00\x00");
+        assert!(pfb.ends_with(b"cleartomark\n\x80\x03"));
+        assert!(fonts[3].embedded && fonts[3].id.is_none());
+    }
+
+    #[test]
+    fn ranges() {
+        let ranges = [(1, 3, 'a'), (2, 2, 'b'), (10, 20, 'c')];
+        let found: Vec<Option<&char>> =
+            [0, 1, 2, 3, 5, 20, 21].iter().map(|c| find_range(&ranges, *c)).collect();
+        assert_eq!(found, [None, Some(&'a'), Some(&'b'), Some(&'a'), None, Some(&'c'), None]);
+    }
+}
@@ -0,0 +1,1388 @@
+use super::*;
+use std::collections::{HashMap, HashSet};
+
+const PDF_NAME_AcroForm: &str = "AcroForm";
+const PDF_NAME_Fields: &str = "Fields";
+const PDF_NAME_T: &str = "T";
+const PDF_NAME_TU: &str = "TU";
+const PDF_NAME_FT: &str = "FT";
+const PDF_NAME_Ff: &str = "Ff";
+const PDF_NAME_V: &str = "V";
+const PDF_NAME_DV: &str = "DV";
+const PDF_NAME_DA: &str = "DA";
+const PDF_NAME_Q: &str = "Q";
+const PDF_NAME_Opt: &str = "Opt";
+const PDF_NAME_MaxLen: &str = "MaxLen";
+const PDF_NAME_P: &str = "P";
+const PDF_NAME_Rect: &str = "Rect";
+const PDF_NAME_AP: &str = "AP";
+const PDF_NAME_N: &str = "N";
+const PDF_NAME_Annots: &str = "Annots";
+const PDF_NAME_Subtype: &str = "Subtype";
+
+// field flags (/Ff)
+pub const FIELD_FLAG_READ_ONLY: i32 = 1;
+pub const FIELD_FLAG_REQUIRED: i32 = 1 << 1;
+pub const FIELD_FLAG_NO_EXPORT: i32 = 1 << 2;
+pub const FIELD_FLAG_MULTILINE: i32 = 1 << 12;
+pub const FIELD_FLAG_PASSWORD: i32 = 1 << 13;
+pub const FIELD_FLAG_NO_TOGGLE_TO_OFF: i32 = 1 << 14;
+pub const FIELD_FLAG_RADIO: i32 = 1 << 15;
+pub const FIELD_FLAG_PUSHBUTTON: i32 = 1 << 16;
+pub const FIELD_FLAG_COMBO: i32 = 1 << 17;
+pub const FIELD_FLAG_EDIT: i32 = 1 << 18;
+pub const FIELD_FLAG_SORT: i32 = 1 << 19;
+pub const FIELD_FLAG_FILE_SELECT: i32 = 1 << 20;
+pub const FIELD_FLAG_MULTI_SELECT: i32 = 1 << 21;
+pub const FIELD_FLAG_DO_NOT_SPELL_CHECK: i32 = 1 << 22;
+pub const FIELD_FLAG_DO_NOT_SCROLL: i32 = 1 << 23;
+pub const FIELD_FLAG_COMB: i32 = 1 << 24;
+pub const FIELD_FLAG_RADIOS_IN_UNISON: i32 = 1 << 25;
+pub const FIELD_FLAG_COMMIT_ON_SEL_CHANGE: i32 = 1 << 26;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldType {
+    TEXT,
+    CHECKBOX,
+    RADIO,
+    PUSH_BUTTON,
+    COMBO,
+    LIST,
+    SIGNATURE,
+    UNKNOWN,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldValue {
+    NONE,
+    TEXT(String),
+    /// State name of a checkbox or radio group, "Off" when unselected.
+    STATE(String),
+    /// Selected items of a multiple selection list.
+    LIST(Vec<String>),
+}
+
+/// A widget annotation showing a field.
+#[derive(Debug, Clone)]
+pub struct FieldWidget {
+    pub id: Option<ObjectId>,
+    pub page: Option<i32>,
+    pub rect: Rect,
+    /// The "on" appearance state of a checkbox or radio button widget.
+    pub on_state: Option<String>,
+}
+
+/// A terminal field of the interactive form.
+#[derive(Debug, Clone)]
+pub struct FormField {
+    pub id: ObjectId,
+    /// Fully qualified name, the partial names joined with '.'.
+    pub name: String,
+    pub partial_name: Option<String>,
+    /// /TU, the name shown to users.
+    pub alternate_name: Option<String>,
+    pub field_type: FieldType,
+    pub flags: i32,
+    pub value: FieldValue,
+    pub default_value: FieldValue,
+    /// Choice options as (export value, display text).
+    pub options: Vec<(String, String)>,
+    pub max_len: Option<i32>,
+    pub default_appearance: Option<String>,
+    pub quadding: i32,
+    pub widgets: Vec<FieldWidget>,
+}
+
+impl FieldValue {
+    // the /V entry holding this value, None for no value
+    pub(crate) fn to_value(&self) -> Option<Value> {
+        match self {
+            FieldValue::NONE => None,
+            FieldValue::TEXT(s) => Some(Value::STRING(encode_text(s))),
+            FieldValue::STATE(s) => Some(Value::NAME(s.clone())),
+            FieldValue::LIST(items) => Some(Value::ARRAY(
+                items.iter().map(|s| Value::STRING(encode_text(s))).collect(),
+            )),
+        }
+    }
+}
+
+impl FormField {
+    pub fn has_flag(&self, flag: i32) -> bool {
+        self.flags & flag != 0
+    }
+    // the value as stored in this field, Err when it does not fit the field type
+    pub(crate) fn check_value(&self, value: FieldValue) -> io::Result<FieldValue> {
+        let mismatch = || Error::new(ErrorKind::Other, "value does not match the field type");
+        let value = match (self.field_type, value) {
+            (_, FieldValue::NONE) => FieldValue::NONE,
+            (FieldType::TEXT, FieldValue::TEXT(s)) => match self.max_len {
+                Some(n) if n >= 0 => FieldValue::TEXT(s.chars().take(n as usize).collect()),
+                _ => FieldValue::TEXT(s),
+            },
+            (FieldType::COMBO, FieldValue::TEXT(s)) | (FieldType::LIST, FieldValue::TEXT(s)) => {
+                FieldValue::TEXT(s)
+            }
+            (FieldType::LIST, FieldValue::LIST(items)) if self.has_flag(FIELD_FLAG_MULTI_SELECT) => {
+                FieldValue::LIST(items)
+            }
+            (FieldType::CHECKBOX, FieldValue::STATE(s)) | (FieldType::RADIO, FieldValue::STATE(s)) => {
+                let known = s == "Off"
+                    || self.widgets.iter().all(|w| w.on_state.is_none())
+                    || self.widgets.iter().any(|w| w.on_state.as_deref() == Some(&s));
+                if !known {
+                    return Err(Error::new(ErrorKind::Other, "unknown button state"));
+                }
+                FieldValue::STATE(s)
+            }
+            _ => return Err(mismatch()),
+        };
+        Ok(value)
+    }
+}
+
+// inheritable attributes collected on the way down the field tree
+#[derive(Clone, Default)]
+struct Inherited<'a> {
+    name: Option<String>,
+    ft: Option<&'a Value>,
+    ff: Option<&'a Value>,
+    v: Option<&'a Value>,
+    dv: Option<&'a Value>,
+    da: Option<&'a Value>,
+    q: Option<&'a Value>,
+    opt: Option<&'a Value>,
+    max_len: Option<&'a Value>,
+}
+
+impl Pdf {
+    /// The catalog /AcroForm dictionary.
+    pub fn acro_form(&self) -> Option<&Dict> {
+        let catalog = self.catalog()?;
+        self.lookup(catalog, PDF_NAME_AcroForm)?.as_dict()
+    }
+    /// All terminal fields of the interactive form in tree order.
+    pub fn form_fields(&self) -> Vec<FormField> {
+        self.collect_form_fields(None)
+    }
+    // the field with the fully qualified `name`, only the nodes on the way to it
+    // are read. the pages of its widgets are those of their /P
+    fn find_field(&self, name: &str) -> Option<FormField> {
+        self.collect_form_fields(Some(name)).into_iter().next()
+    }
+    fn collect_form_fields(&self, target: Option<&str>) -> Vec<FormField> {
+        let mut fields = Vec::new();
+        let form = match self.acro_form() {
+            Some(form) => form,
+            None => return fields,
+        };
+        let widget_pages = match target {
+            Some(_) => HashMap::new(),
+            None => self.widget_pages(),
+        };
+        // the form level /DA and /Q are defaults for all fields
+        let inherited = Inherited {
+            da: self.lookup(form, PDF_NAME_DA),
+            q: self.lookup(form, PDF_NAME_Q),
+            ..Default::default()
+        };
+        let mut visited = HashSet::new();
+        if let Some(roots) = self.lookup(form, PDF_NAME_Fields).and_then(|v| v.as_array()) {
+            for root in roots {
+                if target.is_some() && !fields.is_empty() {
+                    break;
+                }
+                let pages = &widget_pages;
+                self.collect_fields(root, &inherited, target, pages, &mut visited, &mut fields, 0);
+            }
+        }
+        fields
+    }
+    // `target` limits the walk to the field of that name and its ancestors
+    #[allow(clippy::too_many_arguments)]
+    fn collect_fields<'a>(
+        &'a self,
+        node: &'a Value,
+        parent: &Inherited<'a>,
+        target: Option<&str>,
+        widget_pages: &HashMap<i32, i32>,
+        visited: &mut HashSet<i32>,
+        fields: &mut Vec<FormField>,
+        depth: i32,
+    ) {
+        let id = match node.as_object_id() {
+            Some(id) => id,
+            None => return,
+        };
+        if depth > 32 || !visited.insert(id.id) {
+            return;
+        }
+        let dict = match self.resolve(node).as_dict() {
+            Some(dict) => dict,
+            None => return,
+        };
+        let get = |key: &str| self.lookup(dict, key);
+        let partial_name = get(PDF_NAME_T).and_then(|v| v.as_text());
+        let mut inherited = parent.clone();
+        inherited.name = match (&parent.name, &partial_name) {
+            (Some(p), Some(t)) => Some(format!("{}.{}", p, t)),
+            (None, Some(t)) => Some(t.clone()),
+            (p, None) => p.clone(),
+        };
+        if let (Some(target), Some(name)) = (target, &inherited.name) {
+            let on_path = target == name
+                || (target.starts_with(name.as_str()) && target[name.len()..].starts_with('.'));
+            if !on_path {
+                return;
+            }
+        }
+        inherited.ft = get(PDF_NAME_FT).or(parent.ft);
+        inherited.ff = get(PDF_NAME_Ff).or(parent.ff);
+        inherited.v = get(PDF_NAME_V).or(parent.v);
+        inherited.dv = get(PDF_NAME_DV).or(parent.dv);
+        inherited.da = get(PDF_NAME_DA).or(parent.da);
+        inherited.q = get(PDF_NAME_Q).or(parent.q);
+        inherited.opt = get(PDF_NAME_Opt).or(parent.opt);
+        inherited.max_len = get(PDF_NAME_MaxLen).or(parent.max_len);
+
+        // kids with /T are fields, kids without are the widgets of this field
+        let kids = get(PDF_NAME_Kids).and_then(|v| v.as_array());
+        let mut widgets = Vec::new();
+        let mut has_child_fields = false;
+        for kid in kids.into_iter().flatten() {
+            let kid_dict = match self.resolve(kid).as_dict() {
+                Some(d) => d,
+                None => continue,
+            };
+            if kid_dict.contains_key(PDF_NAME_T) {
+                has_child_fields = true;
+                let depth = depth + 1;
+                self.collect_fields(kid, &inherited, target, widget_pages, visited, fields, depth);
+            } else {
+                widgets.push(self.field_widget(kid.as_object_id(), kid_dict, widget_pages));
+            }
+        }
+        if has_child_fields && widgets.is_empty() {
+            return;
+        }
+        if kids.is_none() {
+            // field and widget merged into one dictionary
+            widgets.push(self.field_widget(Some(id), dict, widget_pages));
+        }
+        if target.is_some_and(|t| Some(t) != inherited.name.as_deref()) {
+            return;
+        }
+        let flags = inherited.ff.and_then(|v| v.as_int()).unwrap_or(0);
+        let field_type = match inherited.ft.and_then(|v| v.as_name()) {
+            Some("Tx") => FieldType::TEXT,
+            Some("Btn") if flags & FIELD_FLAG_PUSHBUTTON != 0 => FieldType::PUSH_BUTTON,
+            Some("Btn") if flags & FIELD_FLAG_RADIO != 0 => FieldType::RADIO,
+            Some("Btn") => FieldType::CHECKBOX,
+            Some("Ch") if flags & FIELD_FLAG_COMBO != 0 => FieldType::COMBO,
+            Some("Ch") => FieldType::LIST,
+            Some("Sig") => FieldType::SIGNATURE,
+            _ => FieldType::UNKNOWN,
+        };
+        fields.push(FormField {
+            id,
+            name: inherited.name.clone().unwrap_or_default(),
+            partial_name,
+            alternate_name: get(PDF_NAME_TU).and_then(|v| v.as_text()),
+            field_type,
+            flags,
+            value: self.field_value(inherited.v),
+            default_value: self.field_value(inherited.dv),
+            options: inherited.opt.map(|v| self.field_options(v)).unwrap_or_default(),
+            max_len: inherited.max_len.and_then(|v| v.as_int()),
+            default_appearance: inherited.da.and_then(|v| v.as_text()),
+            quadding: inherited.q.and_then(|v| v.as_int()).unwrap_or(0),
+            widgets,
+        });
+    }
+    fn field_value(&self, val: Option<&Value>) -> FieldValue {
+        match val {
+            Some(Value::STRING(s)) => FieldValue::TEXT(decode_text(s)),
+            Some(Value::NAME(n)) => FieldValue::STATE(n.clone()),
+            // long text values may be stored in a stream
+            Some(Value::STREAM(s)) => FieldValue::TEXT(decode_text(&s.data)),
+            Some(Value::ARRAY(array)) => FieldValue::LIST(
+                array
+                    .iter()
+                    .filter_map(|v| self.resolve(v).as_text())
+                    .collect(),
+            ),
+            _ => FieldValue::NONE,
+        }
+    }
+    // /Opt items are either text or [export display] pairs
+    fn field_options(&self, opt: &Value) -> Vec<(String, String)> {
+        let mut options = Vec::new();
+        for item in opt.as_array().into_iter().flatten() {
+            match self.resolve(item) {
+                Value::STRING(s) => {
+                    let text = decode_text(s);
+                    options.push((text.clone(), text));
+                }
+                Value::ARRAY(pair) if pair.len() == 2 => {
+                    let export = self.resolve(&pair[0]).as_text().unwrap_or_default();
+                    let display = self.resolve(&pair[1]).as_text().unwrap_or_default();
+                    options.push((export, display));
+                }
+                _ => (),
+            }
+        }
+        options
+    }
+    fn field_widget(
+        &self,
+        id: Option<ObjectId>,
+        dict: &Dict,
+        widget_pages: &HashMap<i32, i32>,
+    ) -> FieldWidget {
+        let page = id
+            .and_then(|id| widget_pages.get(&id.id).copied())
+            .or_else(|| match dict.get(PDF_NAME_P) {
+                Some(Value::REF(n0, _)) => self.page_index(*n0),
+                _ => None,
+            });
+        let on_state = self
+            .lookup(dict, PDF_NAME_AP)
+            .and_then(|v| v.as_dict())
+            .and_then(|ap| self.lookup(ap, PDF_NAME_N))
+            .and_then(|v| v.as_dict())
+            .and_then(|n| n.keys().into_iter().find(|k| *k != "Off").map(String::from));
+        FieldWidget {
+            id,
+            page,
+            rect: self
+                .lookup(dict, PDF_NAME_Rect)
+                .and_then(Rect::from_value)
+                .unwrap_or(Rect::new(0.0, 0.0, 0.0, 0.0)),
+            on_state,
+        }
+    }
+    // widget annotation object id -> page index, /P is optional and often wrong
+    fn widget_pages(&self) -> HashMap<i32, i32> {
+        let mut map = HashMap::new();
+        for (no, id) in self.pages.iter().enumerate() {
+            let page = self.obj_list.get(id).and_then(|obj| obj.dict());
+            let annots = page
+                .and_then(|p| self.lookup(p, PDF_NAME_Annots))
+                .and_then(|v| v.as_array());
+            for annot in annots.into_iter().flatten() {
+                if let Value::REF(n0, _) = annot {
+                    let is_widget = self
+                        .resolve(annot)
+                        .as_dict()
+                        .and_then(|d| self.lookup(d, PDF_NAME_Subtype))
+                        .and_then(|v| v.as_name())
+                        == Some("Widget");
+                    if is_widget {
+                        map.insert(*n0, no as i32);
+                    }
+                }
+            }
+        }
+        map
+    }
+}
+
+const PDF_NAME_AS: &str = "AS";
+const PDF_NAME_MK: &str = "MK";
+const PDF_NAME_BG: &str = "BG";
+const PDF_NAME_BC: &str = "BC";
+const PDF_NAME_CA: &str = "CA";
+const PDF_NAME_R: &str = "R";
+const PDF_NAME_BS: &str = "BS";
+const PDF_NAME_W: &str = "W";
+const PDF_NAME_Border: &str = "Border";
+const PDF_NAME_F: &str = "F";
+const PDF_NAME_I: &str = "I";
+const PDF_NAME_TI: &str = "TI";
+const PDF_NAME_DR: &str = "DR";
+const PDF_NAME_Font: &str = "Font";
+const PDF_NAME_Resources: &str = "Resources";
+const PDF_NAME_XObject: &str = "XObject";
+const PDF_NAME_BBox: &str = "BBox";
+const PDF_NAME_Matrix: &str = "Matrix";
+
+// list box selection color used by Acrobat
+const LIST_HIGHLIGHT: &str = "0.6 0.75686 0.8549 rg";
+
+// how /DA and /MK ask a widget to be drawn
+struct WidgetStyle {
+    font: String,
+    font_size: f64,
+    // color operator from /DA, e.g. "0 g"
+    color: String,
+    background: Vec<f64>,
+    border: Vec<f64>,
+    border_width: f64,
+    rotation: i32,
+    caption: Option<String>,
+}
+
+impl WidgetStyle {
+    // background and border of the widget rectangle
+    fn frame(&self, w: f64, h: f64) -> String {
+        let mut out = String::new();
+        if let Some(op) = color_op(&self.background, false) {
+            out += &format!("{}\n0 0 {} {} re f\n", op, num(w), num(h));
+        }
+        if let Some(op) = color_op(&self.border, true) {
+            if self.border_width > 0.0 {
+                let bw = self.border_width;
+                out += &format!(
+                    "{} {} w\n{} {} {} {} re S\n",
+                    op,
+                    num(bw),
+                    num(bw / 2.0),
+                    num(bw / 2.0),
+                    num(w - bw),
+                    num(h - bw)
+                );
+            }
+        }
+        out
+    }
+}
+
+fn num(f: f64) -> String {
+    format_number(f)
+}
+
+fn color_op(color: &[f64], stroke: bool) -> Option<String> {
+    let op = match (color.len(), stroke) {
+        (1, false) => "g",
+        (1, true) => "G",
+        (3, false) => "rg",
+        (3, true) => "RG",
+        (4, false) => "k",
+        (4, true) => "K",
+        _ => return None,
+    };
+    let operands: Vec<String> = color.iter().map(|c| num(*c)).collect();
+    Some(format!("{} {}", operands.join(" "), op))
+}
+
+// font resource name, font size and color operator of a /DA string
+fn parse_da(da: &str) -> (String, f64, String) {
+    let mut font = "Helv".to_string();
+    let mut size = 0.0;
+    let mut color = "0 g".to_string();
+    let mut operands: Vec<&str> = Vec::new();
+    for tok in da.split_whitespace() {
+        match tok {
+            "Tf" if operands.len() >= 2 => {
+                font = operands[operands.len() - 2].trim_start_matches('/').to_string();
+                size = operands[operands.len() - 1].parse().unwrap_or(0.0);
+            }
+            "g" | "rg" | "k" => color = format!("{} {}", operands.join(" "), tok),
+            _ if tok.starts_with('/') || tok.parse::<f64>().is_ok() => {
+                operands.push(tok);
+                continue;
+            }
+            _ => (),
+        }
+        operands.clear();
+    }
+    (font, size, color)
+}
+
+// a font of the form's /DR as an appearance stream shows text in it
+struct AppearanceFont {
+    resource: Value,
+    font: Font,
+    // the code of each character the font's encoding has a glyph for
+    codes: HashMap<char, u8>,
+}
+
+impl AppearanceFont {
+    // characters the encoding lacks become '?'
+    fn encode(&self, text: &str) -> Vec<u8> {
+        text.chars()
+            .map(|c| self.codes.get(&c).copied().unwrap_or(b'?'))
+            .collect()
+    }
+    // width of font codes in text space units of 1000 per em
+    fn width(&self, text: &[u8]) -> f64 {
+        text.iter().map(|c| self.font.width(*c as u32) * 1000.0).sum()
+    }
+}
+
+impl Pdf {
+    /// Sets the value of the field with the fully qualified `name` and regenerates
+    /// the appearance of its widgets. Checkboxes and radio groups take a
+    /// `FieldValue::STATE` naming one of their widgets' on states, or "Off".
+    pub fn set_field_value(&mut self, name: &str, value: FieldValue) -> io::Result<()> {
+        let field = self
+            .find_field(name)
+            .ok_or_else(|| Error::new(ErrorKind::Other, "field not found"))?;
+        let value = field.check_value(value)?;
+        self.apply_field_value(field, value)
+    }
+    // stores a value already checked by FormField::check_value
+    pub(crate) fn apply_field_value(&mut self, mut field: FormField, value: FieldValue) -> io::Result<()> {
+        let v = value.to_value();
+        // /I holds the selected option indices of a list box
+        let indices: Vec<Value> = match &value {
+            FieldValue::TEXT(s) => vec![s.clone()],
+            FieldValue::LIST(items) => items.clone(),
+            _ => Vec::new(),
+        }
+        .iter()
+        .filter_map(|s| field.options.iter().position(|(export, _)| export == s))
+        .map(|i| Value::INTEGER(i as i32))
+        .collect();
+        let field_type = field.field_type;
+        let dict = self
+            .get_object_mut(field.id)
+            .and_then(|v| v.as_dict_mut())
+            .ok_or_else(|| Error::new(ErrorKind::Other, "field not found"))?;
+        match v {
+            Some(v) => dict.push(PDF_NAME_V.to_string(), v),
+            None => {
+                dict.remove(PDF_NAME_V);
+            }
+        }
+        if field_type == FieldType::LIST {
+            dict.remove(PDF_NAME_I);
+            if !indices.is_empty() {
+                dict.push(PDF_NAME_I.to_string(), Value::ARRAY(indices));
+            }
+        }
+        field.value = value;
+        self.update_appearance(&field)
+    }
+    /// Regenerates the normal appearance of every widget of a field from its value.
+    pub fn update_appearance(&mut self, field: &FormField) -> io::Result<()> {
+        self.field_appearance(field, &mut HashMap::new())
+    }
+    // `fonts` holds the fonts loaded so far by resource name
+    fn field_appearance(
+        &mut self,
+        field: &FormField,
+        fonts: &mut HashMap<String, AppearanceFont>,
+    ) -> io::Result<()> {
+        for widget in &field.widgets {
+            let id = match widget.id {
+                Some(id) => id,
+                None => continue,
+            };
+            match field.field_type {
+                FieldType::TEXT | FieldType::COMBO | FieldType::LIST => {
+                    self.text_appearance(field, id, fonts)?
+                }
+                FieldType::CHECKBOX | FieldType::RADIO => {
+                    let state = match &field.value {
+                        FieldValue::STATE(s) => s.as_str(),
+                        _ => "Off",
+                    };
+                    self.button_appearance(field, widget, state)?
+                }
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+    fn widget_style(&self, field: &FormField, widget: &Dict) -> WidgetStyle {
+        let da = self
+            .lookup(widget, PDF_NAME_DA)
+            .and_then(|v| v.as_text())
+            .or_else(|| field.default_appearance.clone())
+            .unwrap_or_default();
+        let (font, font_size, color) = parse_da(&da);
+        let mk = self.lookup(widget, PDF_NAME_MK).and_then(|v| v.as_dict());
+        let mk_get = |key: &str| mk.and_then(|mk| self.lookup(mk, key));
+        let colors = |key: &str| -> Vec<f64> {
+            mk_get(key)
+                .and_then(|v| v.as_array())
+                .map(|a| a.iter().filter_map(|v| self.resolve(v).as_f64()).collect())
+                .unwrap_or_default()
+        };
+        let border_width = self
+            .lookup(widget, PDF_NAME_BS)
+            .and_then(|v| v.as_dict())
+            .and_then(|bs| self.lookup(bs, PDF_NAME_W))
+            .and_then(|v| v.as_f64())
+            .or_else(|| {
+                self.lookup(widget, PDF_NAME_Border)
+                    .and_then(|v| v.as_array())
+                    .and_then(|a| a.get(2))
+                    .and_then(|v| v.as_f64())
+            })
+            .unwrap_or(1.0);
+        WidgetStyle {
+            font,
+            font_size,
+            color,
+            background: colors(PDF_NAME_BG),
+            border: colors(PDF_NAME_BC),
+            border_width,
+            rotation: mk_get(PDF_NAME_R)
+                .and_then(|v| v.as_int())
+                .unwrap_or(0)
+                .rem_euclid(360),
+            caption: mk_get(PDF_NAME_CA).and_then(|v| v.as_text()),
+        }
+    }
+    // the font resource `name` of the form's /DR, created as a standard font when missing
+    fn form_font(&mut self, name: &str, base_font: &str) -> io::Result<Value> {
+        let existing = self
+            .acro_form()
+            .and_then(|form| self.lookup(form, PDF_NAME_DR))
+            .and_then(|v| v.as_dict())
+            .and_then(|dr| self.lookup(dr, PDF_NAME_Font))
+            .and_then(|v| v.as_dict())
+            .and_then(|fonts| fonts.get(name))
+            .cloned();
+        if let Some(font) = existing {
+            return Ok(font);
+        }
+        let mut font = Dict::new();
+        font.push("Type".to_string(), Value::NAME("Font".to_string()));
+        font.push("Subtype".to_string(), Value::NAME("Type1".to_string()));
+        font.push("BaseFont".to_string(), Value::NAME(base_font.to_string()));
+        if base_font != "ZapfDingbats" {
+            font.push("Encoding".to_string(), Value::NAME("WinAnsiEncoding".to_string()));
+        }
+        let id = self.add_object(Value::DICT(font));
+        let font_ref = Value::REF(id.id, id.genid);
+        let root = self.object_id(self.root_id);
+        let fonts = self
+            .dict_path_mut(root, &[PDF_NAME_AcroForm, PDF_NAME_DR, PDF_NAME_Font])
+            .ok_or_else(|| Error::new(ErrorKind::Other, "no AcroForm"))?;
+        fonts.push(name.to_string(), font_ref.clone());
+        Ok(font_ref)
+    }
+    // form_font loaded with the reverse of its encoding, /WinAnsiEncoding for a
+    // created font. standard fonts without /Widths are measured with their AFM metrics
+    fn appearance_font(&mut self, name: &str, base_font: &str) -> io::Result<AppearanceFont> {
+        let resource = self.form_font(name, base_font)?;
+        let font = self.load_font(&resource);
+        let mut codes = HashMap::new();
+        for code in 1..=255u8 {
+            let text = font.unicode(code as u32);
+            let mut chars = text.chars();
+            if let (Some(c), None) = (chars.next(), chars.next()) {
+                codes.entry(c).or_insert(code);
+            }
+        }
+        Ok(AppearanceFont { resource, font, codes })
+    }
+    // a form XObject of size w x h drawn rotated by the widget's /MK /R
+    fn add_appearance_stream(
+        &mut self,
+        style: &WidgetStyle,
+        w: f64,
+        h: f64,
+        font: Option<(&str, Value)>,
+        content: Vec<u8>,
+    ) -> Value {
+        let mut dict = Dict::new();
+        dict.push("Type".to_string(), Value::NAME("XObject".to_string()));
+        dict.push("Subtype".to_string(), Value::NAME("Form".to_string()));
+        let bbox = [0.0, 0.0, w, h];
+        dict.push(
+            PDF_NAME_BBox.to_string(),
+            Value::ARRAY(bbox.iter().map(|n| Value::FLOAT(*n)).collect()),
+        );
+        let matrix: Option<[i32; 6]> = match style.rotation {
+            90 => Some([0, 1, -1, 0, 0, 0]),
+            180 => Some([-1, 0, 0, -1, 0, 0]),
+            270 => Some([0, -1, 1, 0, 0, 0]),
+            _ => None,
+        };
+        if let Some(m) = matrix {
+            dict.push(
+                PDF_NAME_Matrix.to_string(),
+                Value::ARRAY(m.iter().map(|n| Value::INTEGER(*n)).collect()),
+            );
+        }
+        if let Some((name, font_ref)) = font {
+            let mut fonts = Dict::new();
+            fonts.push(name.to_string(), font_ref);
+            let mut resources = Dict::new();
+            resources.push(PDF_NAME_Font.to_string(), Value::DICT(fonts));
+            dict.push(PDF_NAME_Resources.to_string(), Value::DICT(resources));
+        }
+        let id = self.add_object(Value::STREAM(Stream { dict, data: content }));
+        Value::REF(id.id, id.genid)
+    }
+    // widget rectangle size in the rotated appearance space
+    fn appearance_size(widget: &Dict, style: &WidgetStyle) -> (f64, f64) {
+        let rect = widget
+            .get(PDF_NAME_Rect)
+            .and_then(Rect::from_value)
+            .unwrap_or(Rect::new(0.0, 0.0, 0.0, 0.0));
+        if style.rotation == 90 || style.rotation == 270 {
+            (rect.height(), rect.width())
+        } else {
+            (rect.width(), rect.height())
+        }
+    }
+    // text, combo and list box appearance
+    fn text_appearance(
+        &mut self,
+        field: &FormField,
+        widget_id: ObjectId,
+        fonts: &mut HashMap<String, AppearanceFont>,
+    ) -> io::Result<()> {
+        let widget = self
+            .get_object(widget_id)
+            .and_then(|v| v.as_dict())
+            .cloned()
+            .ok_or_else(|| Error::new(ErrorKind::Other, "widget not found"))?;
+        let style = self.widget_style(field, &widget);
+        let (w, h) = Pdf::appearance_size(&widget, &style);
+        if !fonts.contains_key(&style.font) {
+            let font = self.appearance_font(&style.font, "Helvetica")?;
+            fonts.insert(style.font.clone(), font);
+        }
+        let font = &fonts[&style.font];
+        let pad = style.border_width.max(0.0) + 2.0;
+        let avail_w = (w - 2.0 * pad).max(0.0);
+        let avail_h = (h - 2.0 * pad).max(0.0);
+        let mut size = style.font_size;
+        let mut lines: Vec<(f64, f64, Vec<u8>)> = Vec::new();
+        let mut highlights: Vec<f64> = Vec::new();
+        let value_text = match &field.value {
+            FieldValue::TEXT(s) => s.clone(),
+            FieldValue::LIST(items) => items.join(", "),
+            _ => String::new(),
+        };
+        let align = |line_w: f64| match field.quadding {
+            1 => (w - line_w) / 2.0,
+            2 => w - pad - line_w,
+            _ => pad,
+        };
+        match field.field_type {
+            FieldType::LIST => {
+                if size <= 0.0 {
+                    size = 12.0;
+                }
+                let leading = size * 1.15;
+                let selected: Vec<&String> = match &field.value {
+                    FieldValue::TEXT(s) => vec![s],
+                    FieldValue::LIST(items) => items.iter().collect(),
+                    _ => Vec::new(),
+                };
+                let top = self
+                    .lookup(&widget, PDF_NAME_TI)
+                    .or_else(|| {
+                        self.get_object(field.id)
+                            .and_then(|v| v.as_dict())
+                            .and_then(|d| self.lookup(d, PDF_NAME_TI))
+                    })
+                    .and_then(|v| v.as_int())
+                    .unwrap_or(0)
+                    .max(0) as usize;
+                for (i, (export, display)) in field.options.iter().skip(top).enumerate() {
+                    let row_top = h - pad - i as f64 * leading;
+                    if row_top - leading < 0.0 {
+                        break;
+                    }
+                    if selected.contains(&export) {
+                        highlights.push(row_top - leading);
+                    }
+                    let text = font.encode(display);
+                    let line_w = font.width(&text) * size / 1000.0;
+                    lines.push((align(line_w), row_top - size, text));
+                }
+                let mut out = String::new();
+                for y in &highlights {
+                    out += &format!(
+                        "{}\n{} {} {} {} re f\n",
+                        LIST_HIGHLIGHT,
+                        num(pad - 1.0),
+                        num(*y),
+                        num(avail_w + 2.0),
+                        num(leading)
+                    );
+                }
+                let font = font.resource.clone();
+                return self.finish_text_appearance(widget_id, &style, w, h, pad, size, font, out, lines);
+            }
+            FieldType::COMBO => {
+                let display = field
+                    .options
+                    .iter()
+                    .find(|(export, _)| *export == value_text)
+                    .map(|(_, display)| display.clone())
+                    .unwrap_or(value_text);
+                let text = font.encode(&display);
+                let line_w = font.width(&text);
+                if size <= 0.0 {
+                    size = auto_font_size(avail_w, avail_h, line_w);
+                }
+                lines.push((align(line_w * size / 1000.0), h / 2.0 - 0.28 * size, text));
+            }
+            _ if field.has_flag(FIELD_FLAG_MULTILINE) => {
+                if size <= 0.0 {
+                    size = 12.0;
+                }
+                let leading = size * 1.15;
+                let text = if field.has_flag(FIELD_FLAG_PASSWORD) {
+                    "*".repeat(value_text.chars().count())
+                } else {
+                    value_text
+                };
+                let mut y = h - pad - size;
+                for line in wrap_text(font, &text, size, avail_w) {
+                    let line_w = font.width(&line) * size / 1000.0;
+                    lines.push((align(line_w), y, line));
+                    y -= leading;
+                }
+            }
+            _ => {
+                let text = if field.has_flag(FIELD_FLAG_PASSWORD) {
+                    "*".repeat(value_text.chars().count())
+                } else {
+                    value_text
+                };
+                let text = font.encode(&text);
+                let line_w = font.width(&text);
+                let cells = match field.max_len {
+                    Some(n) if n > 0 && field.has_flag(FIELD_FLAG_COMB) => n,
+                    _ => 0,
+                };
+                if cells > 0 {
+                    // one character centered in each of max_len cells
+                    if size <= 0.0 {
+                        size = (avail_h / 1.15).min(12.0);
+                    }
+                    let cell = w / cells as f64;
+                    for (i, c) in text.iter().enumerate().take(cells as usize) {
+                        let cw = font.width(&[*c]) * size / 1000.0;
+                        let x = i as f64 * cell + (cell - cw) / 2.0;
+                        lines.push((x, h / 2.0 - 0.28 * size, vec![*c]));
+                    }
+                } else {
+                    if size <= 0.0 {
+                        size = auto_font_size(avail_w, avail_h, line_w);
+                    }
+                    lines.push((align(line_w * size / 1000.0), h / 2.0 - 0.28 * size, text));
+                }
+            }
+        }
+        let (font, highlights) = (font.resource.clone(), String::new());
+        self.finish_text_appearance(widget_id, &style, w, h, pad, size, font, highlights, lines)
+    }
+    #[allow(clippy::too_many_arguments)]
+    fn finish_text_appearance(
+        &mut self,
+        widget_id: ObjectId,
+        style: &WidgetStyle,
+        w: f64,
+        h: f64,
+        pad: f64,
+        size: f64,
+        font: Value,
+        highlights: String,
+        lines: Vec<(f64, f64, Vec<u8>)>,
+    ) -> io::Result<()> {
+        let mut content = style.frame(w, h).into_bytes();
+        content.extend_from_slice(b"/Tx BMC\nq\n");
+        content.extend_from_slice(
+            format!(
+                "{} {} {} {} re W n\n",
+                num(pad - 1.0),
+                num(pad - 1.0),
+                num(w - 2.0 * pad + 2.0),
+                num(h - 2.0 * pad + 2.0)
+            )
+            .as_bytes(),
+        );
+        content.extend_from_slice(highlights.as_bytes());
+        content.extend_from_slice(
+            format!("BT\n/{} {} Tf\n{}\n", style.font, num(size), style.color).as_bytes(),
+        );
+        for (x, y, text) in lines {
+            content.extend_from_slice(format!("1 0 0 1 {} {} Tm\n", num(x), num(y)).as_bytes());
+            write_string(&mut content, &text);
+            content.extend_from_slice(b" Tj\n");
+        }
+        content.extend_from_slice(b"ET\nQ\nEMC\n");
+        let ap = self.add_appearance_stream(style, w, h, Some((&style.font, font)), content);
+        let mut ap_dict = Dict::new();
+        ap_dict.push(PDF_NAME_N.to_string(), ap);
+        if let Some(widget) = self.get_object_mut(widget_id).and_then(|v| v.as_dict_mut()) {
+            widget.push(PDF_NAME_AP.to_string(), Value::DICT(ap_dict));
+        }
+        Ok(())
+    }
+    // selects the widget's /AS, drawing on and off appearances when it has none
+    fn button_appearance(
+        &mut self,
+        field: &FormField,
+        widget: &FieldWidget,
+        state: &str,
+    ) -> io::Result<()> {
+        let id = match widget.id {
+            Some(id) => id,
+            None => return Ok(()),
+        };
+        let on_state = match &widget.on_state {
+            Some(on) => on.clone(),
+            None if state != "Off" => {
+                self.draw_button(field, id, state)?;
+                state.to_string()
+            }
+            None => String::new(),
+        };
+        let selected = if on_state == state { state } else { "Off" };
+        if let Some(dict) = self.get_object_mut(id).and_then(|v| v.as_dict_mut()) {
+            dict.push(PDF_NAME_AS.to_string(), Value::NAME(selected.to_string()));
+        }
+        Ok(())
+    }
+    fn draw_button(&mut self, field: &FormField, widget_id: ObjectId, on_state: &str) -> io::Result<()> {
+        let widget = self
+            .get_object(widget_id)
+            .and_then(|v| v.as_dict())
+            .cloned()
+            .ok_or_else(|| Error::new(ErrorKind::Other, "widget not found"))?;
+        let style = self.widget_style(field, &widget);
+        let (w, h) = Pdf::appearance_size(&widget, &style);
+        let font = self.form_font("ZaDb", "ZapfDingbats")?;
+        // ZapfDingbats check mark for checkboxes, filled circle for radio buttons
+        let default_caption = if field.field_type == FieldType::RADIO { "l" } else { "4" };
+        let caption = style.caption.clone().unwrap_or_else(|| default_caption.to_string());
+        let pad = style.border_width.max(0.0) + 1.0;
+        let size = if style.font_size > 0.0 {
+            style.font_size
+        } else {
+            (w.min(h) - 2.0 * pad).max(1.0) * 0.8
+        };
+        let frame = style.frame(w, h);
+        let mut on = frame.clone().into_bytes();
+        on.extend_from_slice(
+            format!(
+                "q\nBT\n/ZaDb {} Tf\n{}\n1 0 0 1 {} {} Tm\n",
+                num(size),
+                style.color,
+                num((w - size * 0.75) / 2.0),
+                num((h - size * 0.7) / 2.0)
+            )
+            .as_bytes(),
+        );
+        // the caption is in the font's built-in encoding, "4" a check mark
+        let caption: Vec<u8> =
+            caption.chars().filter(|c| (*c as u32) < 256).map(|c| c as u8).collect();
+        write_string(&mut on, &caption);
+        on.extend_from_slice(b" Tj\nET\nQ\n");
+        let on_ap = self.add_appearance_stream(&style, w, h, Some(("ZaDb", font)), on);
+        let off_ap = self.add_appearance_stream(&style, w, h, None, frame.into_bytes());
+        let mut states = Dict::new();
+        states.push(on_state.to_string(), on_ap);
+        states.push("Off".to_string(), off_ap);
+        let mut ap = Dict::new();
+        ap.push(PDF_NAME_N.to_string(), Value::DICT(states));
+        if let Some(dict) = self.get_object_mut(widget_id).and_then(|v| v.as_dict_mut()) {
+            dict.push(PDF_NAME_AP.to_string(), Value::DICT(ap));
+        }
+        Ok(())
+    }
+    /// Draws the widgets of all form fields into their pages' content and removes the
+    /// interactive form. Text and choice widgets without an appearance get one first.
+    pub fn flatten_form(&mut self) -> io::Result<()> {
+        let fields = self.form_fields();
+        let mut fonts = HashMap::new();
+        for field in &fields {
+            let text_like = matches!(
+                field.field_type,
+                FieldType::TEXT | FieldType::COMBO | FieldType::LIST
+            );
+            let missing_ap = field.widgets.iter().any(|w| {
+                w.id.and_then(|id| self.get_object(id))
+                    .and_then(|v| v.as_dict())
+                    .is_some_and(|d| !d.contains_key(PDF_NAME_AP))
+            });
+            if text_like && missing_ap {
+                self.field_appearance(field, &mut fonts)?;
+            }
+        }
+        let mut per_page: HashMap<i32, Vec<(ObjectId, Option<ObjectId>)>> = HashMap::new();
+        for widget in fields.iter().flat_map(|f| f.widgets.iter()) {
+            if let (Some(id), Some(page)) = (widget.id, widget.page) {
+                let ap = self.normal_appearance(id);
+                per_page.entry(page).or_default().push((id, ap));
+            }
+        }
+        let mut pages: Vec<i32> = per_page.keys().copied().collect();
+        pages.sort_unstable();
+        for no in pages {
+            self.flatten_page(no, &per_page[&no])?;
+        }
+        let root = self.object_id(self.root_id);
+        if let Some(catalog) = self.dict_path_mut(root, &[]) {
+            catalog.remove(PDF_NAME_AcroForm);
+        }
+        Ok(())
+    }
+    // the appearance stream a viewer shows for a widget, None when hidden
+    fn normal_appearance(&self, widget_id: ObjectId) -> Option<ObjectId> {
+        let widget = self.get_object(widget_id)?.as_dict()?;
+        let flags = self
+            .lookup(widget, PDF_NAME_F)
+            .and_then(|v| v.as_int())
+            .unwrap_or(0);
+        if flags & (ANNOT_FLAG_HIDDEN | ANNOT_FLAG_NO_VIEW) != 0 {
+            return None;
+        }
+        let ap = self.lookup(widget, PDF_NAME_AP)?.as_dict()?;
+        let normal = ap.get(PDF_NAME_N)?;
+        match self.resolve(normal) {
+            Value::STREAM(_) => normal.as_object_id(),
+            Value::DICT(states) => {
+                let state = self.lookup(widget, PDF_NAME_AS)?.as_name()?;
+                states.get(state)?.as_object_id()
+            }
+            _ => None,
+        }
+    }
+    fn flatten_page(&mut self, no: i32, widgets: &[(ObjectId, Option<ObjectId>)]) -> io::Result<()> {
+        let page_id = self
+            .page_id(no)
+            .ok_or_else(|| Error::new(ErrorKind::Other, "page out of range"))?;
+        let mut resources = inherited_attr(self, page_id, PDF_NAME_Resources)
+            .and_then(|v| v.as_dict())
+            .cloned()
+            .unwrap_or_default();
+        let mut xobjects = self
+            .lookup(&resources, PDF_NAME_XObject)
+            .and_then(|v| v.as_dict())
+            .cloned()
+            .unwrap_or_default();
+        // the page content may leave the graphics state changed, wrap it in q/Q
+        let mut content = b"Q\n".to_vec();
+        for (widget_id, ap) in widgets {
+            let ap = match ap {
+                Some(ap) => *ap,
+                None => continue,
+            };
+            let rect = match self
+                .get_object(*widget_id)
+                .and_then(|v| v.as_dict())
+                .and_then(|d| self.lookup(d, PDF_NAME_Rect))
+                .and_then(Rect::from_value)
+            {
+                Some(rect) => rect,
+                None => continue,
+            };
+            let form = match self.get_object(ap).and_then(|v| v.as_dict()) {
+                Some(form) => form,
+                None => continue,
+            };
+            let bbox = match self.lookup(form, PDF_NAME_BBox).and_then(Rect::from_value) {
+                Some(bbox) => bbox,
+                None => continue,
+            };
+            let m: Vec<f64> = self
+                .lookup(form, PDF_NAME_Matrix)
+                .and_then(|v| v.as_array())
+                .map(|a| a.iter().filter_map(|v| v.as_f64()).collect())
+                .filter(|m: &Vec<f64>| m.len() == 6)
+                .unwrap_or_else(|| vec![1.0, 0.0, 0.0, 1.0, 0.0, 0.0]);
+            // bounding box of the transformed form bbox, mapped onto the widget rect
+            let corners = [
+                (bbox.llx, bbox.lly),
+                (bbox.urx, bbox.lly),
+                (bbox.llx, bbox.ury),
+                (bbox.urx, bbox.ury),
+            ];
+            let points: Vec<(f64, f64)> = corners
+                .iter()
+                .map(|(x, y)| (m[0] * x + m[2] * y + m[4], m[1] * x + m[3] * y + m[5]))
+                .collect();
+            let x0 = points.iter().map(|p| p.0).fold(f64::INFINITY, f64::min);
+            let x1 = points.iter().map(|p| p.0).fold(f64::NEG_INFINITY, f64::max);
+            let y0 = points.iter().map(|p| p.1).fold(f64::INFINITY, f64::min);
+            let y1 = points.iter().map(|p| p.1).fold(f64::NEG_INFINITY, f64::max);
+            if x1 - x0 <= 0.0 || y1 - y0 <= 0.0 {
+                continue;
+            }
+            let sx = rect.width() / (x1 - x0);
+            let sy = rect.height() / (y1 - y0);
+            let ap_ref = Value::REF(ap.id, ap.genid);
+            let shared = xobjects
+                .iter()
+                .find(|(_, v)| v.as_object_id() == Some(ap))
+                .map(|(k, _)| k.to_string());
+            let name = match shared {
+                Some(name) => name,
+                None => {
+                    let mut k = xobjects.len();
+                    let name = loop {
+                        let name = format!("Fm{}", k);
+                        if !xobjects.contains_key(&name) {
+                            break name;
+                        }
+                        k += 1;
+                    };
+                    xobjects.push(name.clone(), ap_ref);
+                    name
+                }
+            };
+            content.extend_from_slice(
+                format!(
+                    "q {} 0 0 {} {} {} cm /{} Do Q\n",
+                    num(sx),
+                    num(sy),
+                    num(rect.llx - x0 * sx),
+                    num(rect.lly - y0 * sy),
+                    name
+                )
+                .as_bytes(),
+            );
+        }
+        resources.push(PDF_NAME_XObject.to_string(), Value::DICT(xobjects));
+        let begin = self.add_object(Value::STREAM(Stream::new(b"q\n".to_vec())));
+        let end = self.add_object(Value::STREAM(Stream::new(content)));
+        let page = self.object_id(page_id);
+        let page_dict = self
+            .get_object(page)
+            .and_then(|v| v.as_dict())
+            .ok_or_else(|| Error::new(ErrorKind::Other, "page out of range"))?;
+        let mut contents = vec![Value::REF(begin.id, begin.genid)];
+        if let Some(v) = page_dict.get(PDF_NAME_Contents) {
+            match self.resolve(v) {
+                Value::ARRAY(array) => contents.extend(array.iter().cloned()),
+                Value::STREAM(_) => contents.push(v.clone()),
+                _ => (),
+            }
+        }
+        contents.push(Value::REF(end.id, end.genid));
+        let annots: Vec<Value> = self
+            .lookup(page_dict, PDF_NAME_Annots)
+            .and_then(|v| v.as_array())
+            .map(|a| {
+                a.iter()
+                    .filter(|v| !widgets.iter().any(|(id, _)| v.as_object_id() == Some(*id)))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default();
+        let page_dict = self
+            .dict_path_mut(page, &[])
+            .ok_or_else(|| Error::new(ErrorKind::Other, "page out of range"))?;
+        page_dict.push(PDF_NAME_Resources.to_string(), Value::DICT(resources));
+        page_dict.push(PDF_NAME_Contents.to_string(), Value::ARRAY(contents));
+        if annots.is_empty() {
+            page_dict.remove(PDF_NAME_Annots);
+        } else {
+            page_dict.push(PDF_NAME_Annots.to_string(), Value::ARRAY(annots));
+        }
+        Ok(())
+    }
+}
+
+// greedy word wrap, explicit line breaks are kept
+fn wrap_text(font: &AppearanceFont, text: &str, size: f64, width: f64) -> Vec<Vec<u8>> {
+    let mut lines = Vec::new();
+    for paragraph in text.split('\n') {
+        let mut line: Vec<u8> = Vec::new();
+        for word in font.encode(paragraph.trim_end_matches('\r')).split(|c| *c == b' ') {
+            let mut candidate = line.clone();
+            if !candidate.is_empty() {
+                candidate.push(b' ');
+            }
+            candidate.extend_from_slice(word);
+            if !line.is_empty() && font.width(&candidate) * size / 1000.0 > width {
+                lines.push(line);
+                line = word.to_vec();
+            } else {
+                line = candidate;
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+// font size filling the field height, shrunk so that the text fits its width
+fn auto_font_size(avail_w: f64, avail_h: f64, text_width: f64) -> f64 {
+    let mut size = (avail_h / 1.15).min(12.0);
+    if text_width > 0.0 {
+        size = size.min(avail_w * 1000.0 / text_width);
+    }
+    size.max(4.0_f64.min(avail_h))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // text fields under a common parent, a checkbox, a radio group and a
+    // multiple selection list box
+    const FORM: &str = r"
+1 0 obj << /Type /Catalog /Pages 2 0 R
+  /AcroForm << /Fields [10 0 R 20 0 R 30 0 R 40 0 R] /DA (/Helv 0 Tf 0 g) /Q 1
+  /DR << /Font << /Cust 50 0 R >> >> >> >> endobj
+2 0 obj << /Type /Pages /Kids [3 0 R] /Count 1 >> endobj
+3 0 obj << /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] /Contents 4 0 R
+  /Annots [11 0 R 12 0 R 20 0 R 31 0 R 32 0 R 40 0 R] >> endobj
+4 0 obj << >> stream
+0 0 m 1 1 l S
+endstream endobj
+10 0 obj << /T (person) /FT /Tx /Kids [11 0 R 12 0 R] >> endobj
+11 0 obj << /T (first) /Parent 10 0 R /Subtype /Widget /Rect [10 700 110 720] /V (Ann)
+  /MaxLen 5 >> endobj
+12 0 obj << /T (last) /Parent 10 0 R /Subtype /Widget /Rect [10 670 110 690] /Q 0
+  /TU (Family name) /F 2 >> endobj
+20 0 obj << /T (agree) /FT /Btn /V /Off /Subtype /Widget /Rect [10 640 22 652] /AS /Off
+  /AP << /N << /Yes 21 0 R /Off 22 0 R >> >> >> endobj
+21 0 obj << /Type /XObject /Subtype /Form /BBox [0 0 12 12] >> stream
+0 0 12 12 re f
+endstream endobj
+22 0 obj << /Type /XObject /Subtype /Form /BBox [0 0 12 12] >> stream
+endstream endobj
+30 0 obj << /T (color) /FT /Btn /Ff 49152 /Kids [31 0 R 32 0 R] /V /Red >> endobj
+31 0 obj << /Parent 30 0 R /Subtype /Widget /Rect [10 600 22 612] /AS /Red
+  /AP << /N << /Red 21 0 R /Off 22 0 R >> >> >> endobj
+32 0 obj << /Parent 30 0 R /Subtype /Widget /Rect [30 600 42 612] /AS /Off
+  /AP << /N << /Blue 21 0 R /Off 22 0 R >> >> >> endobj
+40 0 obj << /T (fruit) /FT /Ch /Ff 2097152 /Opt [(Apple) [(p) (Pear)]] /Subtype /Widget
+  /Rect [10 500 110 560] /DA (/Helv 10 Tf 0 g) >> endobj
+50 0 obj << /Type /Font /Subtype /Type1 /BaseFont /Helvetica
+  /Encoding << /Differences [65 /Euro /quotedblleft] >> >> endobj
+";
+
+    fn widget(pdf: &Pdf, id: i32) -> &Dict {
+        pdf.get_object(ObjectId::new(id, 0)).and_then(|v| v.as_dict()).unwrap()
+    }
+
+    #[test]
+    fn fields() {
+        let pdf = test_pdf(FORM);
+        let fields = pdf.form_fields();
+        let names: Vec<(&str, FieldType)> =
+            fields.iter().map(|f| (f.name.as_str(), f.field_type)).collect();
+        assert_eq!(
+            names,
+            [
+                ("person.first", FieldType::TEXT),
+                ("person.last", FieldType::TEXT),
+                ("agree", FieldType::CHECKBOX),
+                ("color", FieldType::RADIO),
+                ("fruit", FieldType::LIST)
+            ]
+        );
+        let first = &fields[0];
+        assert_eq!(first.partial_name.as_deref(), Some("first"));
+        assert_eq!(first.value, FieldValue::TEXT("Ann".to_string()));
+        assert_eq!(first.max_len, Some(5));
+        // /DA and /Q from the form
+        assert_eq!(first.default_appearance.as_deref(), Some("/Helv 0 Tf 0 g"));
+        assert_eq!((first.quadding, fields[1].quadding), (1, 0));
+        assert_eq!(fields[1].alternate_name.as_deref(), Some("Family name"));
+        assert_eq!(fields[1].value, FieldValue::NONE);
+        assert_eq!(fields[2].widgets[0].on_state.as_deref(), Some("Yes"));
+        let color = &fields[3];
+        assert!(color.has_flag(FIELD_FLAG_NO_TOGGLE_TO_OFF));
+        assert_eq!(color.value, FieldValue::STATE("Red".to_string()));
+        let pages: Vec<Option<i32>> = color.widgets.iter().map(|w| w.page).collect();
+        assert_eq!(pages, [Some(0), Some(0)]);
+        let options =
+            [("Apple", "Apple"), ("p", "Pear")].map(|(e, d)| (e.to_string(), d.to_string()));
+        assert_eq!(fields[4].options, options);
+        assert!(test_pdf("1 0 obj << /Type /Catalog >> endobj").form_fields().is_empty());
+    }
+
+    #[test]
+    fn set_values() {
+        let mut pdf = test_pdf(FORM);
+        pdf.set_field_value("person.first", FieldValue::TEXT("Annabelle".to_string())).unwrap();
+        assert_eq!(pdf.form_fields()[0].value, FieldValue::TEXT("Annab".to_string()));
+        let ap = pdf.lookup(widget(&pdf, 11), "AP").and_then(|v| v.as_dict()).unwrap();
+        let normal = pdf.lookup(ap, "N").and_then(|v| v.as_stream()).unwrap();
+        assert!(String::from_utf8_lossy(&normal.data).contains("(Annab) Tj"));
+        let helv = pdf
+            .acro_form()
+            .and_then(|f| pdf.lookup(f, "DR"))
+            .and_then(|v| v.as_dict())
+            .and_then(|dr| pdf.lookup(dr, "Font"))
+            .and_then(|v| v.as_dict())
+            .and_then(|fonts| fonts.get("Helv"));
+        assert!(helv.is_some());
+
+        assert!(pdf.set_field_value("agree", FieldValue::STATE("Maybe".to_string())).is_err());
+        assert!(pdf.set_field_value("agree", FieldValue::TEXT("Yes".to_string())).is_err());
+        pdf.set_field_value("agree", FieldValue::STATE("Yes".to_string())).unwrap();
+        assert_eq!(widget(&pdf, 20).get("AS").and_then(|v| v.as_name()), Some("Yes"));
+        pdf.set_field_value("color", FieldValue::STATE("Blue".to_string())).unwrap();
+        assert_eq!(widget(&pdf, 31).get("AS").and_then(|v| v.as_name()), Some("Off"));
+        assert_eq!(widget(&pdf, 32).get("AS").and_then(|v| v.as_name()), Some("Blue"));
+        let items = vec!["p".to_string(), "Apple".to_string()];
+        pdf.set_field_value("fruit", FieldValue::LIST(items)).unwrap();
+        assert!(matches!(
+            widget(&pdf, 40).get("I").and_then(|v| v.as_array()).map(|a| &a[..]),
+            Some([Value::INTEGER(1), Value::INTEGER(0)])
+        ));
+        assert!(pdf.set_field_value("missing", FieldValue::NONE).is_err());
+    }
+
+    #[test]
+    fn flatten() {
+        let mut pdf = test_pdf(FORM);
+        pdf.set_field_value("fruit", FieldValue::TEXT("p".to_string())).unwrap();
+        pdf.flatten_form().unwrap();
+        assert!(pdf.acro_form().is_none());
+        assert!(pdf.annotations(0).unwrap().is_empty());
+        let ops = pdf.page_operations(0).unwrap();
+        let names: Vec<&str> = ops.iter().map(|o| o.operator.as_str()).collect();
+        assert_eq!(&names[..4], ["q", "m", "l", "S"]);
+        // the hidden last name is left out
+        assert_eq!(names.iter().filter(|n| **n == "Do").count(), 5);
+        let text = pdf.page_text(0);
+        assert!(text.contains("Ann") && text.contains("Pear"));
+    }
+
+    #[test]
+    fn appearance_strings() {
+        let da = ("Helv".to_string(), 12.0, "1 0 0 rg".to_string());
+        assert_eq!(parse_da("/Helv 12 Tf 1 0 0 rg"), da);
+        assert_eq!(parse_da("0.5 g /Cour 0 Tf"), ("Cour".to_string(), 0.0, "0.5 g".to_string()));
+        assert_eq!(parse_da(""), ("Helv".to_string(), 0.0, "0 g".to_string()));
+        assert_eq!(color_op(&[1.0, 0.0, 0.0], true).as_deref(), Some("1 0 0 RG"));
+        assert_eq!(color_op(&[], false), None);
+        assert_eq!(auto_font_size(100.0, 16.0, 1000.0), 12.0);
+        assert_eq!(auto_font_size(10.0, 16.0, 2000.0), 5.0);
+        assert_eq!(auto_font_size(1.0, 16.0, 2000.0), 4.0);
+    }
+
+    #[test]
+    fn encodings() {
+        let mut pdf = test_pdf(FORM);
+        // a created font is WinAnsi encoded
+        let helv = pdf.appearance_font("Helv", "Helvetica").unwrap();
+        let text = "a\u{e9}\u{20ac}\u{201c}\u{201d}\u{2026}\u{2013}\u{2014}\u{160}\u{2603}";
+        assert_eq!(helv.encode(text), b"a\xe9\x80\x93\x94\x85\x96\x97\x8a?");
+        assert_eq!(helv.width(b"\x80\x97"), 556.0 + 1000.0);
+        // an existing one keeps its /Encoding, here /StandardEncoding with differences
+        let cust = pdf.appearance_font("Cust", "Helvetica").unwrap();
+        assert_eq!(cust.encode("\u{20ac}\u{201c}AB\u{e9}"), b"AB???");
+        assert_eq!(cust.resource.as_object_id(), Some(ObjectId::new(50, 0)));
+
+        let quoted = FieldValue::TEXT("\u{201c}\u{160}\u{201d}".to_string());
+        pdf.set_field_value("person.last", quoted).unwrap();
+        let ap = pdf.lookup(widget(&pdf, 12), "AP").and_then(|v| v.as_dict()).unwrap();
+        let normal = pdf.lookup(ap, "N").and_then(|v| v.as_stream()).unwrap();
+        assert!(normal.data.windows(5).any(|w| w == b"(\x93\x8a\x94)"));
+        let lines = wrap_text(&helv, "aaaa aaaa aaaa\nb", 10.0, 60.0);
+        assert_eq!(lines, [&b"aaaa aaaa"[..], b"aaaa", b"b"]);
+    }
+
+    #[test]
+    fn find_by_name() {
+        let pdf = test_pdf(FORM);
+        let first = pdf.find_field("person.first").unwrap();
+        assert_eq!((first.id, first.max_len), (ObjectId::new(11, 0), Some(5)));
+        assert_eq!(first.default_appearance.as_deref(), Some("/Helv 0 Tf 0 g"));
+        assert_eq!(pdf.find_field("color").unwrap().widgets.len(), 2);
+        // only terminal fields, by their whole name
+        for name in ["person", "person.fir", "first", "person.first.x", ""].iter() {
+            assert!(pdf.find_field(name).is_none(), "{}", name);
+        }
+    }
+}
@@ -0,0 +1,556 @@
+use super::*;
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const PDF_NAME_Info: &str = "Info";
+const PDF_NAME_Metadata: &str = "Metadata";
+
+/// A date from a PDF date string (D:YYYYMMDDHHmmSSOHH'mm') or an XMP date.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateTime {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+    /// Offset from UTC in minutes, None when the string has no time zone.
+    pub utc_offset: Option<i32>,
+}
+
+impl DateTime {
+    /// Parses a PDF date string, fields after the year are optional.
+    pub fn parse(s: &str) -> Option<DateTime> {
+        let s = s.trim();
+        let s = s.strip_prefix("D:").unwrap_or(s);
+        let digits = |from: usize, len: usize| -> Option<u32> {
+            let part = s.get(from..from + len)?;
+            if part.bytes().all(|c| c.is_ascii_digit()) {
+                part.parse().ok()
+            } else {
+                None
+            }
+        };
+        let year = digits(0, 4)? as i32;
+        let mut date = DateTime {
+            year,
+            month: 1,
+            day: 1,
+            hour: 0,
+            minute: 0,
+            second: 0,
+            utc_offset: None,
+        };
+        let mut pos = 4;
+        for field in [
+            &mut date.month,
+            &mut date.day,
+            &mut date.hour,
+            &mut date.minute,
+            &mut date.second,
+        ] {
+            match digits(pos, 2) {
+                Some(v) => *field = v,
+                None => break,
+            }
+            pos += 2;
+        }
+        date.utc_offset = match s.as_bytes().get(pos) {
+            Some(b'Z') => Some(0),
+            Some(c @ b'+') | Some(c @ b'-') => {
+                let hours = digits(pos + 1, 2).unwrap_or(0) as i32;
+                // HH'mm' with the apostrophes optional
+                let rest = s.get(pos + 3..).unwrap_or("");
+                let rest = rest.strip_prefix('\'').unwrap_or(rest);
+                let minutes = rest
+                    .get(0..2)
+                    .and_then(|m| m.parse::<i32>().ok())
+                    .unwrap_or(0);
+                let offset = hours * 60 + minutes;
+                Some(if *c == b'-' { -offset } else { offset })
+            }
+            _ => None,
+        };
+        if date.is_valid() {
+            Some(date)
+        } else {
+            None
+        }
+    }
+    /// Parses an ISO 8601 date as used by XMP: YYYY[-MM[-DD[THH:mm[:SS[.s]][TZD]]]].
+    pub fn parse_iso8601(s: &str) -> Option<DateTime> {
+        let s = s.trim();
+        let (date_part, time_part) = match s.find('T') {
+            Some(i) => (&s[..i], Some(&s[i + 1..])),
+            None => (s, None),
+        };
+        let mut d = date_part.split('-');
+        let year: i32 = d.next()?.parse().ok()?;
+        let month: u32 = d.next().map(|m| m.parse().ok()).unwrap_or(Some(1))?;
+        let day: u32 = d.next().map(|m| m.parse().ok()).unwrap_or(Some(1))?;
+        let mut date = DateTime {
+            year,
+            month,
+            day,
+            hour: 0,
+            minute: 0,
+            second: 0,
+            utc_offset: None,
+        };
+        if let Some(t) = time_part {
+            let zone_at = t.find(['Z', '+', '-']).unwrap_or(t.len());
+            let mut hms = t[..zone_at].split(':');
+            date.hour = hms.next()?.parse().ok()?;
+            date.minute = hms.next().map(|m| m.parse().ok()).unwrap_or(Some(0))?;
+            date.second = match hms.next() {
+                // fractions of seconds are dropped
+                Some(sec) => sec.split('.').next()?.parse().ok()?,
+                None => 0,
+            };
+            let zone = &t[zone_at..];
+            date.utc_offset = if zone == "Z" {
+                Some(0)
+            } else if zone.len() > 1 {
+                let mut hm = zone[1..].split(':');
+                let hours: i32 = hm.next()?.parse().ok()?;
+                let minutes: i32 = hm.next().map(|m| m.parse().ok()).unwrap_or(Some(0))?;
+                let offset = hours * 60 + minutes;
+                Some(if zone.starts_with('-') { -offset } else { offset })
+            } else {
+                None
+            };
+        }
+        if date.is_valid() {
+            Some(date)
+        } else {
+            None
+        }
+    }
+    fn is_valid(&self) -> bool {
+        (1..=12).contains(&self.month)
+            && (1..=31).contains(&self.day)
+            && self.hour < 24
+            && self.minute < 60
+            && self.second < 61
+    }
+    /// Seconds since 1970-01-01T00:00:00Z, a date without zone is taken as UTC.
+    pub fn unix_time(&self) -> i64 {
+        // days from civil, proleptic gregorian calendar
+        let y = if self.month <= 2 { self.year - 1 } else { self.year } as i64;
+        let era = if y >= 0 { y } else { y - 399 } / 400;
+        let yoe = y - era * 400;
+        let m = self.month as i64;
+        let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + self.day as i64 - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        let days = era * 146097 + doe - 719468;
+        days * 86400 + self.hour as i64 * 3600 + self.minute as i64 * 60 + self.second as i64
+            - self.utc_offset.unwrap_or(0) as i64 * 60
+    }
+    /// The UTC date of a number of seconds since 1970-01-01T00:00:00Z.
+    pub fn from_unix_time(secs: i64) -> DateTime {
+        // civil from days, the inverse of unix_time
+        let days = secs.div_euclid(86400);
+        let time = secs.rem_euclid(86400);
+        let z = days + 719468;
+        let era = if z >= 0 { z } else { z - 146096 } / 146097;
+        let doe = z - era * 146097;
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+        DateTime {
+            year: year as i32,
+            month: month as u32,
+            day: day as u32,
+            hour: (time / 3600) as u32,
+            minute: (time / 60 % 60) as u32,
+            second: (time % 60) as u32,
+            utc_offset: Some(0),
+        }
+    }
+    /// The current date in UTC.
+    pub fn now() -> DateTime {
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs() as i64);
+        DateTime::from_unix_time(secs)
+    }
+    /// The PDF date string, D:YYYYMMDDHHmmSSOHH'mm'.
+    pub fn to_pdf_string(&self) -> String {
+        let mut s = format!(
+            "D:{:04}{:02}{:02}{:02}{:02}{:02}",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        );
+        match self.utc_offset {
+            Some(0) => s.push('Z'),
+            Some(off) => {
+                let sign = if off < 0 { '-' } else { '+' };
+                s.push_str(&format!("{}{:02}'{:02}'", sign, off.abs() / 60, off.abs() % 60));
+            }
+            None => (),
+        }
+        s
+    }
+}
+
+impl fmt::Display for DateTime {
+    // ISO 8601
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )?;
+        match self.utc_offset {
+            Some(0) => write!(f, "Z"),
+            Some(off) => {
+                let sign = if off < 0 { '-' } else { '+' };
+                write!(f, "{}{:02}:{:02}", sign, off.abs() / 60, off.abs() % 60)
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+/// The trailer /Info dictionary.
+#[derive(Debug, Clone, Default)]
+pub struct DocumentInfo {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub subject: Option<String>,
+    pub keywords: Option<String>,
+    pub creator: Option<String>,
+    pub producer: Option<String>,
+    pub creation_date: Option<DateTime>,
+    pub mod_date: Option<DateTime>,
+}
+
+/// Dublin Core, XMP basic and Adobe PDF schema properties of the catalog /Metadata packet.
+#[derive(Debug, Clone, Default)]
+pub struct XmpMetadata {
+    // dc:
+    pub title: Option<String>,
+    pub creators: Vec<String>,
+    pub description: Option<String>,
+    pub subjects: Vec<String>,
+    pub rights: Option<String>,
+    pub format: Option<String>,
+    // xmp:
+    pub creator_tool: Option<String>,
+    pub create_date: Option<DateTime>,
+    pub modify_date: Option<DateTime>,
+    pub metadata_date: Option<DateTime>,
+    // pdf:
+    pub producer: Option<String>,
+    pub keywords: Option<String>,
+}
+
+/// Document metadata merged from /Info and XMP. The newer source wins,
+/// fields it lacks are taken from the other one.
+#[derive(Debug, Clone, Default)]
+pub struct Metadata {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub subject: Option<String>,
+    pub keywords: Option<String>,
+    pub creator: Option<String>,
+    pub producer: Option<String>,
+    pub creation_date: Option<DateTime>,
+    pub mod_date: Option<DateTime>,
+    /// Names of the fields where /Info and XMP disagree.
+    pub conflicts: Vec<&'static str>,
+}
+
+impl Pdf {
+    pub fn info(&self) -> DocumentInfo {
+        let dict = match self
+            .trailer()
+            .and_then(|t| self.lookup(t, PDF_NAME_Info))
+            .and_then(|v| v.as_dict())
+        {
+            Some(dict) => dict,
+            None => return DocumentInfo::default(),
+        };
+        let text = |key: &str| self.lookup(dict, key).and_then(|v| v.as_text());
+        let date = |key: &str| text(key).and_then(|s| DateTime::parse(&s));
+        DocumentInfo {
+            title: text("Title"),
+            author: text("Author"),
+            subject: text("Subject"),
+            keywords: text("Keywords"),
+            creator: text("Creator"),
+            producer: text("Producer"),
+            creation_date: date("CreationDate"),
+            mod_date: date("ModDate"),
+        }
+    }
+    /// The raw XMP packet of the catalog /Metadata stream.
+    pub fn xmp_packet(&self) -> Option<&[u8]> {
+        self.catalog()
+            .and_then(|c| self.lookup(c, PDF_NAME_Metadata))
+            .and_then(|v| v.as_stream())
+            .map(|s| &s.data[..])
+    }
+    pub fn xmp_metadata(&self) -> Option<XmpMetadata> {
+        let packet = String::from_utf8_lossy(self.xmp_packet()?).into_owned();
+        let root = xml::parse(&packet)?;
+        let mut descriptions = Vec::new();
+        if root.name == "rdf:Description" {
+            descriptions.push(&root);
+        }
+        root.find_all("rdf:Description", &mut descriptions);
+        let find = |names: &[&str]| -> Vec<String> {
+            for desc in &descriptions {
+                for name in names {
+                    if let Some(v) = desc.attr(name) {
+                        return vec![v.to_string()];
+                    }
+                    if let Some(prop) = desc.child(name) {
+                        return xmp_values(prop);
+                    }
+                }
+            }
+            Vec::new()
+        };
+        let first = |names: &[&str]| find(names).into_iter().next();
+        let date = |names: &[&str]| first(names).and_then(|s| DateTime::parse_iso8601(&s));
+        Some(XmpMetadata {
+            title: first(&["dc:title"]),
+            creators: find(&["dc:creator"]),
+            description: first(&["dc:description"]),
+            subjects: find(&["dc:subject"]),
+            rights: first(&["dc:rights"]),
+            format: first(&["dc:format"]),
+            // xap: is the old prefix of the xmp: schema
+            creator_tool: first(&["xmp:CreatorTool", "xap:CreatorTool"]),
+            create_date: date(&["xmp:CreateDate", "xap:CreateDate"]),
+            modify_date: date(&["xmp:ModifyDate", "xap:ModifyDate"]),
+            metadata_date: date(&["xmp:MetadataDate", "xap:MetadataDate"]),
+            producer: first(&["pdf:Producer"]),
+            keywords: first(&["pdf:Keywords"]),
+        })
+    }
+    /// /Info and XMP reconciled into one view.
+    pub fn metadata(&self) -> Metadata {
+        let info = self.info();
+        let xmp = match self.xmp_metadata() {
+            Some(xmp) => xmp,
+            None => {
+                return Metadata {
+                    title: info.title,
+                    author: info.author,
+                    subject: info.subject,
+                    keywords: info.keywords,
+                    creator: info.creator,
+                    producer: info.producer,
+                    creation_date: info.creation_date,
+                    mod_date: info.mod_date,
+                    conflicts: Vec::new(),
+                }
+            }
+        };
+        let xmp_author = if xmp.creators.is_empty() {
+            None
+        } else {
+            Some(xmp.creators.join("; "))
+        };
+        let xmp_keywords = xmp.keywords.clone().or_else(|| {
+            if xmp.subjects.is_empty() {
+                None
+            } else {
+                Some(xmp.subjects.join(", "))
+            }
+        });
+        // a tool that is not XMP aware may have updated /Info after the packet
+        let xmp_date = xmp.metadata_date.or(xmp.modify_date);
+        let info_first = match (info.mod_date, xmp_date) {
+            (Some(i), Some(x)) => i.unix_time() > x.unix_time(),
+            (Some(_), None) => true,
+            _ => false,
+        };
+        let mut conflicts = Vec::new();
+        let mut pick_text = |name: &'static str, i: Option<String>, x: Option<String>| {
+            if let (Some(a), Some(b)) = (&i, &x) {
+                if a.trim() != b.trim() {
+                    conflicts.push(name);
+                }
+            }
+            if info_first {
+                i.or(x)
+            } else {
+                x.or(i)
+            }
+        };
+        let title = pick_text("title", info.title, xmp.title);
+        let author = pick_text("author", info.author, xmp_author);
+        let subject = pick_text("subject", info.subject, xmp.description);
+        let keywords = pick_text("keywords", info.keywords, xmp_keywords);
+        let creator = pick_text("creator", info.creator, xmp.creator_tool);
+        let producer = pick_text("producer", info.producer, xmp.producer);
+        let mut pick_date = |name: &'static str, i: Option<DateTime>, x: Option<DateTime>| {
+            if let (Some(a), Some(b)) = (&i, &x) {
+                if a.unix_time() != b.unix_time() {
+                    conflicts.push(name);
+                }
+            }
+            if info_first {
+                i.or(x)
+            } else {
+                x.or(i)
+            }
+        };
+        let creation_date = pick_date("creation_date", info.creation_date, xmp.create_date);
+        let mod_date = pick_date("mod_date", info.mod_date, xmp.modify_date);
+        Metadata {
+            title,
+            author,
+            subject,
+            keywords,
+            creator,
+            producer,
+            creation_date,
+            mod_date,
+            conflicts,
+        }
+    }
+}
+
+// rdf:Alt (x-default first), rdf:Seq and rdf:Bag items, or the simple value
+fn xmp_values(prop: &xml::Element) -> Vec<String> {
+    for container in ["rdf:Alt", "rdf:Seq", "rdf:Bag"] {
+        if let Some(c) = prop.child(container) {
+            let mut items: Vec<&xml::Element> =
+                c.children.iter().filter(|e| e.name == "rdf:li").collect();
+            if container == "rdf:Alt" {
+                if let Some(i) = items
+                    .iter()
+                    .position(|e| e.attr("xml:lang") == Some("x-default"))
+                {
+                    let default = items.remove(i);
+                    items.insert(0, default);
+                }
+            }
+            return items.iter().map(|e| e.text.trim().to_string()).collect();
+        }
+    }
+    vec![prop.text.trim().to_string()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32, hms: (u32, u32, u32), off: Option<i32>) -> DateTime {
+        DateTime {
+            year,
+            month,
+            day,
+            hour: hms.0,
+            minute: hms.1,
+            second: hms.2,
+            utc_offset: off,
+        }
+    }
+
+    #[test]
+    fn pdf_date() {
+        assert_eq!(
+            DateTime::parse("D:20230415103005+02'30'"),
+            Some(date(2023, 4, 15, (10, 30, 5), Some(150)))
+        );
+        assert_eq!(
+            DateTime::parse("D:19991231235959-08'00"),
+            Some(date(1999, 12, 31, (23, 59, 59), Some(-480)))
+        );
+        assert_eq!(
+            DateTime::parse("D:20230415103005Z"),
+            Some(date(2023, 4, 15, (10, 30, 5), Some(0)))
+        );
+        assert_eq!(DateTime::parse("2023"), Some(date(2023, 1, 1, (0, 0, 0), None)));
+        assert_eq!(DateTime::parse("D:202304"), Some(date(2023, 4, 1, (0, 0, 0), None)));
+        assert_eq!(DateTime::parse("D:20231301"), None);
+        assert_eq!(DateTime::parse("D:20"), None);
+        assert_eq!(DateTime::parse("D:2023+05"), Some(date(2023, 1, 1, (0, 0, 0), Some(300))));
+        let d = date(2023, 4, 15, (10, 30, 5), Some(-150));
+        assert_eq!(d.to_pdf_string(), "D:20230415103005-02'30'");
+        assert_eq!(DateTime::parse(&d.to_pdf_string()), Some(d));
+    }
+
+    #[test]
+    fn iso8601_date() {
+        assert_eq!(
+            DateTime::parse_iso8601("2023-04-15T10:30:05.25+02:00"),
+            Some(date(2023, 4, 15, (10, 30, 5), Some(120)))
+        );
+        assert_eq!(
+            DateTime::parse_iso8601("2023-04-15T10:30Z"),
+            Some(date(2023, 4, 15, (10, 30, 0), Some(0)))
+        );
+        assert_eq!(
+            DateTime::parse_iso8601("2023-04"),
+            Some(date(2023, 4, 1, (0, 0, 0), None))
+        );
+        assert_eq!(DateTime::parse_iso8601("2023-04-15T25:00"), None);
+        assert_eq!(DateTime::parse_iso8601("April"), None);
+        let d = date(2023, 4, 15, (10, 30, 5), Some(-330));
+        assert_eq!(d.to_string(), "2023-04-15T10:30:05-05:30");
+        assert_eq!(DateTime::parse_iso8601(&d.to_string()), Some(d));
+    }
+
+    #[test]
+    fn unix_time() {
+        assert_eq!(date(1970, 1, 1, (0, 0, 0), None).unix_time(), 0);
+        assert_eq!(date(2000, 3, 1, (0, 0, 0), Some(0)).unix_time(), 951868800);
+        assert_eq!(date(2000, 3, 1, (1, 0, 0), Some(60)).unix_time(), 951868800);
+        assert_eq!(date(1969, 12, 31, (23, 59, 59), None).unix_time(), -1);
+        for secs in [0, -1, 951868800, 4107542399, -2208988800] {
+            assert_eq!(DateTime::from_unix_time(secs).unix_time(), secs);
+        }
+        assert_eq!(
+            DateTime::from_unix_time(951868799),
+            date(2000, 2, 29, (23, 59, 59), Some(0))
+        );
+    }
+
+    const XMP: &str = r#"
+1 0 obj << /Type /Catalog /Pages 2 0 R /Metadata 4 0 R >> endobj
+2 0 obj << /Type /Pages /Kids [] /Count 0 >> endobj
+3 0 obj << /Title (Old title) /Author (A. Author) /Producer (Writer 1.0)
+  /ModDate (D:20200101000000Z) /CreationDate (D:20190101000000Z) >> endobj
+4 0 obj << /Type /Metadata /Subtype /XML >> stream
+<x:xmpmeta xmlns:x="adobe:ns:meta/"><rdf:RDF>
+<rdf:Description rdf:about="" xmp:CreatorTool="Editor"
+  xmp:ModifyDate="2021-06-01T12:00:00Z" xmp:CreateDate="2019-01-01T00:00:00Z">
+<dc:title><rdf:Alt><rdf:li xml:lang="de">Titel</rdf:li>
+  <rdf:li xml:lang="x-default">New title</rdf:li></rdf:Alt></dc:title>
+<dc:creator><rdf:Seq><rdf:li>A. Author</rdf:li><rdf:li>B. Author</rdf:li></rdf:Seq></dc:creator>
+<dc:subject><rdf:Bag><rdf:li>pdf</rdf:li><rdf:li>test</rdf:li></rdf:Bag></dc:subject>
+</rdf:Description></rdf:RDF></x:xmpmeta>
+endstream endobj
+5 0 obj << /Root 1 0 R /Info 3 0 R >> endobj
+"#;
+
+    #[test]
+    fn metadata() {
+        let mut pdf = test_pdf(XMP);
+        pdf.trailer = pdf.get_object(ObjectId::new(5, 0)).and_then(|v| v.as_dict()).cloned();
+        let info = pdf.info();
+        assert_eq!(info.title.as_deref(), Some("Old title"));
+        assert_eq!(info.mod_date, Some(date(2020, 1, 1, (0, 0, 0), Some(0))));
+        let xmp = pdf.xmp_metadata().unwrap();
+        assert_eq!(xmp.title.as_deref(), Some("New title"));
+        assert_eq!(xmp.creators, ["A. Author", "B. Author"]);
+        assert_eq!(xmp.subjects, ["pdf", "test"]);
+        assert_eq!(xmp.creator_tool.as_deref(), Some("Editor"));
+        // the packet is newer, so it wins where both have a value
+        let meta = pdf.metadata();
+        assert_eq!(meta.title.as_deref(), Some("New title"));
+        assert_eq!(meta.author.as_deref(), Some("A. Author; B. Author"));
+        assert_eq!(meta.keywords.as_deref(), Some("pdf, test"));
+        assert_eq!(meta.producer.as_deref(), Some("Writer 1.0"));
+        assert_eq!(meta.mod_date, Some(date(2021, 6, 1, (12, 0, 0), Some(0))));
+        assert_eq!(meta.conflicts, ["title", "author", "mod_date"]);
+    }
+}
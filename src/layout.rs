@@ -0,0 +1,441 @@
+use super::*;
+
+/// Thresholds of layout analysis, in units of the font size unless noted.
+#[derive(Debug, Clone)]
+pub struct LayoutOptions {
+    /// A gap between characters wider than this separates words.
+    pub word_margin: f64,
+    /// A gap between characters wider than this ends a line, as at a column gutter
+    /// or between table cells.
+    pub char_margin: f64,
+    /// Pieces of text share a line when they overlap vertically by this fraction
+    /// of the lower one's height.
+    pub line_overlap: f64,
+    /// Lines further apart than this fraction of the line height start a new block.
+    pub line_margin: f64,
+    /// A vertical strip of white space at least this wide separates columns.
+    pub column_gap: f64,
+    /// Joins words hyphenated at the end of a line.
+    pub dehyphenate: bool,
+}
+
+impl Default for LayoutOptions {
+    fn default() -> Self {
+        LayoutOptions {
+            word_margin: 0.15,
+            char_margin: 2.0,
+            line_overlap: 0.5,
+            line_margin: 0.5,
+            column_gap: 1.0,
+            dehyphenate: true,
+        }
+    }
+}
+
+/// Lines set close together, a paragraph, a heading or a table cell.
+#[derive(Debug, Clone)]
+pub struct TextBlock {
+    /// The text of the lines, one per line.
+    pub text: String,
+    pub bbox: Rect,
+    /// Index of the column holding the block among those its part of the page is
+    /// divided into, 0 for text across the full width.
+    pub column: usize,
+    pub lines: Vec<TextLine>,
+}
+
+// glyphs following each other on a line, with whether white space precedes each
+pub(crate) struct LinePiece<'a> {
+    glyphs: Vec<(bool, &'a Glyph)>,
+    bbox: Rect,
+    // left to right along the x axis
+    pub(crate) horizontal: bool,
+}
+
+impl LinePiece<'_> {
+    fn size(&self) -> f64 {
+        self.glyphs.iter().map(|g| g.1.size()).fold(0.0, f64::max)
+    }
+}
+
+impl Pdf {
+    /// The text of page `no` grouped into words, lines and blocks, the blocks in
+    /// reading order: columns are read one after the other, top to bottom.
+    /// /ActualText replaces the text of its marked content.
+    pub fn page_blocks(&self, no: i32, options: &LayoutOptions) -> io::Result<Vec<TextBlock>> {
+        let glyphs = self.page_glyphs(no)?;
+        let mut sizes: Vec<f64> = glyphs.iter().map(|g| g.size()).collect();
+        sizes.sort_by(f64::total_cmp);
+        let size = sizes.get(sizes.len() / 2).copied().unwrap_or(0.0);
+        let pieces = line_pieces(&glyphs, options);
+        let lines: Vec<TextLine> = join_pieces(pieces, options)
+            .iter()
+            .filter_map(|piece| piece_line(piece, options))
+            .collect();
+        let blocks = text_blocks(lines, options);
+        let mut blocks = reading_order(blocks, options.column_gap * size, 0, 0);
+        if options.dehyphenate {
+            for block in blocks.iter_mut() {
+                dehyphenate(block);
+            }
+        }
+        Ok(blocks)
+    }
+    /// The text of page `no` in reading order, see page_blocks: blocks separated by
+    /// an empty line.
+    pub fn page_layout_text(&self, no: i32, options: &LayoutOptions) -> io::Result<String> {
+        let blocks = self.page_blocks(no, options)?;
+        let texts: Vec<&str> = blocks.iter().map(|b| b.text.as_str()).collect();
+        Ok(texts.join("\n\n"))
+    }
+}
+
+// offsets of `glyph` from the end of `prev`, along and across its baseline
+fn offsets(prev: &Glyph, glyph: &Glyph) -> (f64, f64) {
+    let (ex, ey) = prev.end();
+    let (x, y) = glyph.origin();
+    let (dx, dy) = prev.direction();
+    ((x - ex) * dx + (y - ey) * dy, (y - ey) * dx - (x - ex) * dy)
+}
+
+// runs of glyphs in content order, broken where the text leaves the baseline,
+// jumps back or leaves a gap wider than the char margin; white space only marks
+// the glyph after it
+pub(crate) fn line_pieces<'a>(glyphs: &'a [Glyph], options: &LayoutOptions) -> Vec<LinePiece<'a>> {
+    let mut pieces: Vec<LinePiece> = Vec::new();
+    let mut current: Vec<(bool, &Glyph)> = Vec::new();
+    let mut space = false;
+    let mut finish = |current: &mut Vec<(bool, &'a Glyph)>| {
+        if let Some((_, first)) = current.first() {
+            let (dx, dy) = first.direction();
+            pieces.push(LinePiece {
+                bbox: union(current.iter().map(|g| g.1.bbox())),
+                horizontal: first.vertical.is_none() && dx > 0.0 && dy.abs() < 0.1,
+                glyphs: std::mem::take(current),
+            });
+        }
+    };
+    for glyph in glyphs.iter().filter(|g| !g.text.is_empty()) {
+        if glyph.text.trim().is_empty() {
+            space = true;
+            continue;
+        }
+        if let Some((_, prev)) = current.last() {
+            let (along, across) = offsets(prev, glyph);
+            let size = prev.size().max(glyph.size());
+            if across.abs() > size * 0.5 || along > size * options.char_margin || along < -size
+            {
+                finish(&mut current);
+            }
+        }
+        current.push((space, glyph));
+        space = false;
+    }
+    finish(&mut current);
+    pieces
+}
+
+// horizontal pieces overlapping vertically and near each other side by side make
+// up one line, glyphs ordered from left to right
+pub(crate) fn join_pieces<'a>(
+    mut pieces: Vec<LinePiece<'a>>,
+    options: &LayoutOptions,
+) -> Vec<LinePiece<'a>> {
+    pieces.sort_by(|a, b| a.bbox.llx.total_cmp(&b.bbox.llx));
+    let mut lines: Vec<LinePiece> = Vec::new();
+    for piece in pieces {
+        let size = piece.size();
+        let found = lines.iter_mut().find(|line| {
+            let overlap = line.bbox.ury.min(piece.bbox.ury) - line.bbox.lly.max(piece.bbox.lly);
+            let height = (line.bbox.ury - line.bbox.lly).min(piece.bbox.ury - piece.bbox.lly);
+            let gap = piece.bbox.llx - line.bbox.urx;
+            let size = size.max(line.size());
+            line.horizontal
+                && piece.horizontal
+                && overlap >= height * options.line_overlap
+                && gap <= size * options.char_margin
+                && gap >= -size * 0.5
+        });
+        match found {
+            Some(line) => {
+                line.bbox = union([line.bbox, piece.bbox].iter().copied());
+                line.glyphs.extend(piece.glyphs);
+                line.glyphs.sort_by(|a, b| a.1.origin().0.total_cmp(&b.1.origin().0));
+            }
+            None => lines.push(piece),
+        }
+    }
+    lines
+}
+
+// the words of a line, split at white space and at gaps wider than the word margin
+pub(crate) fn piece_line(piece: &LinePiece, options: &LayoutOptions) -> Option<TextLine> {
+    let mut words = Vec::new();
+    let mut chars = Vec::new();
+    let mut prev: Option<&Glyph> = None;
+    for (space, glyph) in piece.glyphs.iter() {
+        let gap = prev.map_or(0.0, |p| offsets(p, glyph).0);
+        let size = prev.map_or(0.0, |p| p.size()).max(glyph.size());
+        if *space || gap > size * options.word_margin {
+            words.extend(text_word(std::mem::take(&mut chars)));
+        }
+        chars.push(glyph.to_char());
+        prev = Some(glyph);
+    }
+    words.extend(text_word(chars));
+    text_line(words)
+}
+
+// lines overlapping horizontally with less than the line margin between them,
+// and of about the same size, form blocks; lines of a block top to bottom
+fn text_blocks(lines: Vec<TextLine>, options: &LayoutOptions) -> Vec<TextBlock> {
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+    let height = |l: &TextLine| l.bbox.ury - l.bbox.lly;
+    let mut parent: Vec<usize> = (0..lines.len()).collect();
+    for i in 0..lines.len() {
+        for j in i + 1..lines.len() {
+            let (a, b) = (&lines[i], &lines[j]);
+            let overlap = a.bbox.urx.min(b.bbox.urx) - a.bbox.llx.max(b.bbox.llx);
+            let gap = a.bbox.lly.max(b.bbox.lly) - a.bbox.ury.min(b.bbox.ury);
+            let (ha, hb) = (height(a), height(b));
+            if overlap > 0.0
+                && gap <= ha.max(hb) * options.line_margin
+                && ha.min(hb) >= ha.max(hb) * 0.7
+            {
+                let (ra, rb) = (root(&mut parent, i), root(&mut parent, j));
+                parent[ra] = rb;
+            }
+        }
+    }
+    let mut groups: Vec<Vec<TextLine>> = Vec::new();
+    let mut group_of: HashMap<usize, usize> = HashMap::new();
+    for (i, line) in lines.into_iter().enumerate() {
+        let r = root(&mut parent, i);
+        let g = *group_of.entry(r).or_insert_with(|| {
+            groups.push(Vec::new());
+            groups.len() - 1
+        });
+        groups[g].push(line);
+    }
+    groups
+        .into_iter()
+        .map(|mut lines| {
+            lines.sort_by(|a, b| b.bbox.ury.total_cmp(&a.bbox.ury));
+            text_block(lines)
+        })
+        .collect()
+}
+
+fn text_block(lines: Vec<TextLine>) -> TextBlock {
+    let text: Vec<&str> = lines.iter().map(|l| l.text.as_str()).collect();
+    TextBlock {
+        text: text.join("\n"),
+        bbox: union(lines.iter().map(|l| l.bbox)),
+        column: 0,
+        lines,
+    }
+}
+
+// the strips of white space at least `min_gap` wide between the blocks, along x
+// or y, as (start, end) in increasing order
+fn gaps(blocks: &[TextBlock], vertical: bool, min_gap: f64) -> Vec<(f64, f64)> {
+    let mut spans: Vec<(f64, f64)> = blocks
+        .iter()
+        .map(|b| match vertical {
+            true => (b.bbox.lly, b.bbox.ury),
+            false => (b.bbox.llx, b.bbox.urx),
+        })
+        .collect();
+    spans.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let mut gaps = Vec::new();
+    let mut end = f64::NEG_INFINITY;
+    for (i, (start, stop)) in spans.into_iter().enumerate() {
+        if i > 0 && start - end >= min_gap.max(f64::MIN_POSITIVE) {
+            gaps.push((end, start));
+        }
+        end = end.max(stop);
+    }
+    gaps
+}
+
+// blocks cut into bands at horizontal gaps, from the top
+fn bands(blocks: Vec<TextBlock>) -> Vec<Vec<TextBlock>> {
+    let cuts = gaps(&blocks, true, 0.0);
+    let mut bands: Vec<Vec<TextBlock>> = (0..=cuts.len()).map(|_| Vec::new()).collect();
+    for block in blocks {
+        let i = cuts.iter().filter(|g| block.bbox.ury <= g.0).count();
+        bands[i].push(block);
+    }
+    bands
+}
+
+// recursive XY cut: blocks split at column gutters running through all of them
+// are read left to right; otherwise they are cut into bands at horizontal gaps
+// and read top to bottom, with bands sharing a gutter read together so that
+// paragraph breaks lining up across columns do not interleave them
+fn reading_order(
+    mut blocks: Vec<TextBlock>,
+    min_gap: f64,
+    column: usize,
+    depth: i32,
+) -> Vec<TextBlock> {
+    if blocks.len() > 1 && depth < 32 {
+        let gutters = gaps(&blocks, false, min_gap);
+        if !gutters.is_empty() {
+            let mut columns: Vec<Vec<TextBlock>> =
+                (0..=gutters.len()).map(|_| Vec::new()).collect();
+            for block in blocks {
+                let i = gutters.iter().filter(|g| block.bbox.llx >= g.1).count();
+                columns[i].push(block);
+            }
+            return columns
+                .into_iter()
+                .enumerate()
+                .flat_map(|(i, c)| reading_order(c, min_gap, i, depth + 1))
+                .collect();
+        }
+        let mut bands = bands(blocks);
+        if bands.len() > 1 {
+            let mut groups: Vec<Vec<TextBlock>> = Vec::new();
+            let mut last: Vec<(f64, f64)> = Vec::new();
+            for band in bands {
+                let gutters = gaps(&band, false, min_gap);
+                let shared = gutters
+                    .iter()
+                    .any(|g| last.iter().any(|l| g.0 < l.1 && l.0 < g.1));
+                match groups.last_mut() {
+                    Some(group) if shared => group.extend(band),
+                    _ => groups.push(band),
+                }
+                last = gutters;
+            }
+            // gutters lining up only pairwise, read the bands one by one
+            if groups.len() == 1 {
+                groups = self::bands(groups.remove(0));
+            }
+            return groups
+                .into_iter()
+                .flat_map(|g| reading_order(g, min_gap, column, depth + 1))
+                .collect();
+        }
+        blocks = bands.remove(0);
+    }
+    blocks.sort_by(|a, b| {
+        (b.bbox.ury.total_cmp(&a.bbox.ury)).then(a.bbox.llx.total_cmp(&b.bbox.llx))
+    });
+    for block in blocks.iter_mut() {
+        block.column = column;
+    }
+    blocks
+}
+
+// whether a line ending in `word` and going on with `next` breaks a word: always
+// at a soft hyphen, at a hyphen between letters when the next line goes on in
+// lower case
+fn hyphenated(word: &str, next: &str) -> bool {
+    let mut last = word.chars().rev();
+    let (hyphen, before) = (last.next(), last.next());
+    let after = next.chars().next();
+    match hyphen {
+        Some('\u{AD}') => before.is_some(),
+        Some('-') | Some('\u{2010}') => {
+            before.is_some_and(|c| c.is_alphabetic()) && after.is_some_and(|c| c.is_lowercase())
+        }
+        _ => false,
+    }
+}
+
+// joins the words hyphenated at line ends with the first word of the next line
+fn dehyphenate(block: &mut TextBlock) {
+    let mut changed = false;
+    for i in 1..block.lines.len() {
+        let (head, tail) = block.lines.split_at_mut(i);
+        let (line, next) = (&mut head[i - 1], &mut tail[0]);
+        let join = match (line.words.last(), next.words.first()) {
+            (Some(word), Some(next_word)) => hyphenated(&word.text, &next_word.text),
+            _ => false,
+        };
+        if !join {
+            continue;
+        }
+        if let Some(mut word) = line.words.pop() {
+            word.chars.pop();
+            word.chars.extend(next.words.remove(0).chars);
+            line.words.extend(text_word(word.chars));
+            changed = true;
+        }
+    }
+    if changed {
+        let lines = std::mem::take(&mut block.lines);
+        let column = block.column;
+        *block = text_block(lines.into_iter().filter_map(|l| text_line(l.words)).collect());
+        block.column = column;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a heading over two columns, shown right column first, the left column
+    // ending in a hyphenated word
+    const COLUMNS: &str = r"
+1 0 obj << /Type /Catalog /Pages 2 0 R >> endobj
+2 0 obj << /Type /Pages /Kids [3 0 R] /Count 1 >> endobj
+3 0 obj << /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] /Contents 4 0 R
+  /Resources << /Font << /F1 5 0 R >> >> >> endobj
+4 0 obj << >> stream
+BT /F1 10 Tf
+1 0 0 1 320 700 Tm (Right top) Tj
+1 0 0 1 320 688 Tm (right bottom) Tj
+1 0 0 1 72 700 Tm (Left top) Tj
+1 0 0 1 72 688 Tm (left bottom with a hyphen-) Tj
+1 0 0 1 72 676 Tm (ated word) Tj
+/F1 20 Tf 1 0 0 1 72 740 Tm (Title) Tj
+ET
+endstream endobj
+5 0 obj << /Type /Font /Subtype /Type1 /BaseFont /Helvetica >> endobj
+";
+
+    #[test]
+    fn reading_order() {
+        let pdf = test_pdf(COLUMNS);
+        let blocks = pdf.page_blocks(0, &LayoutOptions::default()).unwrap();
+        let texts: Vec<(&str, usize)> =
+            blocks.iter().map(|b| (b.text.as_str(), b.column)).collect();
+        assert_eq!(
+            texts,
+            [
+                ("Title", 0),
+                ("Left top\nleft bottom with a hyphenated\nword", 0),
+                ("Right top\nright bottom", 1)
+            ]
+        );
+        assert_eq!(blocks[2].lines[1].words.len(), 2);
+        let options = LayoutOptions {
+            dehyphenate: false,
+            ..Default::default()
+        };
+        let text = pdf.page_layout_text(0, &options).unwrap();
+        assert_eq!(
+            text,
+            "Title\n\nLeft top\nleft bottom with a hyphen-\nated word\n\nRight top\nright bottom"
+        );
+    }
+
+    #[test]
+    fn hyphens() {
+        assert!(hyphenated("hyphen-", "ated"));
+        assert!(hyphenated("soft\u{AD}", "Ware"));
+        assert!(!hyphenated("well-", "Known"));
+        assert!(!hyphenated("2-", "x"));
+        assert!(!hyphenated("-", "x"));
+        assert!(!hyphenated("word", "next"));
+    }
+}
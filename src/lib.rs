@@ -1,3 +1,4 @@
+#![allow(non_camel_case_types, non_upper_case_globals, clippy::upper_case_acronyms)]
 mod pdf;
 pub use self::pdf::*;
 use std::io;
@@ -5,7 +6,7 @@ use std::io;
 pub fn open(path:&str)->io::Result<Pdf>{
     Pdf::open(path)
 }
-pub fn open_with_pwd(path:&str, pwd:&str)->io::Result<Pdf>{
+pub fn open_with_pwd(path:&str, _pwd:&str)->io::Result<Pdf>{
     // Pdf::open_with_pwd(path) // TODO
     Pdf::open(path)
 }
\ No newline at end of file
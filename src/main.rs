@@ -1,19 +1,36 @@
-mod parse;
-
-use rpdf;
-
 use std::env;
+use std::fs;
+use std::path::Path;
 fn main() {
     let args: Vec<String> = env::args().collect();
 
     let mut path = "readme.pdf";
 
     if args.len() > 1 {
-        path = &args[1].as_str();
+        path = args[1].as_str();
     }
     if let Ok(pdf) = rpdf::open(path) {
+        // rpdf <file> --csv <dir>: writes each table to <dir>/page<n>-table<m>.csv
+        if args.len() > 3 && args[2] == "--csv" {
+            let dir = Path::new(&args[3]);
+            let options = rpdf::TableOptions::default();
+            for no in 0..pdf.page_count() {
+                let tables = pdf.page_tables(no, &options).unwrap_or_default();
+                for (i, table) in tables.iter().enumerate() {
+                    let file = dir.join(format!("page{}-table{}.csv", no + 1, i + 1));
+                    match fs::write(&file, table.to_csv()) {
+                        Ok(_) => println!("{}", file.display()),
+                        Err(e) => eprintln!("{}: {}", file.display(), e),
+                    }
+                }
+            }
+            return;
+        }
         println!("{:?}", pdf);
         println!("page count: {:?}", pdf.page_count());
-        pdf.page_text(0);
+        if let Ok(page) = pdf.page(0) {
+            println!("page 0: {:?} size {:?}", page, page.size());
+        }
+        println!("{}", pdf.page_text(0));
     }
 }
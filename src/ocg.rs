@@ -0,0 +1,413 @@
+use super::*;
+use std::collections::HashMap;
+
+const PDF_NAME_OCProperties: &str = "OCProperties";
+const PDF_NAME_OCGs: &str = "OCGs";
+const PDF_NAME_D: &str = "D";
+const PDF_NAME_Configs: &str = "Configs";
+const PDF_NAME_Name: &str = "Name";
+const PDF_NAME_Creator: &str = "Creator";
+const PDF_NAME_BaseState: &str = "BaseState";
+const PDF_NAME_ON: &str = "ON";
+const PDF_NAME_OFF: &str = "OFF";
+const PDF_NAME_Intent: &str = "Intent";
+const PDF_NAME_Locked: &str = "Locked";
+const PDF_NAME_RBGroups: &str = "RBGroups";
+const PDF_NAME_Type: &str = "Type";
+const PDF_NAME_P: &str = "P";
+const PDF_NAME_VE: &str = "VE";
+const PDF_NAME_Properties: &str = "Properties";
+
+/// An optional content group, a layer that can be shown or hidden.
+#[derive(Debug, Clone)]
+pub struct OptionalContentGroup {
+    pub id: ObjectId,
+    pub name: String,
+    /// /Intent, "View" when missing.
+    pub intents: Vec<String>,
+    /// State in the default configuration.
+    pub visible: bool,
+}
+
+/// An optional content configuration dictionary, the default /D or one of /Configs.
+#[derive(Debug, Clone, Default)]
+pub struct OcConfig {
+    pub name: Option<String>,
+    pub creator: Option<String>,
+    /// /BaseState: "ON", "OFF" or "Unchanged".
+    pub base_state: String,
+    pub on: Vec<ObjectId>,
+    pub off: Vec<ObjectId>,
+    /// Groups taking part in visibility decisions, by intent. "View" when missing,
+    /// "All" matches every group.
+    pub intents: Vec<String>,
+    pub locked: Vec<ObjectId>,
+    /// /RBGroups, sets of groups of which at most one is on.
+    pub radio_groups: Vec<Vec<ObjectId>>,
+}
+
+/// The catalog /OCProperties.
+#[derive(Debug, Clone)]
+pub struct OptionalContent {
+    pub groups: Vec<OptionalContentGroup>,
+    pub default_config: OcConfig,
+    pub configs: Vec<OcConfig>,
+}
+
+/// On/off state of the optional content groups, used to decide what content to draw.
+#[derive(Debug, Clone, Default)]
+pub struct OcState {
+    groups: HashMap<ObjectId, bool>,
+}
+
+impl OcState {
+    /// Groups not listed, or whose intent the configuration ignores, are on.
+    pub fn is_on(&self, id: ObjectId) -> bool {
+        self.groups.get(&id).copied().unwrap_or(true)
+    }
+    pub fn set(&mut self, id: ObjectId, on: bool) {
+        self.groups.insert(id, on);
+    }
+}
+
+impl OptionalContent {
+    /// The state of the default configuration, what a viewer shows on opening.
+    pub fn state(&self) -> OcState {
+        self.apply(&self.default_config, &OcState::default())
+    }
+    /// The state of alternate configuration `index`, applied over the default one when
+    /// its base state is Unchanged.
+    pub fn config_state(&self, index: usize) -> Option<OcState> {
+        let config = self.configs.get(index)?;
+        Some(self.apply(config, &self.state()))
+    }
+    /// Turns group `id` on or off; turning it on turns off the other groups of its
+    /// radio button sets in the default configuration.
+    pub fn set_visible(&self, state: &mut OcState, id: ObjectId, on: bool) {
+        if on {
+            for set in self.default_config.radio_groups.iter().filter(|s| s.contains(&id)) {
+                for other in set.iter().filter(|g| **g != id) {
+                    state.set(*other, false);
+                }
+            }
+        }
+        state.set(id, on);
+    }
+    fn apply(&self, config: &OcConfig, base: &OcState) -> OcState {
+        let mut state = OcState::default();
+        for group in &self.groups {
+            let on = match config.base_state.as_str() {
+                "OFF" => false,
+                "Unchanged" => base.is_on(group.id),
+                _ => true,
+            };
+            state.set(group.id, on);
+        }
+        for id in &config.on {
+            state.set(*id, true);
+        }
+        for id in &config.off {
+            state.set(*id, false);
+        }
+        // groups of other intents do not affect visibility
+        if !config.intents.iter().any(|i| i == "All") {
+            for group in &self.groups {
+                if !group.intents.iter().any(|i| config.intents.contains(i)) {
+                    state.set(group.id, true);
+                }
+            }
+        }
+        state
+    }
+}
+
+impl Pdf {
+    /// The optional content groups and configurations, None if the document has none.
+    pub fn optional_content(&self) -> Option<OptionalContent> {
+        let props = self
+            .catalog()
+            .and_then(|c| self.lookup(c, PDF_NAME_OCProperties))?
+            .as_dict()?;
+        let default_config = self
+            .lookup(props, PDF_NAME_D)
+            .and_then(|v| v.as_dict())
+            .map(|d| self.load_oc_config(d))
+            .unwrap_or_else(|| self.load_oc_config(&Dict::new()));
+        let configs = self
+            .lookup(props, PDF_NAME_Configs)
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|v| self.resolve(v).as_dict())
+            .map(|d| self.load_oc_config(d))
+            .collect();
+        let mut content = OptionalContent {
+            groups: Vec::new(),
+            default_config,
+            configs,
+        };
+        for id in self.oc_refs(props, PDF_NAME_OCGs) {
+            let dict = match self.get_object(id).and_then(|v| v.as_dict()) {
+                Some(dict) => dict,
+                None => continue,
+            };
+            content.groups.push(OptionalContentGroup {
+                id,
+                name: self
+                    .lookup(dict, PDF_NAME_Name)
+                    .and_then(|v| v.as_text())
+                    .unwrap_or_default(),
+                intents: self.oc_intents(dict),
+                visible: true,
+            });
+        }
+        let state = content.state();
+        for group in content.groups.iter_mut() {
+            group.visible = state.is_on(group.id);
+        }
+        Some(content)
+    }
+    /// Whether content tagged with `oc`, a group or membership dictionary as found in
+    /// /OC entries and marked content properties, is visible in `state`.
+    pub fn oc_visible(&self, oc: &Value, state: &OcState) -> bool {
+        self.eval_oc(oc, state, &mut HashMap::new())
+    }
+    /// Whether a marked content sequence started by `/OC /name BDC` is visible, with
+    /// `name` (or an inline dictionary) looked up in the /Properties of `resources`.
+    pub fn marked_content_visible(&self, resources: &Dict, props: &Value, state: &OcState) -> bool {
+        match props {
+            Value::NAME(name) => match self
+                .lookup(resources, PDF_NAME_Properties)
+                .and_then(|v| v.as_dict())
+                .and_then(|p| p.get(name))
+            {
+                Some(oc) => self.oc_visible(oc, state),
+                None => true,
+            },
+            props => self.oc_visible(props, state),
+        }
+    }
+    // `seen` holds the result of each referenced membership dictionary and expression
+    // evaluated so far, so shared operands are evaluated once and a reference back into
+    // one being evaluated counts as visible
+    fn eval_oc(&self, oc: &Value, state: &OcState, seen: &mut HashMap<ObjectId, bool>) -> bool {
+        let dict = match self.resolve(oc).as_dict() {
+            Some(dict) => dict,
+            None => return true,
+        };
+        match self.lookup(dict, PDF_NAME_Type).and_then(|v| v.as_name()) {
+            Some("OCG") => return oc.as_object_id().map_or(true, |id| state.is_on(id)),
+            Some("OCMD") => (),
+            _ => return true,
+        }
+        if let Some(id) = oc.as_object_id() {
+            if let Some(on) = seen.get(&id) {
+                return *on;
+            }
+            seen.insert(id, true);
+        }
+        let on = self.eval_ocmd(dict, state, seen);
+        if let Some(id) = oc.as_object_id() {
+            seen.insert(id, on);
+        }
+        on
+    }
+    fn eval_ocmd(&self, dict: &Dict, state: &OcState, seen: &mut HashMap<ObjectId, bool>) -> bool {
+        if let Some(ve) = dict.get(PDF_NAME_VE) {
+            return self.eval_visibility_expr(ve, state, seen);
+        }
+        let groups: Vec<bool> = match dict.get(PDF_NAME_OCGs).map(|v| (v, self.resolve(v))) {
+            Some((_, Value::ARRAY(items))) => items
+                .iter()
+                .filter(|v| !matches!(self.resolve(v), Value::NULL))
+                .map(|v| self.eval_oc(v, state, seen))
+                .collect(),
+            Some((v, Value::DICT(_))) => vec![self.eval_oc(v, state, seen)],
+            _ => Vec::new(),
+        };
+        // an empty membership dictionary has no effect
+        if groups.is_empty() {
+            return true;
+        }
+        match self.lookup(dict, PDF_NAME_P).and_then(|v| v.as_name()) {
+            Some("AllOn") => groups.iter().all(|on| *on),
+            Some("AnyOff") => groups.iter().any(|on| !on),
+            Some("AllOff") => groups.iter().all(|on| !on),
+            _ => groups.iter().any(|on| *on),
+        }
+    }
+    // [/And e1 e2 ...], [/Or ...] or [/Not e], operands are groups or expressions
+    fn eval_visibility_expr(
+        &self,
+        expr: &Value,
+        state: &OcState,
+        seen: &mut HashMap<ObjectId, bool>,
+    ) -> bool {
+        let items = match self.resolve(expr) {
+            Value::ARRAY(items) => items,
+            _ => return self.eval_oc(expr, state, seen),
+        };
+        if let Some(id) = expr.as_object_id() {
+            if let Some(on) = seen.get(&id) {
+                return *on;
+            }
+            seen.insert(id, true);
+        }
+        let on = match items.split_first() {
+            Some((op, operands)) => {
+                let mut values = operands
+                    .iter()
+                    .map(|v| self.eval_visibility_expr(v, state, seen));
+                match self.resolve(op).as_name() {
+                    Some("And") => values.all(|on| on),
+                    Some("Or") => values.any(|on| on),
+                    Some("Not") => !values.next().unwrap_or(false),
+                    _ => true,
+                }
+            }
+            None => true,
+        };
+        if let Some(id) = expr.as_object_id() {
+            seen.insert(id, on);
+        }
+        on
+    }
+    fn load_oc_config(&self, dict: &Dict) -> OcConfig {
+        let text = |key: &str| self.lookup(dict, key).and_then(|v| v.as_text());
+        let intents = match self.lookup(dict, PDF_NAME_Intent) {
+            Some(_) => self.oc_intents(dict),
+            None => vec!["View".to_string()],
+        };
+        OcConfig {
+            name: text(PDF_NAME_Name),
+            creator: text(PDF_NAME_Creator),
+            base_state: self
+                .lookup(dict, PDF_NAME_BaseState)
+                .and_then(|v| v.as_name())
+                .unwrap_or(PDF_NAME_ON)
+                .to_string(),
+            on: self.oc_refs(dict, PDF_NAME_ON),
+            off: self.oc_refs(dict, PDF_NAME_OFF),
+            intents,
+            locked: self.oc_refs(dict, PDF_NAME_Locked),
+            radio_groups: self
+                .lookup(dict, PDF_NAME_RBGroups)
+                .and_then(|v| v.as_array())
+                .into_iter()
+                .flatten()
+                .filter_map(|v| self.resolve(v).as_array())
+                .map(|set| set.iter().filter_map(|v| v.as_object_id()).collect())
+                .collect(),
+        }
+    }
+    // the group references of an array entry
+    fn oc_refs(&self, dict: &Dict, key: &str) -> Vec<ObjectId> {
+        self.lookup(dict, key)
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|v| v.as_object_id())
+            .collect()
+    }
+    // /Intent, a name or an array of names
+    fn oc_intents(&self, dict: &Dict) -> Vec<String> {
+        match self.lookup(dict, PDF_NAME_Intent) {
+            Some(Value::NAME(name)) => vec![name.clone()],
+            Some(Value::ARRAY(names)) => names
+                .iter()
+                .filter_map(|v| self.resolve(v).as_name())
+                .map(String::from)
+                .collect(),
+            _ => vec!["View".to_string()],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // English on and French off as radio buttons, a Design layer off but of an
+    // intent the default configuration ignores, and an alternate configuration
+    const LAYERS: &str = r"
+1 0 obj << /Type /Catalog /Pages 2 0 R /OCProperties << /OCGs [10 0 R 11 0 R 12 0 R]
+  /D << /Name (Default) /OFF [11 0 R 12 0 R] /RBGroups [[10 0 R 11 0 R]] /Locked [10 0 R] >>
+  /Configs [<< /Name (French) /BaseState /OFF /ON [11 0 R] /Intent [/View /Design] >>] >> >>
+endobj
+2 0 obj << /Type /Pages /Kids [] /Count 0 >> endobj
+10 0 obj << /Type /OCG /Name (English) >> endobj
+11 0 obj << /Type /OCG /Name (French) /Intent /View >> endobj
+12 0 obj << /Type /OCG /Name (Design) /Intent /Design >> endobj
+20 0 obj << /Type /OCMD /OCGs [10 0 R 11 0 R null] /P /AnyOff >> endobj
+21 0 obj << /Type /OCMD /OCGs 11 0 R >> endobj
+22 0 obj << /Type /OCMD /VE [/And 10 0 R [/Not 11 0 R]] /OCGs 11 0 R >> endobj
+23 0 obj << /Type /OCMD /OCGs [] /P /AllOff >> endobj
+24 0 obj << /Properties << /MC0 11 0 R /MC1 10 0 R >> >> endobj
+";
+
+    #[test]
+    fn configurations() {
+        let pdf = test_pdf(LAYERS);
+        let oc = pdf.optional_content().unwrap();
+        let names: Vec<(&str, bool)> =
+            oc.groups.iter().map(|g| (g.name.as_str(), g.visible)).collect();
+        assert_eq!(names, [("English", true), ("French", false), ("Design", true)]);
+        assert_eq!(oc.default_config.name.as_deref(), Some("Default"));
+        assert_eq!(oc.default_config.intents, ["View"]);
+        assert_eq!(oc.default_config.locked, [ObjectId::new(10, 0)]);
+        let french = oc.config_state(0).unwrap();
+        assert!(!french.is_on(ObjectId::new(10, 0)));
+        assert!(french.is_on(ObjectId::new(11, 0)));
+        assert!(!french.is_on(ObjectId::new(12, 0)));
+        assert!(oc.config_state(1).is_none());
+        // radio buttons
+        let mut state = oc.state();
+        oc.set_visible(&mut state, ObjectId::new(11, 0), true);
+        assert!(!state.is_on(ObjectId::new(10, 0)));
+        oc.set_visible(&mut state, ObjectId::new(11, 0), false);
+        assert!(!state.is_on(ObjectId::new(10, 0)) && !state.is_on(ObjectId::new(11, 0)));
+        assert!(test_pdf("1 0 obj << /Type /Catalog >> endobj").optional_content().is_none());
+    }
+
+    #[test]
+    fn visibility() {
+        let pdf = test_pdf(LAYERS);
+        let oc = pdf.optional_content().unwrap();
+        let mut state = oc.state();
+        let visible = |id, state: &OcState| pdf.oc_visible(&Value::REF(id, 0), state);
+        assert!(visible(10, &state) && !visible(11, &state));
+        assert!(visible(20, &state));
+        assert!(!visible(21, &state));
+        assert!(visible(22, &state));
+        assert!(visible(23, &state));
+        assert!(visible(99, &state));
+        let resources = pdf.get_object(ObjectId::new(24, 0)).unwrap().as_dict().unwrap();
+        let props = |name: &str| Value::NAME(name.to_string());
+        assert!(!pdf.marked_content_visible(resources, &props("MC0"), &state));
+        assert!(pdf.marked_content_visible(resources, &props("MC1"), &state));
+        assert!(pdf.marked_content_visible(resources, &props("MC9"), &state));
+        oc.set_visible(&mut state, ObjectId::new(11, 0), true);
+        assert!(pdf.marked_content_visible(resources, &props("MC0"), &state));
+        assert!(!visible(22, &state));
+    }
+
+    #[test]
+    fn shared_and_cyclic_expressions() {
+        // 25 refers to itself, 30 to 68 each use the next expression twice, 69 tests 11
+        let mut objects = LAYERS.to_string();
+        objects.push_str("25 0 obj << /Type /OCMD /VE [/And 25 0 R 25 0 R 10 0 R] >> endobj\n");
+        for n in 30..69 {
+            objects.push_str(&format!("{} 0 obj [/And {} 0 R {} 0 R] endobj\n", n, n + 1, n + 1));
+        }
+        objects.push_str("69 0 obj [/Or 11 0 R 11 0 R] endobj\n");
+        objects.push_str("71 0 obj << /Type /OCMD /VE 30 0 R >> endobj\n");
+        let pdf = test_pdf(&objects);
+        let oc = pdf.optional_content().unwrap();
+        let mut state = oc.state();
+        let visible = |id, state: &OcState| pdf.oc_visible(&Value::REF(id, 0), state);
+        assert!(visible(25, &state));
+        assert!(!visible(71, &state));
+        oc.set_visible(&mut state, ObjectId::new(11, 0), true);
+        assert!(visible(71, &state));
+    }
+}
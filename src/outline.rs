@@ -0,0 +1,174 @@
+use super::*;
+use std::collections::HashSet;
+
+const PDF_NAME_Outlines: &str = "Outlines";
+const PDF_NAME_First: &str = "First";
+const PDF_NAME_Next: &str = "Next";
+const PDF_NAME_Title: &str = "Title";
+const PDF_NAME_Dest: &str = "Dest";
+const PDF_NAME_A: &str = "A";
+const PDF_NAME_C: &str = "C";
+const PDF_NAME_F: &str = "F";
+
+/// Document outline (bookmarks).
+#[derive(Debug, Clone, Default)]
+pub struct Outline {
+    pub items: Vec<OutlineItem>,
+}
+
+#[derive(Debug, Clone)]
+pub struct OutlineItem {
+    pub title: String,
+    /// Children are shown expanded (positive /Count).
+    pub open: bool,
+    /// RGB color of the title, components in 0..1.
+    pub color: Option<[f64; 3]>,
+    pub italic: bool,
+    pub bold: bool,
+    pub dest: Option<Destination>,
+    pub action: Option<Action>,
+    pub children: Vec<OutlineItem>,
+}
+
+impl Outline {
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
+
+impl Pdf {
+    /// Reads the catalog /Outlines tree.
+    pub fn outline(&self) -> Outline {
+        let root = match self
+            .catalog()
+            .and_then(|c| self.lookup(c, PDF_NAME_Outlines))
+            .and_then(|v| v.as_dict())
+        {
+            Some(root) => root,
+            None => return Outline::default(),
+        };
+        let mut visited = HashSet::new();
+        Outline {
+            items: self.outline_items(root, &mut visited, 0),
+        }
+    }
+    // the /First, /Next linked list of children of `parent`
+    fn outline_items(
+        &self,
+        parent: &Dict,
+        visited: &mut HashSet<i32>,
+        depth: i32,
+    ) -> Vec<OutlineItem> {
+        let mut items = Vec::new();
+        if depth > 64 {
+            return items;
+        }
+        let mut next = parent.get(PDF_NAME_First);
+        while let Some(Value::REF(n0, _)) = next {
+            // broken files link items in cycles
+            if !visited.insert(*n0) {
+                break;
+            }
+            let dict = match self.obj_list.get(n0).and_then(|obj| obj.dict()) {
+                Some(dict) => dict,
+                None => break,
+            };
+            let flags = self
+                .lookup(dict, PDF_NAME_F)
+                .and_then(|v| v.as_int())
+                .unwrap_or(0);
+            let color = self
+                .lookup(dict, PDF_NAME_C)
+                .and_then(|v| v.as_array())
+                .filter(|c| c.len() == 3)
+                .and_then(|c| {
+                    let mut rgb = [0.0; 3];
+                    for (i, v) in c.iter().enumerate() {
+                        rgb[i] = v.as_f64()?;
+                    }
+                    Some(rgb)
+                });
+            items.push(OutlineItem {
+                title: self
+                    .lookup(dict, PDF_NAME_Title)
+                    .and_then(|v| v.as_text())
+                    .unwrap_or_default(),
+                open: self
+                    .lookup(dict, PDF_NAME_Count)
+                    .and_then(|v| v.as_int())
+                    .unwrap_or(0)
+                    > 0,
+                color,
+                italic: flags & 1 != 0,
+                bold: flags & 2 != 0,
+                dest: self
+                    .lookup(dict, PDF_NAME_Dest)
+                    .and_then(|v| self.destination(v)),
+                action: self
+                    .lookup(dict, PDF_NAME_A)
+                    .and_then(|v| v.as_dict())
+                    .and_then(|a| Action::from_dict(self, a)),
+                children: self.outline_items(dict, visited, depth + 1),
+            });
+            next = dict.get(PDF_NAME_Next);
+        }
+        items
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // two top level items, the first open with a child, the last linking back
+    // to the first
+    const OUTLINE: &str = r"
+1 0 obj << /Type /Catalog /Pages 2 0 R /Outlines 4 0 R >> endobj
+2 0 obj << /Type /Pages /Kids [3 0 R] /Count 1 >> endobj
+3 0 obj << /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] >> endobj
+4 0 obj << /Type /Outlines /First 5 0 R /Last 6 0 R /Count 3 >> endobj
+5 0 obj << /Title (Chapter 1) /Parent 4 0 R /Next 6 0 R /First 7 0 R /Last 7 0 R /Count 1
+  /Dest [3 0 R /Fit] /C [1 0 0] /F 3 >> endobj
+6 0 obj << /Title <FEFF00C4> /Parent 4 0 R /Prev 5 0 R /Next 5 0 R
+  /A << /S /URI /URI (http://example.com/) >> /Count -2 /C [1 0] >> endobj
+7 0 obj << /Title (Section 1.1) /Parent 5 0 R >> endobj
+";
+
+    #[test]
+    fn outline() {
+        let pdf = test_pdf(OUTLINE);
+        let outline = pdf.outline();
+        assert_eq!(outline.items.len(), 2);
+        let chapter = &outline.items[0];
+        assert_eq!(chapter.title, "Chapter 1");
+        assert!(chapter.open && chapter.italic && chapter.bold);
+        assert_eq!(chapter.color, Some([1.0, 0.0, 0.0]));
+        assert_eq!(chapter.dest.as_ref().and_then(|d| d.page), Some(0));
+        assert_eq!(chapter.children.len(), 1);
+        assert_eq!(chapter.children[0].title, "Section 1.1");
+        let last = &outline.items[1];
+        assert_eq!(last.title, "\u{C4}");
+        assert!(!last.open && !last.bold);
+        assert_eq!(last.color, None);
+        assert!(matches!(&last.action, Some(Action::URI(u)) if u == "http://example.com/"));
+        assert!(test_pdf("1 0 obj << /Type /Catalog >> endobj").outline().is_empty());
+    }
+
+    #[test]
+    fn broken_links() {
+        // a child listing its parent as its own child, a /Next to a missing object
+        let pdf = test_pdf(
+            r"
+1 0 obj << /Type /Catalog /Outlines 4 0 R >> endobj
+4 0 obj << /First 5 0 R >> endobj
+5 0 obj << /Title (A) /First 6 0 R /Next 9 0 R >> endobj
+6 0 obj << /Title (B) /First 5 0 R /Next 6 0 R >> endobj
+",
+        );
+        let items = pdf.outline().items;
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].children.len(), 1);
+        assert_eq!(items[0].children[0].title, "B");
+        assert!(items[0].children[0].children.is_empty());
+    }
+}
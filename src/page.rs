@@ -0,0 +1,213 @@
+use super::*;
+
+/// A rectangle in default user space, normalized so that the lower left
+/// corner comes first.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rect {
+    pub llx: f64,
+    pub lly: f64,
+    pub urx: f64,
+    pub ury: f64,
+}
+
+impl Rect {
+    /// US Letter, used when a page has no usable /MediaBox.
+    pub const LETTER: Rect = Rect {
+        llx: 0.0,
+        lly: 0.0,
+        urx: 612.0,
+        ury: 792.0,
+    };
+
+    pub fn new(x0: f64, y0: f64, x1: f64, y1: f64) -> Rect {
+        Rect {
+            llx: x0.min(x1),
+            lly: y0.min(y1),
+            urx: x0.max(x1),
+            ury: y0.max(y1),
+        }
+    }
+    pub fn width(&self) -> f64 {
+        self.urx - self.llx
+    }
+    pub fn height(&self) -> f64 {
+        self.ury - self.lly
+    }
+    /// The overlapping part of two rectangles, empty (zero sized) if they are disjoint.
+    pub fn intersect(&self, other: &Rect) -> Rect {
+        let llx = self.llx.max(other.llx);
+        let lly = self.lly.max(other.lly);
+        Rect {
+            llx,
+            lly,
+            urx: self.urx.min(other.urx).max(llx),
+            ury: self.ury.min(other.ury).max(lly),
+        }
+    }
+    // [x0 y0 x1 y1]
+    pub(crate) fn from_value(val: &Value) -> Option<Rect> {
+        if let Value::ARRAY(array) = val {
+            if array.len() == 4 {
+                let mut n = [0.0; 4];
+                for (i, v) in array.iter().enumerate() {
+                    n[i] = v.as_f64()?;
+                }
+                return Some(Rect::new(n[0], n[1], n[2], n[3]));
+            }
+        }
+        None
+    }
+}
+
+#[derive(Debug)]
+pub struct Page {
+    index: i32,
+    id: i32,
+    media_box: Rect,
+    crop_box: Rect,
+    bleed_box: Rect,
+    trim_box: Rect,
+    art_box: Rect,
+    rotate: i32,
+    user_unit: f64,
+}
+
+impl Page {
+    // MediaBox, CropBox and Rotate are inherited through the page tree,
+    // the other boxes default to the crop box.
+    pub(super) fn load(pdf: &Pdf, no: i32, id: i32) -> Page {
+        let page = pdf.obj_list.get(&id).and_then(|obj| obj.dict());
+        let own_attr = |key: &str| page.and_then(|p| pdf.lookup(p, key));
+        let own_rect = |key: &str| own_attr(key).and_then(Rect::from_value);
+        let media_box = inherited_attr(pdf, id, PDF_NAME_MediaBox)
+            .and_then(Rect::from_value)
+            .unwrap_or(Rect::LETTER);
+        let crop_box = inherited_attr(pdf, id, PDF_NAME_CropBox)
+            .and_then(Rect::from_value)
+            .map(|r| r.intersect(&media_box))
+            .unwrap_or(media_box);
+        let bleed_box = own_rect(PDF_NAME_BleedBox)
+            .map(|r| r.intersect(&media_box))
+            .unwrap_or(crop_box);
+        let trim_box = own_rect(PDF_NAME_TrimBox)
+            .map(|r| r.intersect(&media_box))
+            .unwrap_or(crop_box);
+        let art_box = own_rect(PDF_NAME_ArtBox)
+            .map(|r| r.intersect(&media_box))
+            .unwrap_or(crop_box);
+        let rotate = match inherited_attr(pdf, id, PDF_NAME_Rotate) {
+            Some(Value::INTEGER(n)) => *n,
+            Some(Value::FLOAT(v)) => *v as i32,
+            _ => 0,
+        };
+        let user_unit = match own_attr(PDF_NAME_UserUnit) {
+            Some(Value::INTEGER(n)) if *n > 0 => *n as f64,
+            Some(Value::FLOAT(v)) if *v > 0.0 => *v,
+            _ => 1.0,
+        };
+        Page {
+            index: no,
+            id,
+            media_box,
+            crop_box,
+            bleed_box,
+            trim_box,
+            art_box,
+            // only multiples of 90 are meaningful
+            rotate: (rotate / 90 * 90).rem_euclid(360),
+            user_unit,
+        }
+    }
+    /// Zero based index of the page in the document.
+    pub fn index(&self) -> i32 {
+        self.index
+    }
+    /// Object number of the page dictionary.
+    pub fn id(&self) -> i32 {
+        self.id
+    }
+    pub fn media_box(&self) -> Rect {
+        self.media_box
+    }
+    pub fn crop_box(&self) -> Rect {
+        self.crop_box
+    }
+    pub fn bleed_box(&self) -> Rect {
+        self.bleed_box
+    }
+    pub fn trim_box(&self) -> Rect {
+        self.trim_box
+    }
+    pub fn art_box(&self) -> Rect {
+        self.art_box
+    }
+    /// Clockwise rotation applied when displaying, one of 0, 90, 180, 270.
+    pub fn rotate(&self) -> i32 {
+        self.rotate
+    }
+    /// Size of one user space unit in multiples of 1/72 inch.
+    pub fn user_unit(&self) -> f64 {
+        self.user_unit
+    }
+    /// Width and height in points as displayed: the crop box scaled by
+    /// /UserUnit, swapped when the page is rotated by 90 or 270 degrees.
+    pub fn size(&self) -> (f64, f64) {
+        let w = self.crop_box.width() * self.user_unit;
+        let h = self.crop_box.height() * self.user_unit;
+        if self.rotate % 180 == 90 {
+            (h, w)
+        } else {
+            (w, h)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // boxes and rotation inherited from the page tree, a crop box reaching past
+    // the media box, and a page without any box
+    const PAGES: &str = r"
+1 0 obj << /Type /Catalog /Pages 2 0 R >> endobj
+2 0 obj << /Type /Pages /Kids [3 0 R 4 0 R] /Count 2 /MediaBox [0 0 600 800] /Rotate -90 >>
+endobj
+3 0 obj << /Type /Page /Parent 2 0 R /CropBox [550 850 -10 10] /TrimBox [20 20 100 100]
+  /UserUnit 2 >> endobj
+4 0 obj << /Type /Page /Rotate 45 /MediaBox [0 0 10] >> endobj
+";
+
+    #[test]
+    fn page_boxes() {
+        let pdf = test_pdf(PAGES);
+        let page = pdf.page(0).unwrap();
+        assert_eq!((page.index(), page.id()), (0, 3));
+        assert_eq!(page.media_box(), Rect::new(0.0, 0.0, 600.0, 800.0));
+        assert_eq!(page.crop_box(), Rect::new(0.0, 10.0, 550.0, 800.0));
+        assert_eq!(page.trim_box(), Rect::new(20.0, 20.0, 100.0, 100.0));
+        assert_eq!(page.bleed_box(), page.crop_box());
+        assert_eq!(page.art_box(), page.crop_box());
+        assert_eq!(page.rotate(), 270);
+        assert_eq!(page.user_unit(), 2.0);
+        assert_eq!(page.size(), (1580.0, 1100.0));
+        let page = pdf.page(1).unwrap();
+        assert_eq!(page.media_box(), Rect::LETTER);
+        assert_eq!(page.rotate(), 0);
+        assert_eq!(page.size(), (612.0, 792.0));
+        assert!(pdf.page(2).is_err());
+    }
+
+    #[test]
+    fn rects() {
+        let a = Rect::new(10.0, 10.0, 0.0, 0.0);
+        assert_eq!((a.llx, a.ury, a.width(), a.height()), (0.0, 10.0, 10.0, 10.0));
+        assert_eq!(a.intersect(&Rect::new(5.0, 5.0, 20.0, 20.0)), Rect::new(5.0, 5.0, 10.0, 10.0));
+        let empty = a.intersect(&Rect::new(20.0, 20.0, 30.0, 30.0));
+        assert_eq!((empty.width(), empty.height()), (0.0, 0.0));
+        let array = |v: &[f64]| Value::ARRAY(v.iter().map(|n| Value::FLOAT(*n)).collect());
+        let rect = Rect::from_value(&array(&[1.0, 2.0, 3.0, 4.0]));
+        assert_eq!(rect, Some(Rect::new(1.0, 2.0, 3.0, 4.0)));
+        assert_eq!(Rect::from_value(&array(&[1.0, 2.0, 3.0])), None);
+        assert_eq!(Rect::from_value(&Value::NULL), None);
+    }
+}
@@ -0,0 +1,180 @@
+use super::*;
+
+const PDF_NAME_PageLabels: &str = "PageLabels";
+const PDF_NAME_S: &str = "S";
+const PDF_NAME_P: &str = "P";
+const PDF_NAME_St: &str = "St";
+
+// longest run of a repeated letter in alphabetic labels, and of M in roman ones
+const MAX_REPEAT: i32 = 1000;
+
+impl Pdf {
+    /// Label of page `no` from the catalog /PageLabels number tree,
+    /// the page number (`no + 1`) when the document has no labels.
+    pub fn page_label(&self, no: i32) -> Option<String> {
+        if no < 0 || no >= self.page_count {
+            return None;
+        }
+        match self.label_ranges() {
+            Some(ranges) => self.range_label(&ranges, no),
+            None => Some((no + 1).to_string()),
+        }
+    }
+    /// Index of the first page with the given label.
+    pub fn page_by_label(&self, label: &str) -> Option<i32> {
+        match self.label_ranges() {
+            Some(ranges) => (0..self.page_count)
+                .find(|no| self.range_label(&ranges, *no).as_deref() == Some(label)),
+            None => (0..self.page_count).find(|no| (no + 1).to_string() == label),
+        }
+    }
+    // (first page, label dict) of the /PageLabels number tree, None without labels
+    fn label_ranges(&self) -> Option<Vec<(i32, &Value)>> {
+        let tree = self.catalog().and_then(|c| self.lookup(c, PDF_NAME_PageLabels))?;
+        Some(self.number_tree(tree))
+    }
+    fn range_label(&self, ranges: &[(i32, &Value)], no: i32) -> Option<String> {
+        // the range starting at the greatest key <= no
+        let (start, dict) = ranges
+            .iter()
+            .filter(|(key, _)| *key <= no)
+            .max_by_key(|(key, _)| *key)?;
+        let dict = match dict.as_dict() {
+            Some(dict) => dict,
+            None => return Some(String::new()),
+        };
+        let prefix = self
+            .lookup(dict, PDF_NAME_P)
+            .and_then(|v| v.as_text())
+            .unwrap_or_default();
+        let first = self
+            .lookup(dict, PDF_NAME_St)
+            .and_then(|v| v.as_int())
+            .unwrap_or(1);
+        let n = first.saturating_add(no - start);
+        let number = match self.lookup(dict, PDF_NAME_S).and_then(|v| v.as_name()) {
+            Some("D") => n.to_string(),
+            Some("R") => roman(n),
+            Some("r") => roman(n).to_lowercase(),
+            Some("A") => alphabetic(n),
+            Some("a") => alphabetic(n).to_lowercase(),
+            // no style: the label is the prefix alone
+            _ => String::new(),
+        };
+        Some(prefix + &number)
+    }
+}
+
+fn roman(n: i32) -> String {
+    const NUMERALS: [(i32, &str); 13] = [
+        (1000, "M"),
+        (900, "CM"),
+        (500, "D"),
+        (400, "CD"),
+        (100, "C"),
+        (90, "XC"),
+        (50, "L"),
+        (40, "XL"),
+        (10, "X"),
+        (9, "IX"),
+        (5, "V"),
+        (4, "IV"),
+        (1, "I"),
+    ];
+    let mut n = n.min(MAX_REPEAT * 1000 + 999);
+    let mut out = String::new();
+    for (value, numeral) in NUMERALS.iter() {
+        while n >= *value {
+            out.push_str(numeral);
+            n -= value;
+        }
+    }
+    out
+}
+
+// A..Z, then AA..ZZ, AAA..ZZZ and so on
+fn alphabetic(n: i32) -> String {
+    if n < 1 {
+        return String::new();
+    }
+    let letter = (b'A' + ((n - 1) % 26) as u8) as char;
+    let count = ((n - 1) / 26 + 1).min(MAX_REPEAT);
+    std::iter::repeat(letter).take(count as usize).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn label(style: &str, start: Option<i32>, prefix: Option<&str>) -> Value {
+        let mut dict = Dict::new();
+        dict.push(PDF_NAME_S.to_string(), Value::NAME(style.to_string()));
+        if let Some(start) = start {
+            dict.push(PDF_NAME_St.to_string(), Value::INTEGER(start));
+        }
+        if let Some(prefix) = prefix {
+            dict.push(PDF_NAME_P.to_string(), Value::STRING(encode_text(prefix)));
+        }
+        Value::DICT(dict)
+    }
+
+    fn labeled_pdf(nums: Vec<Value>, page_count: i32) -> Pdf {
+        let mut labels = Dict::new();
+        labels.push("Nums".to_string(), Value::ARRAY(nums));
+        let mut catalog = Dict::new();
+        catalog.push(PDF_NAME_PageLabels.to_string(), Value::DICT(labels));
+        let mut pdf = Pdf::new();
+        pdf.root_id = pdf.add_object(Value::DICT(catalog)).id;
+        pdf.page_count = page_count;
+        pdf
+    }
+
+    #[test]
+    fn labels() {
+        let pdf = labeled_pdf(
+            vec![
+                Value::INTEGER(0),
+                label("r", None, None),
+                Value::INTEGER(3),
+                label("D", None, None),
+                Value::INTEGER(5),
+                label("A", Some(27), Some("App-")),
+            ],
+            7,
+        );
+        let labels: Vec<String> = (0..7).filter_map(|no| pdf.page_label(no)).collect();
+        assert_eq!(labels, ["i", "ii", "iii", "1", "2", "App-AA", "App-BB"]);
+        assert_eq!(pdf.page_label(7), None);
+        assert_eq!(pdf.page_by_label("2"), Some(4));
+        assert_eq!(pdf.page_by_label("App-BB"), Some(6));
+        assert_eq!(pdf.page_by_label("iv"), None);
+    }
+
+    #[test]
+    fn without_labels() {
+        let mut pdf = Pdf::new();
+        pdf.root_id = pdf.add_object(Value::DICT(Dict::new())).id;
+        pdf.page_count = 2;
+        assert_eq!(pdf.page_label(1).as_deref(), Some("2"));
+        assert_eq!(pdf.page_by_label("2"), Some(1));
+    }
+
+    #[test]
+    fn large_start() {
+        let pdf = labeled_pdf(vec![Value::INTEGER(0), label("D", Some(i32::MAX), None)], 2);
+        assert_eq!(pdf.page_label(1), Some(i32::MAX.to_string()));
+        let pdf = labeled_pdf(vec![Value::INTEGER(0), label("A", Some(i32::MAX), None)], 1);
+        assert_eq!(pdf.page_label(0).map(|l| l.len()), Some(MAX_REPEAT as usize));
+        let pdf = labeled_pdf(vec![Value::INTEGER(0), label("R", Some(i32::MAX), None)], 1);
+        assert!(pdf.page_label(0).is_some_and(|l| l.len() < 2 * MAX_REPEAT as usize));
+    }
+
+    #[test]
+    fn styles() {
+        assert_eq!(roman(1994), "MCMXCIV");
+        assert_eq!(roman(0), "");
+        assert_eq!(alphabetic(26), "Z");
+        assert_eq!(alphabetic(53), "AAA");
+        assert_eq!(alphabetic(0), "");
+    }
+}
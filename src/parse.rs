@@ -1,14 +1,11 @@
 use std::collections::HashMap;
 use std::fmt;
-use std::fs::File;
 use std::io;
 use std::io::prelude::*;
 use std::io::BufReader;
 use std::io::SeekFrom;
-use std::io::{Error, ErrorKind};
 #[derive(Debug)]
 pub enum Token {
-    None,
     OBJ_BEGIN,
     OBJ_END,
     ARRAY_BEGIN,
@@ -16,7 +13,6 @@ pub enum Token {
     STREAM_BEGIN,
     STREAM_END,
     R,
-    WORD,
     DICT_END,
     DICT_BEGIN,
     BOOL(bool),
@@ -24,7 +20,8 @@ pub enum Token {
     INTEGER(i32),
     FLOAT(f64),
     NAME(String),
-    STRING(String),
+    STRING(Vec<u8>),
+    KEYWORD(String),
     ERROR(ParseError),
 }
 #[derive(Debug)]
@@ -38,24 +35,43 @@ impl ParseError {
         }
     }
 }
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.msg)
+    }
+}
 impl From<io::Error> for ParseError {
     fn from(err: io::Error) -> ParseError {
         ParseError {
-            msg: String::from(""),
+            msg: err.to_string(),
         }
     }
 }
 
+/// Object identifier: object number and generation number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ObjectId {
+    pub id: i32,
+    pub genid: i32,
+}
+impl ObjectId {
+    pub fn new(id: i32, genid: i32) -> Self {
+        ObjectId { id, genid }
+    }
+}
+
+#[derive(Clone)]
 pub enum Value {
     INTEGER(i32),
     BOOL(bool),
     REF(i32, i32),
     NAME(String),
-    STRING(String),
+    STRING(Vec<u8>),
     NULL,
     FLOAT(f64),
     ARRAY(Vec<Value>),
     DICT(Dict),
+    STREAM(Stream),
 }
 
 impl fmt::Debug for Value{
@@ -66,15 +82,93 @@ impl fmt::Debug for Value{
             Value::BOOL(v) => write!(f,"{}", v),
             Value::REF(v0, v1) =>write!(f,"{}_{}_R", v0,v1),
             Value::NAME(v)=>write!(f,"/{}",v),
-            Value::STRING(v)=>write!(f,"\"{}\"",v),
+            Value::STRING(v)=>write!(f,"\"{}\"",String::from_utf8_lossy(v)),
             Value::NULL =>write!(f,"null"),
             Value::FLOAT(v)=>write!(f,"{}",v),
             Value::ARRAY(v) =>write!(f,"{:?}",v),
             Value::DICT(v)=>write!(f,"<<{:?}>>",v),
+            Value::STREAM(v)=>write!(f,"{:?}",v),
         }
     }
 }
-#[derive(Debug)]
+// typed accessors, None when the value has another type.
+// references are not followed, see Pdf::resolve
+impl Value {
+    pub fn is_null(&self) -> bool {
+        matches!(self, Value::NULL)
+    }
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::BOOL(v) => Some(*v),
+            _ => None,
+        }
+    }
+    pub fn as_int(&self) -> Option<i32> {
+        match self {
+            Value::INTEGER(v) => Some(*v),
+            _ => None,
+        }
+    }
+    /// Integer or real number as f64.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::INTEGER(v) => Some(*v as f64),
+            Value::FLOAT(v) => Some(*v),
+            _ => None,
+        }
+    }
+    pub fn as_name(&self) -> Option<&str> {
+        match self {
+            Value::NAME(v) => Some(v),
+            _ => None,
+        }
+    }
+    /// Raw bytes of a string object.
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Value::STRING(v) => Some(v),
+            _ => None,
+        }
+    }
+    /// A text string decoded from PDFDocEncoding, UTF-16BE or UTF-8 (by BOM).
+    pub fn as_text(&self) -> Option<String> {
+        self.as_bytes().map(decode_text)
+    }
+    pub fn as_array(&self) -> Option<&Vec<Value>> {
+        match self {
+            Value::ARRAY(v) => Some(v),
+            _ => None,
+        }
+    }
+    /// The dictionary of a dictionary or stream object.
+    pub fn as_dict(&self) -> Option<&Dict> {
+        match self {
+            Value::DICT(v) => Some(v),
+            Value::STREAM(v) => Some(&v.dict),
+            _ => None,
+        }
+    }
+    pub fn as_dict_mut(&mut self) -> Option<&mut Dict> {
+        match self {
+            Value::DICT(v) => Some(v),
+            Value::STREAM(v) => Some(&mut v.dict),
+            _ => None,
+        }
+    }
+    pub fn as_stream(&self) -> Option<&Stream> {
+        match self {
+            Value::STREAM(v) => Some(v),
+            _ => None,
+        }
+    }
+    pub fn as_object_id(&self) -> Option<ObjectId> {
+        match self {
+            Value::REF(n0, n1) => Some(ObjectId::new(*n0, *n1)),
+            _ => None,
+        }
+    }
+}
+#[derive(Debug, Clone, Default)]
 pub struct Dict {
     map: HashMap<String, Value>,
 }
@@ -99,34 +193,140 @@ impl Dict {
     pub fn get(&self, key: &str) -> Option<&Value> {
         self.map.get(key)
     }
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Value> {
+        self.map.get_mut(key)
+    }
+    pub fn remove(&mut self, key: &str) -> Option<Value> {
+        self.map.remove(key)
+    }
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.map.contains_key(key)
+    }
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+    /// Keys in sorted order.
+    pub fn keys(&self) -> Vec<&str> {
+        let mut keys: Vec<&str> = self.map.keys().map(|k| k.as_str()).collect();
+        keys.sort_unstable();
+        keys
+    }
+    /// Entries in key order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.keys().into_iter().map(move |k| (k, &self.map[k]))
+    }
+    /// Value of a /Type entry.
+    pub fn get_type(&self) -> Option<&str> {
+        self.get("Type").and_then(|v| v.as_name())
+    }
 }
+#[derive(Clone)]
 pub struct Stream {
+    pub dict: Dict,
+    // decoded data, filters that could not be applied are left in dict
     pub data: Vec<u8>,
 }
 impl Stream {
     pub fn new(data: Vec<u8>) -> Self {
-        Stream { data }
-    }
-    pub fn decode(&mut self){
-
+        Stream {
+            dict: Dict::new(),
+            data,
+        }
     }
 }
 impl fmt::Debug for Stream {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "Stream{{{} bytes}}", self.data.len())
+        write!(f, "Stream{{{:?} {} bytes}}", self.dict, self.data.len())
     }
 }
 
-pub fn peek_token(buf_reader: &mut BufReader<File>) -> Token {
-    if let Ok(pos) = buf_reader.seek(SeekFrom::Current(0)) {
+// PDFDocEncoding codes that differ from Latin-1: 0x18..0x1f and 0x80..0xa0
+const PDF_DOC_18: [char; 8] = ['\u{02d8}', '\u{02c7}', '\u{02c6}', '\u{02d9}', '\u{02dd}', '\u{02db}', '\u{02da}', '\u{02dc}'];
+const PDF_DOC_80: [char; 33] = [
+    '\u{2022}', '\u{2020}', '\u{2021}', '\u{2026}', '\u{2014}', '\u{2013}', '\u{0192}', '\u{2044}',
+    '\u{2039}', '\u{203a}', '\u{2212}', '\u{2030}', '\u{201e}', '\u{201c}', '\u{201d}', '\u{2018}',
+    '\u{2019}', '\u{201a}', '\u{2122}', '\u{fb01}', '\u{fb02}', '\u{0141}', '\u{0152}', '\u{0160}',
+    '\u{0178}', '\u{017d}', '\u{0131}', '\u{0142}', '\u{0153}', '\u{0161}', '\u{017e}', '\u{fffd}',
+    '\u{20ac}',
+];
+/// Unicode value of a PDFDocEncoding byte.
+pub fn pdf_doc_char(c: u8) -> char {
+    match c {
+        0x18..=0x1f => PDF_DOC_18[(c - 0x18) as usize],
+        0x80..=0xa0 => PDF_DOC_80[(c - 0x80) as usize],
+        0xad => '\u{fffd}',
+        _ => c as char,
+    }
+}
+/// Decodes a text string: UTF-16BE or UTF-8 when it starts with a byte order mark,
+/// PDFDocEncoding otherwise.
+pub fn decode_text(bytes: &[u8]) -> String {
+    if bytes.starts_with(&[0xfe, 0xff]) {
+        let units: Vec<u16> = bytes[2..]
+            .chunks(2)
+            .map(|c| ((c[0] as u16) << 8) | *c.get(1).unwrap_or(&0) as u16)
+            .collect();
+        // drop language escapes: U+001B lang U+001B
+        let text = String::from_utf16_lossy(&units);
+        let mut out = String::with_capacity(text.len());
+        let mut in_escape = false;
+        for ch in text.chars() {
+            if ch == '\u{1b}' {
+                in_escape = !in_escape;
+            } else if !in_escape {
+                out.push(ch);
+            }
+        }
+        return out;
+    }
+    if bytes.starts_with(&[0xef, 0xbb, 0xbf]) {
+        return String::from_utf8_lossy(&bytes[3..]).into_owned();
+    }
+    bytes.iter().map(|c| pdf_doc_char(*c)).collect()
+}
+/// Encodes a text string, PDFDocEncoding when every character has a code, UTF-16BE otherwise.
+pub fn encode_text(text: &str) -> Vec<u8> {
+    let doc: Option<Vec<u8>> = text.chars().map(pdf_doc_code).collect();
+    match doc {
+        Some(bytes) => bytes,
+        None => {
+            let mut bytes = vec![0xfe, 0xff];
+            for unit in text.encode_utf16() {
+                bytes.extend_from_slice(&unit.to_be_bytes());
+            }
+            bytes
+        }
+    }
+}
+// PDFDocEncoding byte of a character, the inverse of pdf_doc_char
+fn pdf_doc_code(ch: char) -> Option<u8> {
+    if let Some(i) = PDF_DOC_18.iter().position(|c| *c == ch) {
+        return Some(0x18 + i as u8);
+    }
+    if let Some(i) = PDF_DOC_80.iter().position(|c| *c == ch && *c != '\u{fffd}') {
+        return Some(0x80 + i as u8);
+    }
+    match ch as u32 {
+        0x09 | 0x0a | 0x0d | 0x20..=0x7e | 0xa1..=0xac | 0xae..=0xff => Some(ch as u8),
+        _ => None,
+    }
+}
+
+pub fn peek_token<R: Read + Seek>(buf_reader: &mut BufReader<R>) -> Token {
+    if let Ok(pos) = buf_reader.stream_position() {
         let tk = read_token(buf_reader);
-        buf_reader.seek(SeekFrom::Start(pos));
+        if buf_reader.seek(SeekFrom::Start(pos)).is_err() {
+            return Token::ERROR(ParseError::new("seek error"));
+        }
         return tk;
     }
-    return Token::ERROR(ParseError::new("seek error"));
+    Token::ERROR(ParseError::new("seek error"))
 }
 
-fn read_byte(buf_reader: &mut BufReader<File>) -> Result<u8, ParseError> {
+fn read_byte<R: Read + Seek>(buf_reader: &mut BufReader<R>) -> Result<u8, ParseError> {
     let mut buf: [u8; 1] = [0];
     if let Ok(1) = buf_reader.read(&mut buf) {
         return Ok(buf[0]);
@@ -134,7 +334,7 @@ fn read_byte(buf_reader: &mut BufReader<File>) -> Result<u8, ParseError> {
     Err(ParseError::new("read_byte error "))
 }
 // lexer
-pub fn read_token(buf_reader: &mut BufReader<File>) -> Token {
+pub fn read_token<R: Read + Seek>(buf_reader: &mut BufReader<R>) -> Token {
     loop {
         match read_byte(buf_reader) {
             Ok(c) => {
@@ -167,12 +367,13 @@ pub fn read_token(buf_reader: &mut BufReader<File>) -> Token {
                     },
                     b'(' => return read_string(buf_reader),
                     b')' => {
-                        // should no be here
-                        panic!("should not be herer");
+                        // unbalanced, strings are read by read_string
+                        return Token::ERROR(ParseError::new("unexpected ')'"));
                     }
                     b'[' => return Token::ARRAY_BEGIN,
                     b']' => return Token::ARRAY_END,
                     c if is_number(c) => return read_number(buf_reader, c),
+                    b'{' | b'}' => return Token::KEYWORD((c as char).to_string()),
                     _ => {
                         //what ?
                         unread_bytes(buf_reader, 1);
@@ -206,43 +407,38 @@ fn to_token(s: String) -> Token {
     } else if s == "R" {
         return Token::R;
     }
-    return Token::ERROR(ParseError::new("to_token"));
+    // operators of content streams
+    Token::KEYWORD(s)
 }
-pub fn read_stream(buf_reader: &mut BufReader<File>, size: usize) -> Result<Stream, ParseError> {
-    let mut buf: Vec<u8> = Vec::with_capacity(size);
-    buf.resize(size, 0);
+pub fn read_stream<R: Read + Seek>(buf_reader: &mut BufReader<R>, size: usize) -> Result<Stream, ParseError> {
+    let mut buf: Vec<u8> = vec![0; size];
     match buf_reader.read_exact(buf.as_mut_slice()) {
         Ok(()) => Ok(Stream::new(buf)),
-        Err(e) => Err(ParseError::new("read error")),
+        Err(_) => Err(ParseError::new("read error")),
     }
 }
-pub fn read_number(buf_reader: &mut BufReader<File>, c: u8) -> Token {
+pub fn read_number<R: Read + Seek>(buf_reader: &mut BufReader<R>, c: u8) -> Token {
     let mut num_buf: Vec<u8> = Vec::with_capacity(128);
     let mut is_real = false;
     num_buf.push(c);
     if c == b'.' {
         is_real = true;
     }
-    loop {
-        match read_byte(buf_reader) {
-            Ok(c) => match c {
-                c if is_white(c) => break,
-                c if is_delimiter(c) => {
-                    unread_bytes(buf_reader, 1);
-                    break;
-                }
-                b'.' => {
-                    is_real = true;
-                    num_buf.push(c);
-                }
-                c if is_number(c) => {
-                    num_buf.push(c);
-                }
-                _ => {
-                    return Token::ERROR(ParseError::new("wrong number"));
-                }
-            },
-            Err(e) => {
+    while let Ok(c) = read_byte(buf_reader) {
+        match c {
+            c if is_white(c) => break,
+            c if is_delimiter(c) => {
+                unread_bytes(buf_reader, 1);
+                break;
+            }
+            b'.' => {
+                is_real = true;
+                num_buf.push(c);
+            }
+            c if is_number(c) => {
+                num_buf.push(c);
+            }
+            _ => {
                 return Token::ERROR(ParseError::new("wrong number"));
             }
         }
@@ -250,7 +446,6 @@ pub fn read_number(buf_reader: &mut BufReader<File>, c: u8) -> Token {
     // parse number
     match String::from_utf8(num_buf) {
         Ok(s) => {
-            // println!("xxxxxx read number -> {}", s);
             if is_real {
                 let dr = s.parse::<f64>();
                 if let Ok(n) = dr {
@@ -262,23 +457,37 @@ pub fn read_number(buf_reader: &mut BufReader<File>, c: u8) -> Token {
                     return Token::INTEGER(n);
                 }
             }
-            return Token::ERROR(ParseError::new("from_utf8 read_number"));
+            Token::ERROR(ParseError::new("from_utf8 read_number"))
         }
         Err(_) => Token::ERROR(ParseError::new("from_utf8")),
     }
 }
 
-pub fn read_hex_string(buf_reader: &mut BufReader<File>) -> Token {
-    // read until >
+pub fn read_hex_string<R: Read + Seek>(buf_reader: &mut BufReader<R>) -> Token {
+    // read until >, white space is ignored, odd digit count means a trailing 0
     let mut buf: Vec<u8> = Vec::new();
-    if let Ok(n) = buf_reader.read_until(b'>', &mut buf) {
-        if let Ok(s) = String::from_utf8(buf) {
-            return Token::STRING(s);
+    if buf_reader.read_until(b'>', &mut buf).is_ok() {
+        return Token::STRING(decode_hex(&buf));
+    }
+    Token::ERROR(ParseError::new("read_hex_string"))
+}
+pub fn decode_hex(buf: &[u8]) -> Vec<u8> {
+    let mut out: Vec<u8> = Vec::with_capacity(buf.len() / 2);
+    let mut high: Option<u8> = None;
+    for &c in buf {
+        if let Ok(v) = hex_to_char(c) {
+            match high.take() {
+                Some(h) => out.push((h << 4) + v),
+                None => high = Some(v),
+            }
         }
     }
-    Token::ERROR(ParseError::new("read_hex_string from_utf8"))
+    if let Some(h) = high {
+        out.push(h << 4);
+    }
+    out
 }
-pub fn read_string(buf_reader: &mut BufReader<File>) -> Token {
+pub fn read_string<R: Read + Seek>(buf_reader: &mut BufReader<R>) -> Token {
     let mut name_buf: Vec<u8> = Vec::new();
     // name_buf.push(b'(');
     let mut count: u32 = 1;
@@ -294,47 +503,45 @@ pub fn read_string(buf_reader: &mut BufReader<File>) -> Token {
                                 b'n' => name_buf.push(b'\n'),
                                 b'r' => name_buf.push(b'\r'),
                                 b't' => name_buf.push(b'\t'),
-                                b'b' => {
-                                    name_buf.pop();
-                                }
+                                b'b' => name_buf.push(0x08),
+                                b'f' => name_buf.push(0x0c),
                                 b'\n' => (),
-                                b'\r' => (),
-                                b'f' => {
-                                    // TODO ??? ignore.
-                                    //    name_buf.push(b'\f'),
+                                b'\r' => {
+                                    // line continuation, \r\n counts as one end of line
+                                    if let Ok(c2) = read_byte(buf_reader) {
+                                        if c2 != b'\n' {
+                                            unread_bytes(buf_reader, 1);
+                                        }
+                                    }
                                 }
                                 b'(' => name_buf.push(b'('),
                                 b')' => name_buf.push(b')'),
                                 b'\\' => name_buf.push(c1),
-                                b'0'...b'7' => {
-                                    // todo at most three number 0-7
-                                    if let Ok(c2) = read_byte(buf_reader) {
-                                        match c2 {
-                                            b'0'...b'7' => {
-                                                if let Ok(c3) = read_byte(buf_reader) {
-                                                    match c3 {
-                                                        b'0'...b'7' => {
-                                                            // combine c1 c2 c3
-                                                        }
-                                                        _ => {
-                                                            unread_bytes(buf_reader, 1);
-                                                            // c1 c2 TODO
-                                                        }
-                                                    }
-                                                }
+                                b'0'..=b'7' => {
+                                    // at most three octal digits
+                                    let mut v: u32 = (c1 - b'0') as u32;
+                                    for _ in 0..2 {
+                                        match read_byte(buf_reader) {
+                                            Ok(c2) if (b'0'..=b'7').contains(&c2) => {
+                                                v = v * 8 + (c2 - b'0') as u32;
                                             }
-                                            _ => {
-                                               unread_bytes(buf_reader, 1);
-                                                // c1
+                                            Ok(_) => {
+                                                unread_bytes(buf_reader, 1);
+                                                break;
                                             }
+                                            Err(_) => break,
                                         }
                                     }
+                                    name_buf.push(v as u8);
                                 }
-                                _ => (), // should not be here ignore
+                                _ => name_buf.push(c1), // unknown escape, keep the char
                             }
                         }
                     }
-                    b'(' => count += 1,
+                    b'(' => {
+                        count += 1;
+                        name_buf.push(c);
+                    }
                     b')' => {
                         count -= 1;
                         if count == 0 {
@@ -347,134 +554,158 @@ pub fn read_string(buf_reader: &mut BufReader<File>) -> Token {
                     }
                 }
             }
-            Err(e) => {}
+            Err(e) => return Token::ERROR(e),
         }
     }
-    match String::from_utf8(name_buf) {
-        Ok(s) => Token::STRING(s),
-        Err(_) => Token::ERROR(ParseError::new("read error in read_string")),
-    }
+    Token::STRING(name_buf)
 }
-fn unread_bytes(buf_reader: &mut BufReader<File>, n :i64){
-    buf_reader.seek(SeekFrom::Current(-n));
+fn unread_bytes<R: Read + Seek>(buf_reader: &mut BufReader<R>, n :i64){
+    let _ = buf_reader.seek_relative(-n);
 }
-pub fn read_name(buf_reader: &mut BufReader<File>) -> Token {
+pub fn read_name<R: Read + Seek>(buf_reader: &mut BufReader<R>) -> Token {
     let mut name_buf: Vec<u8> = Vec::with_capacity(128);
 
-    loop {
-        match read_byte(buf_reader) {
-            Ok(c) => {
-                match c {
-                    c if is_white(c) => break,
-                    c if is_delimiter(c) => {
-                        unread_bytes(buf_reader, 1);
-                        break;
-                    }
+    while let Ok(c) = read_byte(buf_reader) {
+        match c {
+            c if is_white(c) => break,
+            c if is_delimiter(c) => {
+                unread_bytes(buf_reader, 1);
+                break;
+            }
 
-                    b'#' => {
-                        //read two byte
-                        if let Ok(c0) = read_byte(buf_reader) {
-                            if let Ok(c1) = read_byte(buf_reader) {
-                                // c0,c1 -> c
-                                if let Ok(c0) = hex_to_char(c0) {
-                                    if let Ok(c1) = hex_to_char(c1) {
-                                        name_buf.push(c0 + c1);
-                                        continue;
-                                    }
-                                }
+            b'#' => {
+                //read two byte
+                if let Ok(c0) = read_byte(buf_reader) {
+                    if let Ok(c1) = read_byte(buf_reader) {
+                        // c0,c1 -> c
+                        if let Ok(c0) = hex_to_char(c0) {
+                            if let Ok(c1) = hex_to_char(c1) {
+                                name_buf.push((c0 << 4) + c1);
+                                continue;
                             }
                         }
-                        break;
                     }
-                    _ => name_buf.push(c),
                 }
-            }
-            Err(e) => {
                 break;
             }
+            _ => name_buf.push(c),
         }
     }
     match String::from_utf8(name_buf) {
         Ok(s) => Token::NAME(s),
-        Err(e) => Token::ERROR(ParseError::new("from_utf8 read name")),
+        Err(e) => Token::NAME(String::from_utf8_lossy(e.as_bytes()).into_owned()),
     }
 
     // Ok(Token::NAME(s))
 }
 fn hex_to_char(c0: u8) -> Result<u8, ParseError> {
     match c0 {
-        b'0'...b'9' => Ok(c0 - b'0'),
-        b'a'...b'z' => Ok(c0 - b'a' + 10),
-        b'A'...b'Z' => Ok(c0 - b'A' + 10),
+        b'0'..=b'9' => Ok(c0 - b'0'),
+        b'a'..=b'f' => Ok(c0 - b'a' + 10),
+        b'A'..=b'F' => Ok(c0 - b'A' + 10),
         _ => Err(ParseError::new("not hex ")),
     }
 }
-pub fn skip_comment(buf_reader: &mut BufReader<File>) {
+pub fn skip_comment<R: Read + Seek>(buf_reader: &mut BufReader<R>) {
     // read until end of line
     let mut buf: [u8; 1] = [0];
-    loop {
-        match buf_reader.read(&mut buf) {
-            Ok(1) => match buf[0] {
-                b'\n' => break,
-                b'\r' => break,
-                _ => (),
-            },
-            _ => break,
+    while let Ok(1) = buf_reader.read(&mut buf) {
+        if buf[0] == b'\n' || buf[0] == b'\r' {
+            break;
         }
     }
 }
-pub fn skip_white(buf_reader: &mut BufReader<File>) {
+pub fn skip_white<R: Read + Seek>(buf_reader: &mut BufReader<R>) {
     let mut buf: [u8; 1] = [0];
-    loop {
-        match buf_reader.read(&mut buf) {
-            Ok(c) => {
-                if !is_white(buf[0]) {
-                    unread_bytes(buf_reader, 1);
-                    break;
-                }
-            }
-            _ => break,
+    while let Ok(1) = buf_reader.read(&mut buf) {
+        if !is_white(buf[0]) {
+            unread_bytes(buf_reader, 1);
+            break;
         }
     }
 }
 //'\x00':case'\x09':case'\x0a':case'\x0c':case'\x0d':case'\x20'
 pub fn is_white(ch: u8) -> bool {
-    match ch {
-        b'\0' => true,
-        b'\t' => true,
-        b'\r' => true,
-        0x0c => true, // form feed 换页符号
-        b'\n' => true,
-        b' ' => true,
-        _ => false,
-    }
+    // 0x0c: form feed 换页符号
+    matches!(ch, b'\0' | b'\t' | b'\r' | 0x0c | b'\n' | b' ')
 }
 
-fn is_delimiter(c: u8) -> bool {
+pub fn is_delimiter(c: u8) -> bool {
     // 空白，
-    match c {
-        b'(' => true,
-        b')' => true,
-        b'<' => true,
-        b'>' => true,
-        b'[' => true,
-        b']' => true,
-        b'{' => true,
-        b'}' => true,
-        b'/' => true,
-        b'%' => true,
-        _ => false,
-    }
+    matches!(
+        c,
+        b'(' | b')' | b'<' | b'>' | b'[' | b']' | b'{' | b'}' | b'/' | b'%'
+    )
 }
 
 pub fn is_number(ch: u8) -> bool {
-    // println!("is_number({})",ch);
-    let ret = match ch {
-        b'+' => true,
-        b'-' => true,
-        b'.' => true,
-        b'0'...b'9' => true,
-        _ => false,
-    };
-    ret
+    matches!(ch, b'+' | b'-' | b'.' | b'0'..=b'9')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn tokens(s: &[u8]) -> Vec<Token> {
+        let mut reader = BufReader::new(Cursor::new(s.to_vec()));
+        let mut tokens = Vec::new();
+        loop {
+            match read_token(&mut reader) {
+                Token::ERROR(_) => break,
+                tk => tokens.push(tk),
+            }
+        }
+        tokens
+    }
+
+    #[test]
+    fn name_escapes() {
+        assert!(matches!(&tokens(b"/A#20B")[..], [Token::NAME(s)] if s == "A B"));
+        assert!(matches!(&tokens(b"/#41#42 /x")[..], [Token::NAME(a), Token::NAME(b)]
+            if a == "AB" && b == "x"));
+        assert!(matches!(&tokens(b"/Adobe#2DJapan1")[..], [Token::NAME(s)] if s == "Adobe-Japan1"));
+    }
+    #[test]
+    fn hex_digits() {
+        assert_eq!(hex_to_char(b'f').ok(), Some(15));
+        assert_eq!(hex_to_char(b'F').ok(), Some(15));
+        assert!(hex_to_char(b'g').is_err());
+        assert!(hex_to_char(b'Z').is_err());
+        assert!(matches!(&tokens(b"<4a6B>")[..], [Token::STRING(s)] if s == b"Jk"));
+        // non-hex characters are skipped, an odd digit count pads with 0
+        assert_eq!(decode_hex(b"4g1 z7"), vec![0x41, 0x70]);
+        // a name escape with non-hex digits ends the name
+        assert!(matches!(&tokens(b"/A#zzB")[..], [Token::NAME(s), ..] if s == "A"));
+    }
+    #[test]
+    fn unterminated_string() {
+        let read = |s: &[u8]| read_token(&mut BufReader::new(Cursor::new(s.to_vec())));
+        assert!(matches!(read(b"(a (b) c)"), Token::STRING(s) if s == b"a (b) c"));
+        assert!(matches!(read(b"(abc"), Token::ERROR(_)));
+        assert!(matches!(read(b"(a (b)"), Token::ERROR(_)));
+        assert!(matches!(read(b"(a\\"), Token::ERROR(_)));
+    }
+    #[test]
+    fn bare_words() {
+        let tks = tokens(b"true false null obj endobj R BT Tj");
+        assert!(matches!(
+            &tks[..],
+            [
+                Token::BOOL(true),
+                Token::BOOL(false),
+                Token::NULL,
+                Token::OBJ_BEGIN,
+                Token::OBJ_END,
+                Token::R,
+                Token::KEYWORD(bt),
+                Token::KEYWORD(tj),
+            ] if bt == "BT" && tj == "Tj"
+        ));
+    }
+    #[test]
+    fn io_error_message() {
+        let err = ParseError::from(io::Error::new(io::ErrorKind::Other, "boom"));
+        assert_eq!(err.to_string(), "boom");
+    }
 }
@@ -15,20 +15,119 @@ use filter::*;
 #[path = "parse.rs"]
 mod parse;
 use parse::*;
+pub use parse::{Dict, ObjectId, Stream, Value};
+
+#[path = "page.rs"]
+mod page;
+pub use page::*;
+
+#[path = "xml.rs"]
+mod xml;
+
+#[path = "info.rs"]
+mod info;
+pub use info::*;
+
+#[path = "tree.rs"]
+mod tree;
+
+#[path = "dest.rs"]
+mod dest;
+pub use dest::*;
+
+#[path = "action.rs"]
+mod action;
+pub use action::*;
+
+#[path = "outline.rs"]
+mod outline;
+pub use outline::*;
+
+#[path = "pagelabel.rs"]
+mod pagelabel;
+
+#[path = "annot.rs"]
+mod annot;
+pub use annot::*;
+
+#[path = "writer.rs"]
+mod writer;
+use writer::*;
+
+#[path = "form.rs"]
+mod form;
+pub use form::*;
+
+#[path = "fdf.rs"]
+mod fdf;
+
+#[path = "xfdf.rs"]
+mod xfdf;
+
+#[path = "attach.rs"]
+mod attach;
+pub use attach::*;
+
+#[path = "ocg.rs"]
+mod ocg;
+pub use ocg::*;
+
+#[path = "content.rs"]
+mod content;
+pub use content::*;
+
+#[path = "structure.rs"]
+mod structure;
+pub use structure::*;
+
+#[path = "encoding.rs"]
+mod encoding;
+pub use encoding::*;
+
+#[path = "afm.rs"]
+mod afm;
+pub use afm::*;
+
+#[path = "cjk.rs"]
+mod cjk;
+use cjk::*;
+
+#[path = "cmap.rs"]
+mod cmap;
+pub use cmap::*;
+
+#[path = "font.rs"]
+mod font;
+pub use font::*;
+
+#[path = "text.rs"]
+mod text;
+pub use text::*;
+
+#[path = "layout.rs"]
+mod layout;
+pub use layout::*;
+
+#[path = "table.rs"]
+mod table;
+pub use table::*;
 
 const PDF_NAME_Root: &str = "Root";
-const PDF_NAME_Type: &str = "Type";
 const PDF_NAME_Length: &str = "Length";
-const PDF_NAME_FlateDecode: &str = "FlateDecode";
 const PDF_NAME_Filter: &str = "Filter";
+const PDF_NAME_DecodeParms: &str = "DecodeParms";
 const PDF_NAME_Pages: &str = "Pages";
 const PDF_NAME_Count: &str = "Count";
 const PDF_NAME_Kids: &str = "Kids";
 const PDF_NAME_MediaBox: &str = "MediaBox";
 const PDF_NAME_Contents: &str = "Contents";
 const PDF_NAME_Parent: &str = "Parent";
-const PDF_NAME_Resources: &str = "Resources";
-const PDF_NAME_Font: &str = "Font";
+const PDF_NAME_CropBox: &str = "CropBox";
+const PDF_NAME_BleedBox: &str = "BleedBox";
+const PDF_NAME_TrimBox: &str = "TrimBox";
+const PDF_NAME_ArtBox: &str = "ArtBox";
+const PDF_NAME_Rotate: &str = "Rotate";
+const PDF_NAME_UserUnit: &str = "UserUnit";
 
 #[derive(Debug)]
 struct Obj {
@@ -36,9 +135,8 @@ struct Obj {
     offset: i32,
     genid: i32,
     used: bool,
-    // box real data
-    dict: Option<Dict>,
-    stream: Option<Stream>,
+    // box real data, a stream object is Value::STREAM
+    value: Option<Value>,
 }
 impl Obj {
     fn new(id: i32, offset: i32, genid: i32, used: bool) -> Obj {
@@ -47,20 +145,17 @@ impl Obj {
             offset,
             genid,
             used,
-            dict: None,
-            stream: None,
+            value: None,
         }
     }
-    fn fill() -> bool {
-        false
+    fn dict(&self) -> Option<&Dict> {
+        self.value.as_ref().and_then(|v| v.as_dict())
     }
     fn get(&self, key: &str) -> Option<&Value> {
-        match &self.dict {
-            Some(dict) => dict.get(key),
-            _ => None,
-        }
+        self.dict().and_then(|dict| dict.get(key))
     }
 }
+static NULL: Value = Value::NULL;
 #[derive(Debug)]
 pub struct Pdf {
     version: String,
@@ -69,6 +164,13 @@ pub struct Pdf {
     root_id: i32,
     pages_id: i32,
     page_count: i32,
+    // page object ids in document order
+    pages: Vec<i32>,
+}
+impl Default for Pdf {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Pdf {
@@ -80,84 +182,49 @@ impl Pdf {
             root_id: 0,
             page_count: 0,
             pages_id: 0,
+            pages: Vec::new(),
         }
     }
     pub fn open(path: &str) -> io::Result<Pdf> {
-        let mut file = File::open(path).unwrap();
-        println!("{:?}", file);
-        let len: u64 = match file.seek(SeekFrom::End(0)) {
-            Ok(len) => len,
-            Err(_) => 0,
-        };
-        println!("file length {}", len);
-        file.seek(SeekFrom::Start(0))?;
+        let file = File::open(path)?;
         let mut buf_reader = BufReader::new(file);
         // let n = buf_reader.read_line(&mut ver);
         let eol = [b'\n', b'\r'];
-        if let Ok(ver) = read_until(&mut buf_reader, &eol) {
-            println!("ver {}", ver);
-            if &ver[0..5] == "%PDF-" {
-                println!("match {}", &ver[5..]);
-            }
-        }
+        let version = read_until(&mut buf_reader, &eol);
         // startxref
-        buf_reader.seek(SeekFrom::End(-32));
+        buf_reader.seek(SeekFrom::End(-32))?;
 
         // read tails
         let mut ref_offset: usize = 0;
-        for i in 0..4 {
+        for _ in 0..4 {
             let mut buffer = String::new();
             buf_reader.read_line(&mut buffer)?;
-            if buffer == "startxref\n" {
+            if buffer.trim_end() == "startxref" {
                 let mut buffer = String::new();
                 buf_reader.read_line(&mut buffer)?;
-                ref_offset = buffer.trim().parse().expect("??");
+                ref_offset = buffer
+                    .trim()
+                    .parse()
+                    .map_err(|_| Error::new(ErrorKind::Other, "bad startxref"))?;
                 break;
             }
-            // println!("{}", buffer);
         }
-        println!("ref_start_oos {}", ref_offset);
         if ref_offset == 0 {
             return Err(Error::new(ErrorKind::Other, "ref_offset"));
         }
-        // Err("?")
         buf_reader.seek(SeekFrom::Start(ref_offset as u64))?;
         let mut buffer = String::new();
         buf_reader.read_line(&mut buffer)?;
-        if buffer != "xref\n" {
+        if buffer.trim_end() != "xref" {
             return Err(Error::new(ErrorKind::Other, "ref sign"));
         }
         let mut pdf = Pdf::new();
-        loop {
-            let mut buffer = String::new();
-            buf_reader.read_line(&mut buffer)?;
-            if buffer == "trailer\n" {
-                pdf.trailer = read_trailer(&mut buf_reader);
-                break;
-            }
-            let iter: Vec<i32> = buffer
-                .split_whitespace()
-                .map(|x| x.parse::<i32>().unwrap())
-                .collect();
-            let mut oid = iter[0]; //.parse().unwrap();
-            let count = iter[1]; //.parse().unwrap();
-            for i in 0..count {
-                buffer.clear();
-                buf_reader.read_line(&mut buffer)?;
-                let three: Vec<&str> = buffer.split_whitespace().collect();
-                println!("{:?}", three);
-                let offset: i32 = three[0].parse().expect("??");
-                let genid: i32 = three[1].parse().expect("??");
-                let sign: bool = match three[2] {
-                    "n" => true,
-                    _ => false,
-                };
-                let obj = Obj::new(oid, offset, genid, sign);
-                pdf.obj_list.insert(oid, obj);
-                oid += 1;
+        if let Ok(ver) = &version {
+            if let Some(v) = ver.strip_prefix("%PDF-") {
+                pdf.version = v.to_string();
             }
-            // println!("{:?}", iter);
         }
+        read_xref_table(&mut buf_reader, &mut pdf)?;
         // read all obj
         read_objects(&mut pdf, &mut buf_reader);
         if pdf.root_id > 0 {
@@ -165,14 +232,157 @@ impl Pdf {
             load_doc(&mut pdf);
             return Ok(pdf);
         }
-        return Err(Error::new(ErrorKind::Other, "not found root"));
+        Err(Error::new(ErrorKind::Other, "not found root"))
+    }
+    pub fn version(&self) -> &str {
+        &self.version
     }
     pub fn page_count(&self) -> i32 {
         self.page_count
     }
-    pub fn page_text(&self, no: i32) -> String {
-        load_page(self, no);
-        String::from("page?")
+    /// Returns the page at index `no` (zero based) with its boxes and rotation resolved.
+    pub fn page(&self, no: i32) -> io::Result<Page> {
+        match self.page_id(no) {
+            Some(id) => Ok(Page::load(self, no, id)),
+            None => Err(Error::new(ErrorKind::Other, "page out of range")),
+        }
+    }
+    fn page_id(&self, no: i32) -> Option<i32> {
+        if no < 0 {
+            return None;
+        }
+        self.pages.get(no as usize).copied()
+    }
+    pub fn trailer(&self) -> Option<&Dict> {
+        self.trailer.as_ref()
+    }
+    /// The document catalog (/Root).
+    pub fn catalog(&self) -> Option<&Dict> {
+        self.obj_list.get(&self.root_id).and_then(|obj| obj.dict())
+    }
+    /// The value of an indirect object, None if it is not in the file.
+    pub fn get_object(&self, id: ObjectId) -> Option<&Value> {
+        match self.obj_list.get(&id.id) {
+            Some(obj) if obj.genid == id.genid => obj.value.as_ref(),
+            _ => None,
+        }
+    }
+    pub fn get_object_mut(&mut self, id: ObjectId) -> Option<&mut Value> {
+        match self.obj_list.get_mut(&id.id) {
+            Some(obj) if obj.genid == id.genid => obj.value.as_mut(),
+            _ => None,
+        }
+    }
+    /// Replaces or creates an indirect object.
+    pub fn set_object(&mut self, id: ObjectId, value: Value) {
+        let obj = self
+            .obj_list
+            .entry(id.id)
+            .or_insert_with(|| Obj::new(id.id, 0, id.genid, true));
+        obj.genid = id.genid;
+        obj.used = true;
+        obj.value = Some(value);
+    }
+    /// Adds a new indirect object with the next free object number.
+    pub fn add_object(&mut self, value: Value) -> ObjectId {
+        let id = ObjectId::new(self.obj_list.keys().max().map_or(1, |n| n + 1), 0);
+        self.set_object(id, value);
+        id
+    }
+    /// Deletes an indirect object, references to it resolve to null.
+    pub fn remove_object(&mut self, id: ObjectId) -> Option<Value> {
+        match self.obj_list.get(&id.id) {
+            Some(obj) if obj.genid == id.genid => self.obj_list.remove(&id.id)?.value,
+            _ => None,
+        }
+    }
+    /// Follows references until a direct value, missing objects resolve to null.
+    pub fn resolve<'a>(&'a self, val: &'a Value) -> &'a Value {
+        let mut val = val;
+        // guard against reference cycles
+        for _ in 0..32 {
+            match val {
+                Value::REF(n0, n1) => match self.get_object(ObjectId::new(*n0, *n1)) {
+                    Some(v) => val = v,
+                    None => return &NULL,
+                },
+                _ => return val,
+            }
+        }
+        &NULL
+    }
+    /// All loaded objects ordered by object number.
+    pub fn objects(&self) -> impl Iterator<Item = (ObjectId, &Value)> {
+        let mut ids: Vec<&i32> = self.obj_list.keys().collect();
+        ids.sort_unstable();
+        ids.into_iter().filter_map(move |id| {
+            let obj = &self.obj_list[id];
+            obj.value
+                .as_ref()
+                .map(|v| (ObjectId::new(obj.id, obj.genid), v))
+        })
+    }
+    // dict[key] with references resolved, None for missing or null entries
+    fn lookup<'a>(&'a self, dict: &'a Dict, key: &str) -> Option<&'a Value> {
+        match dict.get(key).map(|v| self.resolve(v)) {
+            Some(Value::NULL) | None => None,
+            v => v,
+        }
+    }
+    // id of a loaded object with its generation number
+    fn object_id(&self, id: i32) -> ObjectId {
+        ObjectId::new(id, self.obj_list.get(&id).map_or(0, |obj| obj.genid))
+    }
+    // the dictionary at `path` below object `id`, following indirect references.
+    // missing or non dictionary entries along a direct path are replaced by empty dicts
+    fn dict_path_mut(&mut self, id: ObjectId, path: &[&str]) -> Option<&mut Dict> {
+        let mut id = id;
+        let mut start = 0;
+        'indirect: loop {
+            let mut dict = self.get_object(id)?.as_dict()?;
+            for (i, key) in path.iter().enumerate().skip(start) {
+                match dict.get(key) {
+                    Some(Value::REF(n0, n1)) => {
+                        id = ObjectId::new(*n0, *n1);
+                        start = i + 1;
+                        continue 'indirect;
+                    }
+                    Some(Value::DICT(d)) => dict = d,
+                    _ => break,
+                }
+            }
+            break;
+        }
+        let mut dict = self.get_object_mut(id)?.as_dict_mut()?;
+        for key in &path[start..] {
+            if !matches!(dict.get(key), Some(Value::DICT(_))) {
+                dict.push(key.to_string(), Value::DICT(Dict::new()));
+            }
+            dict = match dict.get_mut(key) {
+                Some(Value::DICT(d)) => d,
+                _ => return None,
+            };
+        }
+        Some(dict)
+    }
+    // the decoded /Contents of a page, streams of an array joined by white space
+    fn page_contents(&self, page_id: i32) -> Vec<u8> {
+        let mut data = Vec::new();
+        let contents = self
+            .obj_list
+            .get(&page_id)
+            .and_then(|obj| obj.dict())
+            .and_then(|page| self.lookup(page, PDF_NAME_Contents));
+        let streams = match contents {
+            Some(Value::ARRAY(items)) => items.iter().map(|v| self.resolve(v)).collect(),
+            Some(v) => vec![v],
+            None => Vec::new(),
+        };
+        for stream in streams.into_iter().filter_map(|v| v.as_stream()) {
+            data.extend_from_slice(&stream.data);
+            data.push(b'\n');
+        }
+        data
     }
 }
 fn load_doc(pdf: &mut Pdf) {
@@ -181,79 +391,140 @@ fn load_doc(pdf: &mut Pdf) {
             pdf.pages_id = *n0;
             if let Some(pages) = pdf.obj_list.get(n0) {
                 if let Some(Value::INTEGER(n)) = pages.get(PDF_NAME_Count) {
-                    println!("page count is {}", n);
                     pdf.page_count = *n;
                 }
-                if let Some(Value::ARRAY(kids)) = pages.get(PDF_NAME_Kids) {
-                    println!("kids {:?}", kids);
-                }
             }
+            let mut page_ids = Vec::new();
+            collect_pages(pdf, *n0, &mut page_ids, 0);
+            if !page_ids.is_empty() {
+                pdf.page_count = page_ids.len() as i32;
+            }
+            pdf.pages = page_ids;
         }
     }
 }
-fn load_page(pdf: &Pdf, pn: i32) {
-    if let Some(pages) = pdf.obj_list.get(&pdf.pages_id) {
-        if let Some(Value::ARRAY(kids)) = pages.get(PDF_NAME_Kids) {
-            println!("kids {:?}", kids);
-            if let Some(Value::REF(n0, n1)) = kids.get(pn as usize) {
-                if let Some(page) = pdf.obj_list.get(&n0) {
-                    if let Some(Value::ARRAY(rect)) = page.get(PDF_NAME_MediaBox) {
-                        println!("page Size {:?}", rect);
-                    }
-                    match page.get(PDF_NAME_Contents) {
-                        Some(Value::STRING(s)) => {
-                            println!("page content {}", s);
-                        }
-                        Some(Value::REF(n0, n1)) => {
-                            if let Some(content) = pdf.obj_list.get(&n0) {
-                                if let Some(stream) = &content.stream {
-                                    if let Ok(data) = str::from_utf8(&stream.data) {
-                                        // let content = String::from_utf8(data);
-                                        println!("content stream is {}", data);
-                                    }
-                                }
-                            }
-                        }
-                        _ => (),
+// a document from the text of its "n 0 obj ... endobj" objects, 1 is the catalog
+#[cfg(test)]
+pub(crate) fn test_pdf(objects: &str) -> Pdf {
+    let mut pdf = Pdf::new();
+    for (id, value) in fdf::read_fdf_objects(objects.as_bytes()) {
+        pdf.set_object(ObjectId::new(id, 0), value);
+    }
+    pdf.root_id = 1;
+    load_doc(&mut pdf);
+    pdf
+}
+// walk the page tree, leaves are pages
+fn collect_pages(pdf: &Pdf, node_id: i32, page_ids: &mut Vec<i32>, depth: i32) {
+    if depth > 64 {
+        return;
+    }
+    if let Some(node) = pdf.obj_list.get(&node_id) {
+        match node.get(PDF_NAME_Kids) {
+            Some(Value::ARRAY(kids)) => {
+                for kid in kids {
+                    if let Value::REF(n0, _) = kid {
+                        collect_pages(pdf, *n0, page_ids, depth + 1);
                     }
                 }
             }
+            _ => page_ids.push(node_id),
         }
     }
 }
-
-fn read_objects(pdf: &mut Pdf, buf_reader: &mut BufReader<File>) {
+// look up a key on the page, then through its /Parent chain (inheritable attributes)
+fn inherited_attr<'a>(pdf: &'a Pdf, page_id: i32, key: &str) -> Option<&'a Value> {
+    let mut node_id = page_id;
+    for _ in 0..64 {
+        let node = pdf.obj_list.get(&node_id)?;
+        if let Some(val) = node.get(key) {
+            return Some(pdf.resolve(val));
+        }
+        match node.get(PDF_NAME_Parent) {
+            Some(Value::REF(n0, _)) => node_id = *n0,
+            _ => return None,
+        }
+    }
+    None
+}
+fn read_objects<R: Read + Seek>(pdf: &mut Pdf, buf_reader: &mut BufReader<R>) {
     let mut has_root = false; // TOOD pdf.trailer.
     if let Some(trailer) = &pdf.trailer {
-        if let Some(Value::REF(n0, n1)) = trailer.get(PDF_NAME_Root) {
+        if let Some(Value::REF(n0, _)) = trailer.get(PDF_NAME_Root) {
             pdf.root_id = *n0;
             has_root = true;
         }
     }
     // for obj in &mut pdf.obj_list {
-    for (key, obj) in &mut pdf.obj_list {
-        println!("{:?}", obj);
-        if obj.used {
-            if let Ok(_) = buf_reader.seek(SeekFrom::Start(obj.offset as u64)) {
-                read_object(buf_reader, obj);
-                if !has_root {
-                    if let Some(dict) = &obj.dict {
-                        // try find root
-                        if let Some(Value::REF(n0, n1)) = dict.get(PDF_NAME_Root) {
-                            // record root id TODO
-                            pdf.root_id = *n0;
-                            has_root = true;
-                        }
+    for obj in pdf.obj_list.values_mut() {
+        if obj.used && buf_reader.seek(SeekFrom::Start(obj.offset as u64)).is_ok() {
+            // damaged objects are left without a value
+            if read_object(buf_reader, obj).is_ok() && !has_root {
+                if let Some(dict) = obj.dict() {
+                    // try find root
+                    if let Some(Value::REF(n0, _)) = dict.get(PDF_NAME_Root) {
+                        // record root id TODO
+                        pdf.root_id = *n0;
+                        has_root = true;
                     }
                 }
             }
         }
     }
 }
-fn read_trailer(buf_reader: &mut BufReader<File>) -> Option<Dict> {
+// xref subsections up to the trailer, entries are "offset genid n|f"
+fn read_xref_table<R: Read + Seek>(
+    buf_reader: &mut BufReader<R>,
+    pdf: &mut Pdf,
+) -> io::Result<()> {
+    let truncated = || Error::new(ErrorKind::Other, "xref without trailer");
+    loop {
+        let mut buffer = String::new();
+        if buf_reader.read_line(&mut buffer)? == 0 {
+            return Err(truncated());
+        }
+        match buffer.trim_end() {
+            "trailer" => {
+                pdf.trailer = read_trailer(buf_reader);
+                return Ok(());
+            }
+            "" => continue,
+            _ => (),
+        }
+        let header: Vec<i32> = buffer
+            .split_whitespace()
+            .map(|x| x.parse::<i32>().ok())
+            .collect::<Option<_>>()
+            .unwrap_or_default();
+        let (first, count) = match header[..] {
+            [first, count] if first >= 0 && count >= 0 => (first, count),
+            _ => return Err(Error::new(ErrorKind::Other, "bad xref subsection")),
+        };
+        let end = first
+            .checked_add(count)
+            .ok_or_else(|| Error::new(ErrorKind::Other, "bad xref subsection"))?;
+        for oid in first..end {
+            buffer.clear();
+            if buf_reader.read_line(&mut buffer)? == 0 {
+                return Err(truncated());
+            }
+            let three: Vec<&str> = buffer.split_whitespace().collect();
+            let (offset, genid, sign) = match three[..] {
+                [offset, genid, sign @ ("n" | "f")] => match (offset.parse(), genid.parse()) {
+                    (Ok(offset), Ok(genid)) => (offset, genid, sign == "n"),
+                    _ => return Err(Error::new(ErrorKind::Other, "bad xref entry")),
+                },
+                _ => return Err(Error::new(ErrorKind::Other, "bad xref entry")),
+            };
+            let obj = Obj::new(oid, offset, genid, sign);
+            pdf.obj_list.insert(oid, obj);
+        }
+    }
+}
+
+fn read_trailer<R: Read + Seek>(buf_reader: &mut BufReader<R>) -> Option<Dict> {
     if let Ok(dict) = read_dictonary(buf_reader) {
-        println!("trailer dict {:?}", dict);
-        // read Info obj
+        // Info obj is read on demand, see Pdf::info
         // read Root obj
         return Some(dict);
     }
@@ -261,71 +532,186 @@ fn read_trailer(buf_reader: &mut BufReader<File>) -> Option<Dict> {
 }
 
 // obj ...  endobj
-fn read_object(buf_reader: &mut BufReader<File>, obj: &mut Obj) {
-    if let Token::INTEGER(oid) = read_token(buf_reader) {
-        if let Token::INTEGER(gid) = read_token(buf_reader) {
+fn read_object<R: Read + Seek>(buf_reader: &mut BufReader<R>, obj: &mut Obj) -> io::Result<()> {
+    if let Token::INTEGER(_) = read_token(buf_reader) {
+        if let Token::INTEGER(_) = read_token(buf_reader) {
             if let Token::OBJ_BEGIN = read_token(buf_reader) {
-                println!("{} {} obj", oid, gid);
-                if let Token::DICT_BEGIN = read_token(buf_reader) {
-                    if let Ok(dict) = read_dictonary(buf_reader) {
+                match read_token(buf_reader) {
+                    Token::DICT_BEGIN => {
+                        let dict = read_dictonary(buf_reader)?;
                         match read_token(buf_reader) {
                             Token::OBJ_END => {}
                             Token::STREAM_BEGIN => {
-                                if let Some(val) = dict.get(PDF_NAME_Length) {
-                                    if let Value::INTEGER(len) = val {
-                                        if let Ok(mut stream) =
-                                            read_stream(buf_reader, *len as usize)
-                                        {
-                                            // decode stream
-                                            println!("len {}", len);
-                                            let fs = String::from(PDF_NAME_FlateDecode);
-                                            match dict.get(PDF_NAME_Filter) {
-                                                Some(Value::NAME(fs)) => {
-                                                    if let Ok(mut decoded) =
-                                                        decode_deflate(&stream.data[..], fs)
-                                                    {
-                                                        // let s = String::from_utf8(decoded);
-                                                        // println!("inflatexxxx xxx {:?}",s);
-                                                        // panic!("xx");
-                                                        stream.data.clear();
-                                                        stream.data.append(&mut decoded);
-                                                    }
-                                                }
-                                                Some(Value::ARRAY(array)) => {
-                                                    panic!("xxxxxxxxx");
-                                                }
-                                                Some(n) => {
-                                                    println!("xxxxxxxx {:?}", n);
-                                                    panic!("xxxxxxxxx ");
-                                                }
-                                                None => (),
-                                                _ => {
-                                                    panic!("xxxxxxxxx");
-                                                }
-                                            }
-                                            // if let Some(Value::NAME(fs)) = dict.get("Filter"){
-                                            // }
-                                            obj.stream = Some(stream);
-                                        } else {
-                                            println!("what's wrong?");
-                                        }
-                                    }
+                                if let Some(stream) = read_stream_object(buf_reader, dict) {
+                                    obj.value = Some(Value::STREAM(stream));
+                                    return Ok(());
                                 }
+                                return Err(Error::new(ErrorKind::Other, "bad stream"));
                             }
-                            _ => {
-                                println!("should get endobj:");
+                            _ => {}
+                        }
+                        obj.value = Some(Value::DICT(dict));
+                    }
+                    Token::ARRAY_BEGIN => {
+                        obj.value = Some(Value::ARRAY(read_array(buf_reader)?));
+                    }
+                    Token::INTEGER(n) => {
+                        // either a number or a reference
+                        if let Token::INTEGER(n2) = peek_token(buf_reader) {
+                            read_token(buf_reader);
+                            if let Token::R = read_token(buf_reader) {
+                                obj.value = Some(Value::REF(n, n2));
                             }
+                        } else {
+                            obj.value = Some(Value::INTEGER(n));
                         }
-                        obj.dict = Some(dict);
                     }
-                    // panic!("????");
+                    Token::FLOAT(v) => obj.value = Some(Value::FLOAT(v)),
+                    Token::BOOL(v) => obj.value = Some(Value::BOOL(v)),
+                    Token::NAME(v) => obj.value = Some(Value::NAME(v)),
+                    Token::STRING(v) => obj.value = Some(Value::STRING(v)),
+                    Token::NULL => obj.value = Some(Value::NULL),
+                    _ => {
+                        return Err(Error::new(ErrorKind::Other, "unexpected object"));
+                    }
                 }
+                return Ok(());
+            }
+        }
+    }
+    Err(Error::new(ErrorKind::Other, "not an object"))
+}
+// stream ... endstream, data is decoded as far as the filters allow
+fn read_stream_object<R: Read + Seek>(buf_reader: &mut BufReader<R>, mut dict: Dict) -> Option<Stream> {
+    // the keyword is followed by \r\n or \n, the lexer consumed one byte already
+    if buf_reader.seek_relative(-1).is_err() {
+        return None;
+    }
+    let mut eol = [0u8; 1];
+    buf_reader.read_exact(&mut eol).ok()?;
+    if eol[0] == b'\r' {
+        buf_reader.read_exact(&mut eol).ok()?;
+        if eol[0] != b'\n' {
+            buf_reader.seek_relative(-1).ok()?;
+        }
+    }
+    let mut stream = match dict.get(PDF_NAME_Length) {
+        Some(Value::INTEGER(len)) => {
+            // the length must fit in what is left of the file
+            let pos = buf_reader.stream_position().ok()?;
+            let end = buf_reader.seek(SeekFrom::End(0)).ok()?;
+            buf_reader.seek(SeekFrom::Start(pos)).ok()?;
+            if *len < 0 || *len as u64 > end.saturating_sub(pos) {
+                return None;
+            }
+            read_stream(buf_reader, *len as usize).ok()?
+        }
+        // indirect length, objects are not all loaded yet: scan for endstream
+        _ => Stream::new(read_until_endstream(buf_reader).ok()?),
+    };
+    let filters: Vec<String> = match dict.get(PDF_NAME_Filter) {
+        Some(Value::NAME(fs)) => vec![fs.clone()],
+        Some(Value::ARRAY(array)) => array
+            .iter()
+            .filter_map(|v| v.as_name().map(String::from))
+            .collect(),
+        _ => Vec::new(),
+    };
+    let parms: Vec<DecodeParms> = match dict.get(PDF_NAME_DecodeParms) {
+        Some(Value::DICT(d)) => vec![decode_parms(d)],
+        Some(Value::ARRAY(array)) => array
+            .iter()
+            .map(|v| v.as_dict().map(decode_parms).unwrap_or_default())
+            .collect(),
+        _ => Vec::new(),
+    };
+    let mut applied = 0;
+    for (i, fs) in filters.iter().enumerate() {
+        let default_parms = DecodeParms::default();
+        let p = parms.get(i).unwrap_or(&default_parms);
+        match decode(&stream.data, fs, p) {
+            Ok(decoded) => {
+                stream.data = decoded;
+                applied += 1;
             }
+            Err(_) => break,
+        }
+    }
+    if applied > 0 {
+        // keep only the filters still to be applied
+        let rest: Vec<Value> = filters[applied..]
+            .iter()
+            .map(|f| Value::NAME(f.clone()))
+            .collect();
+        let rest_parms: Vec<Value> = match dict.remove(PDF_NAME_DecodeParms) {
+            Some(Value::ARRAY(array)) => array.into_iter().skip(applied).collect(),
+            _ => Vec::new(),
+        };
+        dict.remove(PDF_NAME_Filter);
+        if rest.len() == 1 {
+            dict.push(PDF_NAME_Filter.to_string(), rest[0].clone());
+        } else if rest.len() > 1 {
+            dict.push(PDF_NAME_Filter.to_string(), Value::ARRAY(rest));
+        }
+        if !rest_parms.is_empty() {
+            dict.push(PDF_NAME_DecodeParms.to_string(), Value::ARRAY(rest_parms));
+        }
+        dict.push(
+            PDF_NAME_Length.to_string(),
+            Value::INTEGER(stream.data.len() as i32),
+        );
+    }
+    stream.dict = dict;
+    Some(stream)
+}
+/// The data of a stream we could decode, an error naming the filter left otherwise.
+pub(crate) fn decoded_data(stream: &Stream) -> io::Result<&[u8]> {
+    let filter = match stream.dict.get(PDF_NAME_Filter) {
+        Some(Value::ARRAY(array)) => array.first(),
+        filter => filter,
+    };
+    match filter {
+        None => Ok(&stream.data),
+        Some(filter) => Err(Error::new(
+            ErrorKind::Other,
+            format!("unsupported filter {}", filter.as_name().unwrap_or("?")),
+        )),
+    }
+}
+fn decode_parms(dict: &Dict) -> DecodeParms {
+    let int = |key: &str, default: i32| dict.get(key).and_then(|v| v.as_int()).unwrap_or(default);
+    DecodeParms {
+        predictor: int("Predictor", 1),
+        colors: int("Colors", 1),
+        bits_per_component: int("BitsPerComponent", 8),
+        columns: int("Columns", 1),
+        early_change: int("EarlyChange", 1),
+    }
+}
+fn read_until_endstream<R: Read + Seek>(buf_reader: &mut BufReader<R>) -> io::Result<Vec<u8>> {
+    let mut data = Vec::new();
+    let mark = b"endstream";
+    let mut buf = [0u8; 1];
+    loop {
+        if buf_reader.read(&mut buf)? == 0 {
+            return Err(Error::new(ErrorKind::UnexpectedEof, "endstream"));
+        }
+        data.push(buf[0]);
+        if data.ends_with(mark) {
+            data.truncate(data.len() - mark.len());
+            // drop the end of line before endstream
+            if data.ends_with(b"\n") {
+                data.pop();
+            }
+            if data.ends_with(b"\r") {
+                data.pop();
+            }
+            return Ok(data);
         }
     }
 }
 
-fn read_until(buf_reader: &mut BufReader<File>, delim: &[u8]) -> io::Result<String> {
+fn read_until<R: Read + Seek>(buf_reader: &mut BufReader<R>, delim: &[u8]) -> io::Result<String> {
     let mut buf: [u8; 1] = [0];
 
     let mut vec_buf = Vec::new();
@@ -333,7 +719,6 @@ fn read_until(buf_reader: &mut BufReader<File>, delim: &[u8]) -> io::Result<Stri
         if let Ok(1) = buf_reader.read(&mut buf) {
             let ch = buf[0];
             for v in delim {
-                // println!("{}  >>> {} ",ch, *v);
                 if ch == *v {
                     let line = String::from_utf8(vec_buf).unwrap();
                     return Ok(line);
@@ -351,13 +736,11 @@ fn read_until(buf_reader: &mut BufReader<File>, delim: &[u8]) -> io::Result<Stri
 /Root 3 0 R
 /Info 1 0 R>>
 */
-fn read_dictonary(buf_reader: &mut BufReader<File>) -> io::Result<Dict> {
+fn read_dictonary<R: Read + Seek>(buf_reader: &mut BufReader<R>) -> io::Result<Dict> {
     let mut dict = Dict::new();
-    println!("read_dict");
     let mut check_dict_begin = false;
     loop {
         let tk = read_token(buf_reader);
-        println!("{:?}", tk);
         if !check_dict_begin {
             check_dict_begin = true;
             if let Token::DICT_BEGIN = tk {
@@ -377,7 +760,7 @@ fn read_dictonary(buf_reader: &mut BufReader<File>) -> io::Result<Dict> {
                     match tk2 {
                         Token::INTEGER(n2) => {
                             // shoule is REF, we read next Token "R"
-                            let tk = read_token(buf_reader); // peek to read
+                            read_token(buf_reader); // peek to read
                             if let Token::R = read_token(buf_reader) {
                                 dict.push(key, Value::REF(n, n2));
                             } else {
@@ -396,15 +779,15 @@ fn read_dictonary(buf_reader: &mut BufReader<File>) -> io::Result<Dict> {
                     dict.push(key, Value::STRING(s));
                 }
                 Token::ARRAY_BEGIN => {
-                    let value = read_array(buf_reader);
+                    let value = read_array(buf_reader)?;
                     dict.push(key, Value::ARRAY(value));
                 }
                 Token::DICT_BEGIN => {
-                    if let Ok(value) = read_dictonary(buf_reader) {
-                        dict.push(key, Value::DICT(value));
-                    } else {
-                        // TODO
-                    }
+                    let value = read_dictonary(buf_reader)?;
+                    dict.push(key, Value::DICT(value));
+                }
+                Token::ERROR(e) => {
+                    return Err(Error::new(ErrorKind::Other, e.to_string()));
                 }
                 Token::NULL => {
                     dict.push(key, Value::NULL);
@@ -417,16 +800,15 @@ fn read_dictonary(buf_reader: &mut BufReader<File>) -> io::Result<Dict> {
                 }
                 _ => {}
             }
-        } else {
-            println!("error happen {:?}", tk);
+        } else if let Token::ERROR(e) = tk {
+            return Err(Error::new(ErrorKind::Other, e.to_string()));
         }
     }
     Ok(dict)
 }
 
-fn read_array(buf_reader: &mut BufReader<File>) -> Vec<Value> {
+fn read_array<R: Read + Seek>(buf_reader: &mut BufReader<R>) -> io::Result<Vec<Value>> {
     // TODO
-    println!("read_array");
     let mut array: Vec<Value> = Vec::new();
     loop {
         let tk = read_token(buf_reader);
@@ -453,40 +835,27 @@ fn read_array(buf_reader: &mut BufReader<File>) -> Vec<Value> {
                 array.push(Value::NULL);
             }
             Token::ARRAY_BEGIN => {
-                let val = read_array(buf_reader);
+                let val = read_array(buf_reader)?;
                 array.push(Value::ARRAY(val));
             }
-            Token::R => {
-                if let Some(Value::INTEGER(n1)) = array.pop() {
-                    if let Some(Value::INTEGER(n0)) = array.pop() {
-                        array.push(Value::REF(n0, n1));
-                    } else {
-                        // error
-                        panic!("not a REF");
-                    }
-                } else {
-                    // error
-                    panic!("not a REF");
+            Token::R => match (array.pop(), array.pop()) {
+                (Some(Value::INTEGER(n1)), Some(Value::INTEGER(n0))) => {
+                    array.push(Value::REF(n0, n1));
                 }
-            }
+                _ => return Err(Error::new(ErrorKind::Other, "not a REF")),
+            },
             Token::ERROR(e) => {
-                //TODO
-                break;
+                return Err(Error::new(ErrorKind::Other, e.to_string()));
             }
             Token::DICT_BEGIN => {
-                if let Ok(value) = read_dictonary(buf_reader) {
-                    array.push(Value::DICT(value));
-                } else {
-                    // error
-                    panic!("not a DICT");
-                }
+                array.push(Value::DICT(read_dictonary(buf_reader)?));
             }
             _ => {
-                panic!("should no be here {:?}", tk);
+                return Err(Error::new(ErrorKind::Other, "unexpected token in array"));
             }
         }
     }
-    array
+    Ok(array)
 }
 
 /*
@@ -512,3 +881,69 @@ array
 
 
 */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open_bytes(name: &str, data: &[u8]) -> io::Result<Pdf> {
+        let path = std::env::temp_dir().join(format!("open-{}-{}.pdf", name, std::process::id()));
+        let path = path.to_str().unwrap();
+        std::fs::write(path, data).unwrap();
+        let pdf = Pdf::open(path);
+        std::fs::remove_file(path).unwrap();
+        pdf
+    }
+
+    // a one page file with `eol` line breaks, `xref` replaces its table
+    fn file(eol: &str, xref: Option<&str>) -> Vec<u8> {
+        let objects = [
+            "1 0 obj << /Type /Catalog /Pages 2 0 R >> endobj",
+            "2 0 obj << /Type /Pages /Kids [3 0 R] /Count 1 >> endobj",
+            "3 0 obj << /Type /Page /Parent 2 0 R /MediaBox [0 0 10 10] >> endobj",
+        ];
+        let mut out = format!("%PDF-1.4{}", eol);
+        let mut entries = format!("0 4{}0000000000 65535 f{}", eol, eol);
+        for obj in objects.iter() {
+            entries.push_str(&format!("{:010} 00000 n{}", out.len(), eol));
+            out.push_str(obj);
+            out.push_str(eol);
+        }
+        let start = out.len();
+        let xref = xref.map_or(entries, String::from);
+        out.push_str(&format!("xref{}{}trailer{}", eol, xref, eol));
+        out.push_str(&format!("<< /Size 4 /Root 1 0 R >>{}", eol));
+        out.push_str(&format!("startxref{}{}{}%%EOF{}", eol, start, eol, eol));
+        out.into_bytes()
+    }
+
+    #[test]
+    fn open_xref() {
+        for eol in ["\n", "\r\n"].iter() {
+            let pdf = open_bytes("eol", &file(eol, None)).unwrap();
+            assert_eq!(pdf.version(), "1.4");
+            assert_eq!(pdf.page_count(), 1);
+        }
+        let error = |name: &str, data: &[u8]| match open_bytes(name, data) {
+            Ok(_) => String::new(),
+            Err(err) => err.to_string(),
+        };
+        assert!(!error("empty", b"").is_empty());
+        let bad_entry = file("\n", Some("0 2\n0000000000 65535 f\nx\n"));
+        assert_eq!(error("entry", &bad_entry), "bad xref entry");
+        assert_eq!(error("header", &file("\n", Some("0\n"))), "bad xref subsection");
+        assert_eq!(error("count", &file("\n", Some("0 x\n"))), "bad xref subsection");
+        // cut right after the subsection header
+        let mut data = file("\n", None);
+        let xref = data.windows(5).position(|w| w == b"xref\n").unwrap();
+        data.truncate(xref + 9);
+        data.extend_from_slice(format!("startxref\n{}\n%%EOF\n", xref).as_bytes());
+        assert_eq!(error("truncated", &data), "bad xref entry");
+        // the table running into the end of the data
+        for data in [&b""[..], b"0 1\n", b"0 1\n0000000000 65535 f\n"].iter() {
+            let mut reader = BufReader::new(io::Cursor::new(data));
+            let err = read_xref_table(&mut reader, &mut Pdf::new()).unwrap_err();
+            assert_eq!(err.to_string(), "xref without trailer");
+        }
+    }
+}
@@ -0,0 +1,401 @@
+use super::*;
+use std::collections::HashSet;
+
+const PDF_NAME_StructTreeRoot: &str = "StructTreeRoot";
+const PDF_NAME_RoleMap: &str = "RoleMap";
+const PDF_NAME_ClassMap: &str = "ClassMap";
+const PDF_NAME_K: &str = "K";
+const PDF_NAME_S: &str = "S";
+const PDF_NAME_T: &str = "T";
+const PDF_NAME_ID: &str = "ID";
+const PDF_NAME_Lang: &str = "Lang";
+const PDF_NAME_Alt: &str = "Alt";
+const PDF_NAME_ActualText: &str = "ActualText";
+const PDF_NAME_E: &str = "E";
+const PDF_NAME_A: &str = "A";
+const PDF_NAME_C: &str = "C";
+const PDF_NAME_O: &str = "O";
+const PDF_NAME_Pg: &str = "Pg";
+const PDF_NAME_Stm: &str = "Stm";
+const PDF_NAME_MCID: &str = "MCID";
+const PDF_NAME_Obj: &str = "Obj";
+
+/// An attribute object (/A, or a class of /C): the owner and its entries.
+#[derive(Debug, Clone)]
+pub struct StructAttribute {
+    /// /O, e.g. "Layout", "List", "Table" or "PrintField".
+    pub owner: String,
+    pub entries: Vec<(String, Value)>,
+}
+
+/// A child of a structure element.
+#[derive(Debug, Clone)]
+pub enum StructKid {
+    ELEMENT(Box<StructElement>),
+    /// A marked content sequence with /MCID `mcid` on page `page`, in the content
+    /// stream `stream` when it is not the page content (a form XObject for instance).
+    MARKED_CONTENT {
+        page: Option<i32>,
+        mcid: i32,
+        stream: Option<ObjectId>,
+    },
+    /// An object reference, an annotation or XObject belonging to the element.
+    OBJECT { page: Option<i32>, id: ObjectId },
+}
+
+/// A structure element of a tagged PDF.
+#[derive(Debug, Clone)]
+pub struct StructElement {
+    pub id: Option<ObjectId>,
+    /// /S as written.
+    pub struct_type: String,
+    /// /S mapped through the /RoleMap, a standard type when the map leads to one.
+    pub role: String,
+    /// /T
+    pub title: Option<String>,
+    /// /ID
+    pub element_id: Option<String>,
+    pub lang: Option<String>,
+    /// Alternate description, for figures and formulas.
+    pub alt: Option<String>,
+    /// Replacement text of the content.
+    pub actual_text: Option<String>,
+    /// /E, the expansion of an abbreviation.
+    pub expansion: Option<String>,
+    /// Attribute objects of /A followed by those of the /C classes.
+    pub attributes: Vec<StructAttribute>,
+    /// /Pg, the page holding the content unless a kid says otherwise.
+    pub page: Option<i32>,
+    /// Children in logical order.
+    pub kids: Vec<StructKid>,
+}
+
+/// The logical structure of a tagged PDF, the catalog /StructTreeRoot.
+#[derive(Debug, Clone)]
+pub struct StructTree {
+    pub role_map: HashMap<String, String>,
+    pub kids: Vec<StructElement>,
+}
+
+impl StructElement {
+    /// This element and its descendants, depth first in logical order.
+    pub fn elements(&self) -> Vec<&StructElement> {
+        let mut list = vec![self];
+        for kid in &self.kids {
+            if let StructKid::ELEMENT(elem) = kid {
+                list.extend(elem.elements());
+            }
+        }
+        list
+    }
+}
+
+impl StructTree {
+    /// All structure elements, depth first in logical order.
+    pub fn elements(&self) -> Vec<&StructElement> {
+        self.kids.iter().flat_map(|e| e.elements()).collect()
+    }
+    /// Follows the role map from `struct_type` until a type without mapping.
+    pub fn map_role(&self, struct_type: &str) -> String {
+        let mut role = struct_type;
+        // mappings may chain, and loop in broken files
+        for _ in 0..16 {
+            match self.role_map.get(role) {
+                Some(next) if next != role => role = next,
+                _ => break,
+            }
+        }
+        role.to_string()
+    }
+}
+
+impl Pdf {
+    /// The structure tree, None for untagged documents.
+    pub fn struct_tree(&self) -> Option<StructTree> {
+        let root = self
+            .catalog()
+            .and_then(|c| self.lookup(c, PDF_NAME_StructTreeRoot))?
+            .as_dict()?;
+        let mut tree = StructTree {
+            role_map: HashMap::new(),
+            kids: Vec::new(),
+        };
+        if let Some(map) = self.lookup(root, PDF_NAME_RoleMap).and_then(|v| v.as_dict()) {
+            for (key, v) in map.iter() {
+                if let Some(name) = self.resolve(v).as_name() {
+                    tree.role_map.insert(key.to_string(), name.to_string());
+                }
+            }
+        }
+        let class_map = self.lookup(root, PDF_NAME_ClassMap).and_then(|v| v.as_dict());
+        let page_index: HashMap<i32, i32> = self
+            .pages
+            .iter()
+            .enumerate()
+            .map(|(no, id)| (*id, no as i32))
+            .collect();
+        let mut loader = StructLoader {
+            pdf: self,
+            tree: &tree,
+            class_map,
+            page_index,
+            visited: HashSet::new(),
+        };
+        let kids = root
+            .get(PDF_NAME_K)
+            .map(|k| loader.kids(k, None, 0))
+            .unwrap_or_default();
+        tree.kids = kids
+            .into_iter()
+            .filter_map(|kid| match kid {
+                StructKid::ELEMENT(elem) => Some(*elem),
+                _ => None,
+            })
+            .collect();
+        Some(tree)
+    }
+    /// Text of an element in logical order: its /ActualText, or the text of its
+    /// children, marked content being read from the page content streams.
+    pub fn struct_text(&self, elem: &StructElement) -> String {
+        let mut cache = HashMap::new();
+        let mut text = String::new();
+        self.collect_struct_text(elem, &mut cache, &mut text);
+        text
+    }
+    fn collect_struct_text(
+        &self,
+        elem: &StructElement,
+        cache: &mut HashMap<Option<i32>, HashMap<MarkedContentId, String>>,
+        text: &mut String,
+    ) {
+        if let Some(actual) = &elem.actual_text {
+            text.push_str(actual);
+            return;
+        }
+        for kid in &elem.kids {
+            match kid {
+                StructKid::ELEMENT(e) => self.collect_struct_text(e, cache, text),
+                StructKid::MARKED_CONTENT { page, mcid, stream } => {
+                    let texts = cache.entry(*page).or_insert_with(|| {
+                        let glyphs = page.and_then(|no| self.page_glyphs(no).ok());
+                        marked_content_texts(&glyphs.unwrap_or_default())
+                    });
+                    // a form not drawn on the page is read on its own
+                    if let Some(id) = stream {
+                        if !texts.keys().any(|key| key.0 == Some(*id)) {
+                            texts.extend(marked_content_texts(&self.form_glyphs(*id)));
+                        }
+                    }
+                    if let Some(t) = texts.get(&(*stream, *mcid)) {
+                        text.push_str(t);
+                    }
+                }
+                StructKid::OBJECT { .. } => (),
+            }
+        }
+    }
+}
+
+// builds elements, guarding against cycles in the /K graph
+struct StructLoader<'a> {
+    pdf: &'a Pdf,
+    tree: &'a StructTree,
+    class_map: Option<&'a Dict>,
+    page_index: HashMap<i32, i32>,
+    visited: HashSet<ObjectId>,
+}
+
+impl<'a> StructLoader<'a> {
+    // /K: an element, marked content reference, object reference, MCID or an array of them
+    fn kids(&mut self, k: &Value, page: Option<i32>, depth: i32) -> Vec<StructKid> {
+        let pdf = self.pdf;
+        if depth > 64 {
+            return Vec::new();
+        }
+        match pdf.resolve(k) {
+            Value::ARRAY(items) => items
+                .iter()
+                .flat_map(|item| self.kid(item, page, depth))
+                .collect(),
+            _ => self.kid(k, page, depth).into_iter().collect(),
+        }
+    }
+    fn kid(&mut self, k: &Value, page: Option<i32>, depth: i32) -> Option<StructKid> {
+        let pdf = self.pdf;
+        let dict = match pdf.resolve(k) {
+            Value::INTEGER(mcid) => {
+                return Some(StructKid::MARKED_CONTENT {
+                    page,
+                    mcid: *mcid,
+                    stream: None,
+                })
+            }
+            Value::DICT(dict) => dict,
+            _ => return None,
+        };
+        let kid_page = self.page_of(dict).or(page);
+        match dict.get_type() {
+            Some("MCR") => Some(StructKid::MARKED_CONTENT {
+                page: kid_page,
+                mcid: pdf.lookup(dict, PDF_NAME_MCID)?.as_int()?,
+                stream: dict.get(PDF_NAME_Stm).and_then(|v| v.as_object_id()),
+            }),
+            Some("OBJR") => Some(StructKid::OBJECT {
+                page: kid_page,
+                id: dict.get(PDF_NAME_Obj)?.as_object_id()?,
+            }),
+            _ => {
+                let id = k.as_object_id();
+                if let Some(id) = id {
+                    if !self.visited.insert(id) {
+                        return None;
+                    }
+                }
+                Some(StructKid::ELEMENT(Box::new(self.element(id, dict, depth))))
+            }
+        }
+    }
+    fn element(&mut self, id: Option<ObjectId>, dict: &Dict, depth: i32) -> StructElement {
+        let pdf = self.pdf;
+        let text = |key: &str| pdf.lookup(dict, key).and_then(|v| v.as_text());
+        let struct_type = pdf
+            .lookup(dict, PDF_NAME_S)
+            .and_then(|v| v.as_name())
+            .unwrap_or_default()
+            .to_string();
+        let page = self.page_of(dict);
+        let mut attributes = Vec::new();
+        // /A and /C are single entries or arrays, possibly with revision numbers
+        let items = |key: &str| -> Vec<&Value> {
+            match pdf.lookup(dict, key) {
+                Some(Value::ARRAY(items)) => items.iter().map(|v| pdf.resolve(v)).collect(),
+                Some(v) => vec![v],
+                None => Vec::new(),
+            }
+        };
+        for attr in items(PDF_NAME_A) {
+            if let Some(attr) = attr.as_dict() {
+                attributes.push(self.attribute(attr));
+            }
+        }
+        for class in items(PDF_NAME_C) {
+            let class = class
+                .as_name()
+                .and_then(|name| self.class_map.and_then(|map| pdf.lookup(map, name)));
+            let attrs = match class {
+                Some(Value::ARRAY(attrs)) => attrs.iter().map(|v| pdf.resolve(v)).collect(),
+                Some(v) => vec![v],
+                None => Vec::new(),
+            };
+            for attr in attrs.into_iter().filter_map(|v| v.as_dict()) {
+                attributes.push(self.attribute(attr));
+            }
+        }
+        StructElement {
+            id,
+            role: self.tree.map_role(&struct_type),
+            struct_type,
+            title: text(PDF_NAME_T),
+            element_id: text(PDF_NAME_ID),
+            lang: text(PDF_NAME_Lang),
+            alt: text(PDF_NAME_Alt),
+            actual_text: text(PDF_NAME_ActualText),
+            expansion: text(PDF_NAME_E),
+            attributes,
+            page,
+            kids: dict
+                .get(PDF_NAME_K)
+                .map(|k| self.kids(k, page, depth + 1))
+                .unwrap_or_default(),
+        }
+    }
+    fn attribute(&self, dict: &Dict) -> StructAttribute {
+        let pdf = self.pdf;
+        StructAttribute {
+            owner: pdf
+                .lookup(dict, PDF_NAME_O)
+                .and_then(|v| v.as_name())
+                .unwrap_or_default()
+                .to_string(),
+            entries: dict
+                .iter()
+                .filter(|(key, _)| *key != PDF_NAME_O)
+                .map(|(key, v)| (key.to_string(), pdf.resolve(v).clone()))
+                .collect(),
+        }
+    }
+    fn page_of(&self, dict: &Dict) -> Option<i32> {
+        match dict.get(PDF_NAME_Pg) {
+            Some(Value::REF(n0, _)) => self.page_index.get(n0).copied(),
+            _ => None,
+        }
+    }
+}
+
+// text of the glyphs of each marked content sequence with an MCID
+fn marked_content_texts(glyphs: &[Glyph]) -> HashMap<MarkedContentId, String> {
+    let mut groups: HashMap<MarkedContentId, Vec<&Glyph>> = HashMap::new();
+    for glyph in glyphs {
+        if let Some(key) = glyph.marked {
+            groups.entry(key).or_default().push(glyph);
+        }
+    }
+    groups
+        .into_iter()
+        .map(|(key, glyphs)| (key, glyphs_text(glyphs.into_iter())))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a Type0 font with Identity-H, where codes <0024> and <0025> are A and B,
+    // a form shown inside MCID 1 and another one with MCIDs of its own
+    const TAGGED: &str = r"
+1 0 obj << /Type /Catalog /Pages 2 0 R /StructTreeRoot 10 0 R >> endobj
+2 0 obj << /Type /Pages /Kids [3 0 R] /Count 1 >> endobj
+3 0 obj << /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] /Contents 4 0 R
+  /Resources << /Font << /F1 5 0 R >> /XObject << /Fm1 8 0 R >> >> >> endobj
+4 0 obj << >> stream
+/P << /MCID 0 >> BDC BT /F1 12 Tf 72 700 Td <00240025> Tj ET EMC
+/P << /MCID 1 >> BDC /Fm1 Do EMC
+endstream endobj
+5 0 obj << /Type /Font /Subtype /Type0 /BaseFont /X /Encoding /Identity-H
+  /DescendantFonts [6 0 R] /ToUnicode 7 0 R >> endobj
+6 0 obj << /Type /Font /Subtype /CIDFontType2 /BaseFont /X /DW 500
+  /CIDSystemInfo << /Registry (Adobe) /Ordering (Identity) /Supplement 0 >> >> endobj
+7 0 obj << >> stream
+begincmap 1 begincodespacerange <0000> <FFFF> endcodespacerange
+2 beginbfchar <0024> <0041> <0025> <0042> endbfchar endcmap
+endstream endobj
+8 0 obj << /Type /XObject /Subtype /Form /BBox [0 0 100 100]
+  /Resources << /Font << /F1 5 0 R >> >> >> stream
+BT /F1 12 Tf 72 600 Td <0025> Tj ET
+endstream endobj
+9 0 obj << /Type /XObject /Subtype /Form /BBox [0 0 100 100]
+  /Resources << /Font << /F1 5 0 R >> >> >> stream
+/Span << /MCID 0 >> BDC BT /F1 12 Tf <0024> Tj ET EMC
+endstream endobj
+10 0 obj << /Type /StructTreeRoot /K [11 0 R 12 0 R] >> endobj
+11 0 obj << /S /P /Pg 3 0 R /K [0 1] >> endobj
+12 0 obj << /S /Span /Pg 3 0 R /K << /Type /MCR /MCID 0 /Stm 9 0 R >> >> endobj
+";
+
+    #[test]
+    fn marked_content_text() {
+        let pdf = test_pdf(TAGGED);
+        assert_eq!(pdf.page_text(0), "AB\nB");
+        let tree = pdf.struct_tree().unwrap();
+        let texts: Vec<String> = tree.kids.iter().map(|e| pdf.struct_text(e)).collect();
+        assert_eq!(texts, ["ABB", "A"]);
+    }
+
+    #[test]
+    fn actual_text() {
+        let objects = TAGGED.replace("/P << /MCID 0 >>", "/P << /MCID 0 /ActualText (xy) >>");
+        let pdf = test_pdf(&objects);
+        let tree = pdf.struct_tree().unwrap();
+        assert_eq!(pdf.struct_text(&tree.kids[0]), "xyB");
+    }
+}
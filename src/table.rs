@@ -0,0 +1,476 @@
+use super::*;
+
+/// How a table was found.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TableKind {
+    /// From the ruling lines drawn around its cells.
+    LATTICE,
+    /// From text aligned in columns, without rulings.
+    STREAM,
+}
+
+/// Thresholds of table detection, in points unless noted.
+#[derive(Debug, Clone)]
+pub struct TableOptions {
+    /// Looks for tables drawn with ruling lines.
+    pub lattice: bool,
+    /// Looks for tables of text aligned in columns, outside ruled tables.
+    pub stream: bool,
+    /// Rulings this close are taken as one line, and lines this close meet.
+    pub snap_tolerance: f64,
+    /// A gap between characters wider than this, in units of the font size,
+    /// separates the cells of a stream table.
+    pub cell_margin: f64,
+    /// Fewest rows of a stream table.
+    pub min_rows: usize,
+    /// Fewest columns of a stream table.
+    pub min_columns: usize,
+}
+
+impl Default for TableOptions {
+    fn default() -> Self {
+        TableOptions {
+            lattice: true,
+            stream: true,
+            snap_tolerance: 2.0,
+            cell_margin: 1.0,
+            min_rows: 3,
+            min_columns: 2,
+        }
+    }
+}
+
+/// A cell of a table, placed at its top left position in the grid.
+#[derive(Debug, Clone)]
+pub struct TableCell {
+    pub row: usize,
+    pub column: usize,
+    pub row_span: usize,
+    pub column_span: usize,
+    pub bbox: Rect,
+    pub text: String,
+}
+
+/// A grid of cells, rows from top to bottom and columns from left to right.
+#[derive(Debug, Clone)]
+pub struct Table {
+    pub kind: TableKind,
+    pub bbox: Rect,
+    pub rows: usize,
+    pub columns: usize,
+    pub cells: Vec<TableCell>,
+}
+
+impl Table {
+    /// The text at each position of the grid, a spanning cell's text at its top
+    /// left position and the positions it covers empty.
+    pub fn grid(&self) -> Vec<Vec<String>> {
+        let mut grid = vec![vec![String::new(); self.columns]; self.rows];
+        for cell in self.cells.iter() {
+            grid[cell.row][cell.column] = cell.text.clone();
+        }
+        grid
+    }
+    /// The grid as CSV, quoted as in RFC 4180.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::new();
+        for row in self.grid() {
+            let fields: Vec<String> = row
+                .iter()
+                .map(|field| {
+                    if field.contains([',', '"', '\n', '\r']) {
+                        format!("\"{}\"", field.replace('"', "\"\""))
+                    } else {
+                        field.clone()
+                    }
+                })
+                .collect();
+            csv.push_str(&fields.join(","));
+            csv.push_str("\r\n");
+        }
+        csv
+    }
+}
+
+impl Pdf {
+    /// Tables on page `no` from top to bottom: ruled tables first detected from
+    /// the lines drawn on the page, cells spanning where inner rulings are
+    /// missing, then runs of text lines aligned in columns elsewhere.
+    pub fn page_tables(&self, no: i32, options: &TableOptions) -> io::Result<Vec<Table>> {
+        let (glyphs, rulings) = self.page_graphics(no)?;
+        let mut tables = Vec::new();
+        if options.lattice {
+            tables.extend(lattice_tables(&rulings, &glyphs, options.snap_tolerance));
+        }
+        if options.stream {
+            let inside = |g: &Glyph| {
+                let (x, y) = center(&g.bbox());
+                tables.iter().any(|t: &Table| contains(&t.bbox, x, y))
+            };
+            let free: Vec<Glyph> = glyphs.iter().filter(|g| !inside(g)).cloned().collect();
+            let found = stream_tables(&free, options);
+            tables.extend(found);
+        }
+        tables.sort_by(|a, b| b.bbox.ury.total_cmp(&a.bbox.ury));
+        Ok(tables)
+    }
+}
+
+fn center(rect: &Rect) -> (f64, f64) {
+    ((rect.llx + rect.urx) / 2.0, (rect.lly + rect.ury) / 2.0)
+}
+
+fn contains(rect: &Rect, x: f64, y: f64) -> bool {
+    x >= rect.llx && x <= rect.urx && y >= rect.lly && y <= rect.ury
+}
+
+// collinear rulings overlapping or nearly touching joined into one
+fn merge_rulings(rulings: &[Ruling], tolerance: f64) -> Vec<Ruling> {
+    let mut sorted = rulings.to_vec();
+    sorted.sort_by(|a, b| {
+        (a.horizontal.cmp(&b.horizontal))
+            .then(a.pos.total_cmp(&b.pos))
+            .then(a.start.total_cmp(&b.start))
+    });
+    let mut merged: Vec<Ruling> = Vec::new();
+    for ruling in sorted {
+        let joined = merged.iter_mut().rev().take_while(|m| m.horizontal == ruling.horizontal).find(
+            |m| {
+                (m.pos - ruling.pos).abs() <= tolerance
+                    && ruling.start <= m.end + tolerance
+                    && ruling.end >= m.start - tolerance
+            },
+        );
+        match joined {
+            Some(m) => {
+                m.start = m.start.min(ruling.start);
+                m.end = m.end.max(ruling.end);
+            }
+            None => merged.push(ruling),
+        }
+    }
+    merged
+}
+
+fn crosses(h: &Ruling, v: &Ruling, tolerance: f64) -> bool {
+    v.pos >= h.start - tolerance
+        && v.pos <= h.end + tolerance
+        && h.pos >= v.start - tolerance
+        && h.pos <= v.end + tolerance
+}
+
+// positions closer than the tolerance taken as one, ascending
+fn cluster(mut positions: Vec<f64>, tolerance: f64) -> Vec<f64> {
+    positions.sort_by(f64::total_cmp);
+    let mut clusters: Vec<Vec<f64>> = Vec::new();
+    for pos in positions {
+        match clusters.last_mut() {
+            Some(c) if pos - c[c.len() - 1] <= tolerance => c.push(pos),
+            _ => clusters.push(vec![pos]),
+        }
+    }
+    clusters.iter().map(|c| c.iter().sum::<f64>() / c.len() as f64).collect()
+}
+
+// tables of cells enclosed by rulings meeting each other, glyphs placed in the
+// cell holding their center
+fn lattice_tables(rulings: &[Ruling], glyphs: &[Glyph], tolerance: f64) -> Vec<Table> {
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+    let rulings = merge_rulings(rulings, tolerance);
+    let mut parent: Vec<usize> = (0..rulings.len()).collect();
+    for (i, h) in rulings.iter().enumerate().filter(|r| r.1.horizontal) {
+        for (j, v) in rulings.iter().enumerate().filter(|r| !r.1.horizontal) {
+            if crosses(h, v, tolerance) {
+                let (a, b) = (root(&mut parent, i), root(&mut parent, j));
+                parent[a] = b;
+            }
+        }
+    }
+    let mut groups: HashMap<usize, Vec<Ruling>> = HashMap::new();
+    for (i, ruling) in rulings.iter().enumerate() {
+        groups.entry(root(&mut parent, i)).or_default().push(*ruling);
+    }
+    let mut tables = Vec::new();
+    for group in groups.values() {
+        let (hs, vs): (Vec<Ruling>, Vec<Ruling>) = group.iter().partition(|r| r.horizontal);
+        if hs.len() < 2 || vs.len() < 2 {
+            continue;
+        }
+        let xs = cluster(vs.iter().map(|v| v.pos).collect(), tolerance);
+        let mut ys = cluster(hs.iter().map(|h| h.pos).collect(), tolerance);
+        ys.reverse();
+        if let Some(table) = lattice_table(&xs, &ys, &hs, &vs, glyphs, tolerance) {
+            tables.push(table);
+        }
+    }
+    tables
+}
+
+fn lattice_table(
+    xs: &[f64],
+    ys: &[f64],
+    hs: &[Ruling],
+    vs: &[Ruling],
+    glyphs: &[Glyph],
+    tolerance: f64,
+) -> Option<Table> {
+    let (rows, columns) = (ys.len().checked_sub(1)?, xs.len().checked_sub(1)?);
+    if rows * columns < 2 {
+        return None;
+    }
+    // whether a ruling runs along the edge at `pos` between `from` and `to`
+    let edge = |rulings: &[Ruling], pos: f64, from: f64, to: f64| {
+        let mid = (from + to) / 2.0;
+        rulings.iter().any(|r| {
+            (r.pos - pos).abs() <= tolerance
+                && r.start - tolerance <= mid
+                && r.end + tolerance >= mid
+        })
+    };
+    let mut taken = vec![vec![false; columns]; rows];
+    let mut cells = Vec::new();
+    for row in 0..rows {
+        for column in 0..columns {
+            if taken[row][column] {
+                continue;
+            }
+            let mut column_span = 1;
+            while column + column_span < columns
+                && !edge(vs, xs[column + column_span], ys[row + 1], ys[row])
+            {
+                column_span += 1;
+            }
+            let mut row_span = 1;
+            while row + row_span < rows
+                && (column..column + column_span)
+                    .all(|c| !edge(hs, ys[row + row_span], xs[c], xs[c + 1]))
+            {
+                row_span += 1;
+            }
+            for taken in taken[row..row + row_span].iter_mut() {
+                for t in taken[column..column + column_span].iter_mut() {
+                    *t = true;
+                }
+            }
+            let bbox = Rect::new(xs[column], ys[row + row_span], xs[column + column_span], ys[row]);
+            let text = glyphs_text(glyphs.iter().filter(|g| {
+                let (x, y) = center(&g.bbox());
+                contains(&bbox, x, y)
+            }));
+            cells.push(TableCell {
+                row,
+                column,
+                row_span,
+                column_span,
+                bbox,
+                text: text.trim().to_string(),
+            });
+        }
+    }
+    Some(Table {
+        kind: TableKind::LATTICE,
+        bbox: Rect::new(xs[0], ys[rows], xs[columns], ys[0]),
+        rows,
+        columns,
+        cells,
+    })
+}
+
+// tables of consecutive rows of text, each row pieces of text on a baseline
+// separated by gaps wider than the cell margin, whose pieces fall into the same
+// columns of white space
+fn stream_tables(glyphs: &[Glyph], options: &TableOptions) -> Vec<Table> {
+    let layout = LayoutOptions {
+        char_margin: options.cell_margin,
+        ..LayoutOptions::default()
+    };
+    let mut pieces: Vec<TextLine> = join_pieces(line_pieces(glyphs, &layout), &layout)
+        .iter()
+        .filter(|piece| piece.horizontal)
+        .filter_map(|piece| piece_line(piece, &layout))
+        .collect();
+    pieces.sort_by(|a, b| center(&b.bbox).1.total_cmp(&center(&a.bbox).1));
+    let mut rows: Vec<(Rect, Vec<TextLine>)> = Vec::new();
+    for piece in pieces {
+        let same_row = rows.last().is_some_and(|(bbox, _)| {
+            let overlap = bbox.ury.min(piece.bbox.ury) - bbox.lly.max(piece.bbox.lly);
+            overlap >= bbox.height().min(piece.bbox.height()) * 0.5
+        });
+        match rows.last_mut() {
+            Some((bbox, row)) if same_row => {
+                *bbox = union([*bbox, piece.bbox].iter().copied());
+                row.push(piece);
+            }
+            _ => rows.push((piece.bbox, vec![piece])),
+        }
+    }
+    let mut tables = Vec::new();
+    let mut run: Vec<(Rect, Vec<TextLine>)> = Vec::new();
+    for row in rows.into_iter().chain(std::iter::once((Rect::new(0.0, 0.0, 0.0, 0.0), vec![]))) {
+        let follows = run.last().map_or(true, |(prev, _)| {
+            prev.lly - row.0.ury <= prev.height().max(row.0.height()) * 1.5
+        });
+        if row.1.len() >= options.min_columns && follows {
+            run.push(row);
+            continue;
+        }
+        if run.len() >= options.min_rows {
+            tables.extend(stream_table(std::mem::take(&mut run), options));
+        }
+        run.clear();
+        if row.1.len() >= options.min_columns {
+            run.push(row);
+        }
+    }
+    tables
+}
+
+fn stream_table(rows: Vec<(Rect, Vec<TextLine>)>, options: &TableOptions) -> Option<Table> {
+    // columns are the x ranges covered by pieces, white space between them
+    let mut spans: Vec<(f64, f64)> =
+        rows.iter().flat_map(|r| r.1.iter().map(|p| (p.bbox.llx, p.bbox.urx))).collect();
+    spans.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let mut columns: Vec<(f64, f64)> = Vec::new();
+    for (x0, x1) in spans {
+        match columns.last_mut() {
+            Some(c) if x0 <= c.1 => c.1 = c.1.max(x1),
+            _ => columns.push((x0, x1)),
+        }
+    }
+    // prose set in columns has long lines, table cells hold a few words
+    let pieces = rows.iter().flat_map(|r| r.1.iter());
+    let words: usize = pieces.clone().map(|p| p.words.len()).sum();
+    if columns.len() < options.min_columns || words > pieces.count() * 3 {
+        return None;
+    }
+    let mut cells = Vec::new();
+    for (row, (bbox, pieces)) in rows.iter().enumerate() {
+        for (column, (x0, x1)) in columns.iter().enumerate() {
+            let texts: Vec<&str> = pieces
+                .iter()
+                .filter(|p| p.bbox.llx >= *x0 && p.bbox.urx <= *x1)
+                .map(|p| p.text.trim())
+                .collect();
+            cells.push(TableCell {
+                row,
+                column,
+                row_span: 1,
+                column_span: 1,
+                bbox: Rect::new(*x0, bbox.lly, *x1, bbox.ury),
+                text: texts.join(" "),
+            });
+        }
+    }
+    Some(Table {
+        kind: TableKind::STREAM,
+        bbox: union(cells.iter().map(|c| c.bbox)),
+        rows: rows.len(),
+        columns: columns.len(),
+        cells,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a ruled table whose header spans both columns, and below it text aligned
+    // in three columns without rulings
+    const TABLES: &str = r"
+1 0 obj << /Type /Catalog /Pages 2 0 R >> endobj
+2 0 obj << /Type /Pages /Kids [3 0 R] /Count 1 >> endobj
+3 0 obj << /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] /Contents 4 0 R
+  /Resources << /Font << /F1 5 0 R >> >> >> endobj
+4 0 obj << >> stream
+72 660 m 272 660 l 72 640 m 272 640 l 72 620 m 272 620 l 72 600 m 272 600 l S
+72 600 m 72 660 l 172 600 m 172 640 l 272 600 m 272 660 l S
+BT /F1 10 Tf
+1 0 0 1 80 645 Tm (Header) Tj
+1 0 0 1 80 625 Tm (a) Tj 1 0 0 1 180 625 Tm (b) Tj
+1 0 0 1 80 605 Tm (c, d) Tj 1 0 0 1 180 605 Tm (e) Tj
+1 0 0 1 72 400 Tm (Name) Tj 1 0 0 1 200 400 Tm (Qty) Tj 1 0 0 1 300 400 Tm (Price) Tj
+1 0 0 1 72 388 Tm (Apple) Tj 1 0 0 1 200 388 Tm (3) Tj 1 0 0 1 300 388 Tm (1.20) Tj
+1 0 0 1 72 376 Tm (Pear) Tj 1 0 0 1 200 376 Tm (10) Tj 1 0 0 1 300 376 Tm (0.80) Tj
+ET
+endstream endobj
+5 0 obj << /Type /Font /Subtype /Type1 /BaseFont /Helvetica >> endobj
+";
+
+    #[test]
+    fn page_tables() {
+        let pdf = test_pdf(TABLES);
+        let tables = pdf.page_tables(0, &TableOptions::default()).unwrap();
+        assert_eq!(tables.len(), 2);
+        let lattice = &tables[0];
+        assert_eq!(lattice.kind, TableKind::LATTICE);
+        assert_eq!((lattice.rows, lattice.columns), (3, 2));
+        assert_eq!((lattice.cells[0].row_span, lattice.cells[0].column_span), (1, 2));
+        assert_eq!(lattice.grid(), [["Header", ""], ["a", "b"], ["c, d", "e"]]);
+        assert_eq!(lattice.to_csv(), "Header,\r\na,b\r\n\"c, d\",e\r\n");
+        let stream = &tables[1];
+        assert_eq!(stream.kind, TableKind::STREAM);
+        assert_eq!(
+            stream.grid(),
+            [["Name", "Qty", "Price"], ["Apple", "3", "1.20"], ["Pear", "10", "0.80"]]
+        );
+        let options = TableOptions {
+            lattice: false,
+            min_rows: 4,
+            ..Default::default()
+        };
+        assert!(pdf.page_tables(0, &options).unwrap().is_empty());
+        let options = TableOptions {
+            stream: false,
+            ..Default::default()
+        };
+        assert_eq!(pdf.page_tables(0, &options).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn merged_rulings() {
+        let ruling = |horizontal, pos, start, end| Ruling {
+            horizontal,
+            pos,
+            start,
+            end,
+        };
+        let merged = merge_rulings(
+            &[
+                ruling(true, 10.0, 0.0, 50.0),
+                ruling(true, 11.0, 51.0, 100.0),
+                ruling(true, 20.0, 0.0, 100.0),
+                ruling(false, 0.0, 10.0, 20.0),
+            ],
+            2.0,
+        );
+        assert_eq!(merged.len(), 3);
+        assert_eq!((merged[1].start, merged[1].end), (0.0, 100.0));
+        assert_eq!(cluster(vec![3.0, 1.0, 2.0, 10.0], 1.0), [2.0, 10.0]);
+    }
+
+    #[test]
+    fn csv_quoting() {
+        let cell = |column, text: &str| TableCell {
+            row: 0,
+            column,
+            row_span: 1,
+            column_span: 1,
+            bbox: Rect::new(0.0, 0.0, 0.0, 0.0),
+            text: text.to_string(),
+        };
+        let table = Table {
+            kind: TableKind::STREAM,
+            bbox: Rect::new(0.0, 0.0, 0.0, 0.0),
+            rows: 1,
+            columns: 3,
+            cells: vec![cell(0, "say \"hi\""), cell(1, "two\nlines"), cell(2, "plain")],
+        };
+        assert_eq!(table.to_csv(), "\"say \"\"hi\"\"\",\"two\nlines\",plain\r\n");
+    }
+}
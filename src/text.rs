@@ -0,0 +1,1001 @@
+use super::*;
+use std::rc::Rc;
+
+const PDF_NAME_Resources: &str = "Resources";
+const PDF_NAME_Font: &str = "Font";
+const PDF_NAME_XObject: &str = "XObject";
+const PDF_NAME_ExtGState: &str = "ExtGState";
+const PDF_NAME_Subtype: &str = "Subtype";
+const PDF_NAME_Matrix: &str = "Matrix";
+const PDF_NAME_OC: &str = "OC";
+const PDF_NAME_Properties: &str = "Properties";
+const PDF_NAME_ActualText: &str = "ActualText";
+const PDF_NAME_MCID: &str = "MCID";
+
+// a b c d e f, maps (x, y) to (a x + c y + e, b x + d y + f)
+pub(crate) type Matrix = [f64; 6];
+
+pub(crate) const IDENTITY: Matrix = [1.0, 0.0, 0.0, 1.0, 0.0, 0.0];
+
+// m1 then m2
+pub(crate) fn multiply(m1: &Matrix, m2: &Matrix) -> Matrix {
+    [
+        m1[0] * m2[0] + m1[1] * m2[2],
+        m1[0] * m2[1] + m1[1] * m2[3],
+        m1[2] * m2[0] + m1[3] * m2[2],
+        m1[2] * m2[1] + m1[3] * m2[3],
+        m1[4] * m2[0] + m1[5] * m2[2] + m2[4],
+        m1[4] * m2[1] + m1[5] * m2[3] + m2[5],
+    ]
+}
+
+pub(crate) fn transform(m: &Matrix, x: f64, y: f64) -> (f64, f64) {
+    (m[0] * x + m[2] * y + m[4], m[1] * x + m[3] * y + m[5])
+}
+
+pub(crate) fn invert(m: &Matrix) -> Option<Matrix> {
+    let det = m[0] * m[3] - m[1] * m[2];
+    if det == 0.0 {
+        return None;
+    }
+    let (a, b, c, d) = (m[3] / det, -m[1] / det, -m[2] / det, m[0] / det);
+    Some([a, b, c, d, -(m[4] * a + m[5] * c), -(m[4] * b + m[5] * d)])
+}
+
+// a matrix operand of cm, Tm or /Matrix
+fn matrix_of(values: &[Value]) -> Option<Matrix> {
+    if values.len() != 6 {
+        return None;
+    }
+    let mut m = IDENTITY;
+    for (i, v) in values.iter().enumerate() {
+        m[i] = v.as_f64()?;
+    }
+    Some(m)
+}
+
+/// A character shown on a page, positions in default user space.
+#[derive(Debug, Clone)]
+pub struct TextChar {
+    pub text: String,
+    /// /BaseFont of the font.
+    pub font: String,
+    /// The font size scaled to user space by the text matrix and the CTM.
+    pub size: f64,
+    /// Text rendering mode (Tr), 0 fill to 7 clip.
+    pub render_mode: i32,
+    /// Components of the fill color, or of the stroke color for stroke only
+    /// modes: 1 gray, 3 RGB, 4 CMYK or those of the color space in use.
+    pub color: Vec<f64>,
+    /// The glyph cell, from the font descent to its ascent over the advance width.
+    pub bbox: Rect,
+}
+
+/// Characters between white space and gaps on a line.
+#[derive(Debug, Clone)]
+pub struct TextWord {
+    pub text: String,
+    pub bbox: Rect,
+    pub chars: Vec<TextChar>,
+}
+
+/// Words sharing a baseline, in content order.
+#[derive(Debug, Clone)]
+pub struct TextLine {
+    pub text: String,
+    pub bbox: Rect,
+    pub words: Vec<TextWord>,
+}
+
+// what separates two consecutive glyphs
+#[derive(PartialEq)]
+enum TextBreak {
+    NONE,
+    SPACE,
+    LINE,
+}
+
+// the numeric operands of a color operator, `current` for a pattern
+// without components
+fn color_of(args: &[Value], current: &Rc<Vec<f64>>) -> Rc<Vec<f64>> {
+    let color: Vec<f64> = args.iter().filter_map(|v| v.as_f64()).collect();
+    if color.is_empty() {
+        current.clone()
+    } else {
+        Rc::new(color)
+    }
+}
+
+// the color set by cs and CS, black in the device spaces
+fn initial_color(space: Option<&Value>) -> Rc<Vec<f64>> {
+    let color = match space.and_then(|v| v.as_name()) {
+        Some("DeviceRGB") | Some("RGB") | Some("CalRGB") => vec![0.0; 3],
+        Some("DeviceCMYK") | Some("CMYK") => vec![0.0, 0.0, 0.0, 1.0],
+        _ => vec![0.0],
+    };
+    Rc::new(color)
+}
+
+// a glyph shown by a text operator, positions in default user space
+#[derive(Debug, Clone)]
+pub(crate) struct Glyph {
+    pub(crate) text: String,
+    pub(crate) font: Rc<Font>,
+    pub(crate) render_mode: i32,
+    pub(crate) color: Rc<Vec<f64>>,
+    // text space of the glyph, font size and horizontal scaling applied, to user space
+    pub(crate) matrix: Matrix,
+    // horizontal displacement in that text space, without character and word
+    // spacing; the origin of the matrix is the horizontal origin of the glyph
+    pub(crate) width: f64,
+    // for vertical writing w1y, vx and vy, see Font::vertical_metrics
+    pub(crate) vertical: Option<(f64, f64, f64)>,
+    // the marked content sequence with an MCID the glyph is shown in
+    pub(crate) marked: Option<MarkedContentId>,
+}
+
+impl Glyph {
+    // where the glyph starts in writing direction
+    pub(crate) fn origin(&self) -> (f64, f64) {
+        match self.vertical {
+            Some((_, vx, vy)) => transform(&self.matrix, vx, vy),
+            None => transform(&self.matrix, 0.0, 0.0),
+        }
+    }
+    pub(crate) fn end(&self) -> (f64, f64) {
+        match self.vertical {
+            Some((w1y, vx, vy)) => transform(&self.matrix, vx, vy + w1y),
+            None => transform(&self.matrix, self.width, 0.0),
+        }
+    }
+    // font size in user space, the length of the text space unit across the
+    // writing direction
+    pub(crate) fn size(&self) -> f64 {
+        match self.vertical {
+            Some(_) => self.matrix[0].hypot(self.matrix[1]),
+            None => self.matrix[2].hypot(self.matrix[3]),
+        }
+    }
+    // unit vector in writing direction, along the baseline or down a column
+    pub(crate) fn direction(&self) -> (f64, f64) {
+        let (dx, dy) = match self.vertical {
+            Some(_) => (-self.matrix[2], -self.matrix[3]),
+            None => (self.matrix[0], self.matrix[1]),
+        };
+        let len = dx.hypot(dy);
+        if len == 0.0 {
+            (1.0, 0.0)
+        } else {
+            (dx / len, dy / len)
+        }
+    }
+    pub(crate) fn bbox(&self) -> Rect {
+        let (ascent, descent) = (self.font.ascent, self.font.descent);
+        let corners = [
+            transform(&self.matrix, 0.0, descent),
+            transform(&self.matrix, self.width, descent),
+            transform(&self.matrix, self.width, ascent),
+            transform(&self.matrix, 0.0, ascent),
+        ];
+        let xs = corners.iter().map(|c| c.0);
+        let ys = corners.iter().map(|c| c.1);
+        Rect::new(
+            xs.clone().fold(f64::INFINITY, f64::min),
+            ys.clone().fold(f64::INFINITY, f64::min),
+            xs.fold(f64::NEG_INFINITY, f64::max),
+            ys.fold(f64::NEG_INFINITY, f64::max),
+        )
+    }
+    pub(crate) fn to_char(&self) -> TextChar {
+        TextChar {
+            text: self.text.clone(),
+            font: self.font.base_font.clone(),
+            size: self.size(),
+            render_mode: self.render_mode,
+            color: self.color.to_vec(),
+            bbox: self.bbox(),
+        }
+    }
+    // a new line when `self` leaves the baseline of `prev`, a space when it
+    // is further along it than a fraction of the font size
+    fn break_after(&self, prev: &Glyph) -> TextBreak {
+        let (ex, ey) = prev.end();
+        let (x, y) = self.origin();
+        let (dx, dy) = prev.direction();
+        // offsets along and across the previous baseline
+        let along = (x - ex) * dx + (y - ey) * dy;
+        let across = (y - ey) * dx - (x - ex) * dy;
+        let size = prev.size().max(self.size());
+        if across.abs() > size * 0.5 {
+            TextBreak::LINE
+        } else if along > size * 0.15 {
+            TextBreak::SPACE
+        } else {
+            TextBreak::NONE
+        }
+    }
+}
+
+// a content stream, None for the page contents, and an MCID in it
+pub(crate) type MarkedContentId = (Option<ObjectId>, i32);
+
+// a horizontal or vertical line segment painted on a page, a stroked path
+// segment or a thin filled rectangle, in default user space
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Ruling {
+    pub(crate) horizontal: bool,
+    // y of a horizontal line, x of a vertical one
+    pub(crate) pos: f64,
+    // from the lower to the higher x or y
+    pub(crate) start: f64,
+    pub(crate) end: f64,
+}
+
+impl Ruling {
+    // the segment from `p0` to `p1` if it runs along an axis
+    fn between(p0: (f64, f64), p1: (f64, f64)) -> Option<Ruling> {
+        let (dx, dy) = ((p1.0 - p0.0).abs(), (p1.1 - p0.1).abs());
+        if dx.max(dy) < 1.0 {
+            None
+        } else if dy <= 0.5 {
+            Some(Ruling {
+                horizontal: true,
+                pos: (p0.1 + p1.1) / 2.0,
+                start: p0.0.min(p1.0),
+                end: p0.0.max(p1.0),
+            })
+        } else if dx <= 0.5 {
+            Some(Ruling {
+                horizontal: false,
+                pos: (p0.0 + p1.0) / 2.0,
+                start: p0.1.min(p1.1),
+                end: p0.1.max(p1.1),
+            })
+        } else {
+            None
+        }
+    }
+}
+
+// points of a subpath in user space, with whether a straight line leads to each
+type Subpath = Vec<((f64, f64), bool)>;
+
+// an open marked content sequence: whether it is hidden, the index of its first
+// glyph with its /ActualText, and its MCID
+type MarkedSequence = (bool, Option<(usize, String)>, Option<i32>);
+
+#[derive(Clone)]
+struct GraphicsState {
+    ctm: Matrix,
+    char_spacing: f64,
+    word_spacing: f64,
+    // Tz / 100
+    scale: f64,
+    leading: f64,
+    font: Option<Rc<Font>>,
+    font_size: f64,
+    render_mode: i32,
+    rise: f64,
+    fill: Rc<Vec<f64>>,
+    stroke: Rc<Vec<f64>>,
+}
+
+impl GraphicsState {
+    fn new(ctm: Matrix) -> GraphicsState {
+        GraphicsState {
+            ctm,
+            char_spacing: 0.0,
+            word_spacing: 0.0,
+            scale: 1.0,
+            leading: 0.0,
+            font: None,
+            font_size: 0.0,
+            render_mode: 0,
+            rise: 0.0,
+            fill: Rc::new(vec![0.0]),
+            stroke: Rc::new(vec![0.0]),
+        }
+    }
+}
+
+// runs content streams, collecting the glyphs they show and the lines they paint
+struct TextRunner<'a> {
+    pdf: &'a Pdf,
+    fonts: HashMap<ObjectId, Rc<Font>>,
+    oc: Option<OcState>,
+    glyphs: Vec<Glyph>,
+    rulings: Vec<Ruling>,
+}
+
+impl<'a> TextRunner<'a> {
+    fn font(&mut self, font: &Value) -> Rc<Font> {
+        match font.as_object_id() {
+            Some(id) => {
+                let pdf = self.pdf;
+                self.fonts
+                    .entry(id)
+                    .or_insert_with(|| Rc::new(pdf.load_font(font)))
+                    .clone()
+            }
+            None => Rc::new(self.pdf.load_font(font)),
+        }
+    }
+    fn visible(&self, resources: Option<&Dict>, props: Option<&Value>) -> bool {
+        match (&self.oc, props) {
+            (Some(state), Some(props)) => {
+                let empty = Dict::new();
+                self.pdf
+                    .marked_content_visible(resources.unwrap_or(&empty), props, state)
+            }
+            _ => true,
+        }
+    }
+    // `stream` is the form being run, `outer` the MCID its invocation is marked with
+    fn run(
+        &mut self,
+        ops: &[Operation],
+        resources: Option<&'a Dict>,
+        ctm: Matrix,
+        depth: i32,
+        stream: Option<ObjectId>,
+        outer: Option<MarkedContentId>,
+    ) {
+        let pdf = self.pdf;
+        let resource = |kind: &str, name: &Value| -> Option<&'a Value> {
+            let name = name.as_name()?;
+            let dict = pdf.lookup(resources?, kind)?.as_dict()?;
+            dict.get(name)
+        };
+        let mut gs = GraphicsState::new(ctm);
+        let mut stack: Vec<GraphicsState> = Vec::new();
+        let mut tm = IDENTITY;
+        let mut tlm = IDENTITY;
+        let mut path: Vec<Subpath> = Vec::new();
+        let mut marked: Vec<MarkedSequence> = Vec::new();
+        for op in ops {
+            let args = &op.operands;
+            let num = |i: usize| args.get(i).and_then(|v| v.as_f64()).unwrap_or(0.0);
+            let hidden = marked.iter().any(|m| m.0);
+            // sequences without an MCID belong to the enclosing one
+            let mcid = match marked.iter().rev().find_map(|m| m.2) {
+                Some(mcid) => Some((stream, mcid)),
+                None => outer,
+            };
+            match op.operator.as_str() {
+                "BMC" => marked.push((false, None, None)),
+                "BDC" => {
+                    let is_oc = args.first().and_then(|v| v.as_name()) == Some(PDF_NAME_OC);
+                    let hide = is_oc && !self.visible(resources, args.get(1));
+                    let props = match args.get(1) {
+                        Some(name @ Value::NAME(_)) => resource(PDF_NAME_Properties, name)
+                            .and_then(|v| pdf.resolve(v).as_dict()),
+                        Some(props) => pdf.resolve(props).as_dict(),
+                        None => None,
+                    };
+                    // an enclosing actual text replaces this one too
+                    let actual = props
+                        .filter(|_| marked.iter().all(|m| m.1.is_none()))
+                        .and_then(|d| pdf.lookup(d, PDF_NAME_ActualText))
+                        .and_then(|v| v.as_text());
+                    let id = props
+                        .and_then(|d| pdf.lookup(d, PDF_NAME_MCID))
+                        .and_then(|v| v.as_int());
+                    marked.push((hide, actual.map(|text| (self.glyphs.len(), text)), id));
+                }
+                "EMC" => {
+                    if let Some((_, Some((start, text)), _)) = marked.pop() {
+                        self.replace_text(start, text);
+                    }
+                }
+                "q" => stack.push(gs.clone()),
+                "Q" => {
+                    if let Some(saved) = stack.pop() {
+                        gs = saved;
+                    }
+                }
+                "cm" => {
+                    if let Some(m) = matrix_of(args) {
+                        gs.ctm = multiply(&m, &gs.ctm);
+                    }
+                }
+                "gs" => {
+                    let font = args
+                        .first()
+                        .and_then(|name| resource(PDF_NAME_ExtGState, name))
+                        .and_then(|v| pdf.resolve(v).as_dict())
+                        .and_then(|d| pdf.lookup(d, PDF_NAME_Font))
+                        .and_then(|v| v.as_array());
+                    if let Some([font, size]) = font.map(|f| f.as_slice()) {
+                        gs.font = Some(self.font(font));
+                        gs.font_size = pdf.resolve(size).as_f64().unwrap_or(0.0);
+                    }
+                }
+                "m" | "l" | "c" | "v" | "y" => {
+                    // the end point of a curve, with no straight line to it
+                    let point = match args.len() {
+                        n if n >= 2 => transform(&gs.ctm, num(n - 2), num(n - 1)),
+                        _ => continue,
+                    };
+                    match path.last_mut() {
+                        Some(subpath) if op.operator != "m" => {
+                            subpath.push((point, op.operator == "l"))
+                        }
+                        _ => path.push(vec![(point, false)]),
+                    }
+                }
+                "re" => {
+                    let (x, y, w, h) = (num(0), num(1), num(2), num(3));
+                    let corners = [(x, y), (x + w, y), (x + w, y + h), (x, y + h), (x, y)];
+                    path.push(
+                        corners
+                            .iter()
+                            .enumerate()
+                            .map(|(i, c)| (transform(&gs.ctm, c.0, c.1), i > 0))
+                            .collect(),
+                    );
+                }
+                "h" => {
+                    if let Some(subpath) = path.last_mut() {
+                        let first = subpath[0].0;
+                        subpath.push((first, true));
+                    }
+                }
+                "S" | "s" | "f" | "F" | "f*" | "B" | "B*" | "b" | "b*" | "n" => {
+                    let paint = op.operator.as_str();
+                    if paint.starts_with(['s', 'b']) {
+                        if let Some(subpath) = path.last_mut() {
+                            let first = subpath[0].0;
+                            subpath.push((first, true));
+                        }
+                    }
+                    if !hidden {
+                        let stroke = matches!(paint, "S" | "s" | "B" | "B*" | "b" | "b*");
+                        let fill = !matches!(paint, "S" | "s" | "n");
+                        self.add_rulings(&path, stroke, fill);
+                    }
+                    path.clear();
+                }
+                "BT" => {
+                    tm = IDENTITY;
+                    tlm = IDENTITY;
+                }
+                "Tc" => gs.char_spacing = num(0),
+                "Tw" => gs.word_spacing = num(0),
+                "Tz" => gs.scale = num(0) / 100.0,
+                "TL" => gs.leading = num(0),
+                "Ts" => gs.rise = num(0),
+                "Tr" => gs.render_mode = num(0) as i32,
+                "g" | "rg" | "k" | "sc" | "scn" => gs.fill = color_of(args, &gs.fill),
+                "G" | "RG" | "K" | "SC" | "SCN" => gs.stroke = color_of(args, &gs.stroke),
+                "cs" => gs.fill = initial_color(args.first()),
+                "CS" => gs.stroke = initial_color(args.first()),
+                "Tf" => {
+                    gs.font = args
+                        .first()
+                        .and_then(|name| resource(PDF_NAME_Font, name))
+                        .map(|font| self.font(font));
+                    gs.font_size = num(1);
+                }
+                "Td" | "TD" => {
+                    if op.operator == "TD" {
+                        gs.leading = -num(1);
+                    }
+                    tlm = multiply(&[1.0, 0.0, 0.0, 1.0, num(0), num(1)], &tlm);
+                    tm = tlm;
+                }
+                "Tm" => {
+                    if let Some(m) = matrix_of(args) {
+                        tlm = m;
+                        tm = m;
+                    }
+                }
+                "T*" => {
+                    tlm = multiply(&[1.0, 0.0, 0.0, 1.0, 0.0, -gs.leading], &tlm);
+                    tm = tlm;
+                }
+                "Tj" | "'" | "\"" | "TJ" => {
+                    if op.operator != "Tj" && op.operator != "TJ" {
+                        if op.operator == "\"" {
+                            gs.word_spacing = num(0);
+                            gs.char_spacing = num(1);
+                        }
+                        tlm = multiply(&[1.0, 0.0, 0.0, 1.0, 0.0, -gs.leading], &tlm);
+                        tm = tlm;
+                    }
+                    let items: Vec<&Value> = match (op.operator.as_str(), args.last()) {
+                        ("TJ", Some(Value::ARRAY(items))) => items.iter().collect(),
+                        (_, Some(v)) => vec![v],
+                        _ => Vec::new(),
+                    };
+                    for item in items {
+                        match item {
+                            Value::STRING(s) => self.show(s, &gs, &mut tm, hidden, mcid),
+                            v => {
+                                // a number adjusts the position, in thousandths of a unit
+                                let adjust = -v.as_f64().unwrap_or(0.0) / 1000.0 * gs.font_size;
+                                let vertical = gs.font.as_ref().is_some_and(|f| f.is_vertical());
+                                let shift = if vertical {
+                                    [1.0, 0.0, 0.0, 1.0, 0.0, adjust]
+                                } else {
+                                    [1.0, 0.0, 0.0, 1.0, adjust * gs.scale, 0.0]
+                                };
+                                tm = multiply(&shift, &tm);
+                            }
+                        }
+                    }
+                }
+                "Do" if depth < 16 => {
+                    let xobject = args.first().and_then(|name| resource(PDF_NAME_XObject, name));
+                    let id = xobject.and_then(|v| v.as_object_id());
+                    let xobject = xobject.map(|v| pdf.resolve(v)).and_then(|v| v.as_stream());
+                    let form = match xobject {
+                        Some(form) => form,
+                        None => continue,
+                    };
+                    let is_form = pdf.lookup(&form.dict, PDF_NAME_Subtype).and_then(|v| v.as_name())
+                        == Some("Form");
+                    let oc_hidden = match (&self.oc, form.dict.get(PDF_NAME_OC)) {
+                        (Some(state), Some(oc)) => !pdf.oc_visible(oc, state),
+                        _ => false,
+                    };
+                    if !is_form || hidden || oc_hidden {
+                        continue;
+                    }
+                    let matrix = pdf
+                        .lookup(&form.dict, PDF_NAME_Matrix)
+                        .and_then(|v| v.as_array())
+                        .and_then(|m| matrix_of(m))
+                        .unwrap_or(IDENTITY);
+                    // a form without resources uses those of its parent
+                    let form_resources = pdf
+                        .lookup(&form.dict, PDF_NAME_Resources)
+                        .and_then(|v| v.as_dict())
+                        .or(resources);
+                    let ops = parse_content(&form.data);
+                    let ctm = multiply(&matrix, &gs.ctm);
+                    self.run(&ops, form_resources, ctm, depth + 1, id, mcid);
+                }
+                _ => (),
+            }
+        }
+    }
+    // the straight segments along the axes of a stroked path, and the rectangles
+    // of a filled one thin enough to be lines
+    fn add_rulings(&mut self, path: &[Subpath], stroke: bool, fill: bool) {
+        for subpath in path {
+            let segments: Vec<Option<Ruling>> = subpath
+                .windows(2)
+                .filter(|w| w[1].1)
+                .map(|w| Ruling::between(w[0].0, w[1].0))
+                .collect();
+            if stroke {
+                self.rulings.extend(segments.iter().flatten());
+                continue;
+            }
+            let rectangle = (4..=5).contains(&subpath.len())
+                && subpath.iter().skip(1).all(|p| p.1)
+                && subpath.windows(2).all(|w| {
+                    let ((x0, y0), (x1, y1)) = (w[0].0, w[1].0);
+                    (x1 - x0).abs() <= 0.5 || (y1 - y0).abs() <= 0.5
+                });
+            if !fill || !rectangle {
+                continue;
+            }
+            let xs = subpath.iter().map(|p| p.0 .0);
+            let ys = subpath.iter().map(|p| p.0 .1);
+            let (x0, x1) = (xs.clone().fold(f64::INFINITY, f64::min), xs.fold(f64::MIN, f64::max));
+            let (y0, y1) = (ys.clone().fold(f64::INFINITY, f64::min), ys.fold(f64::MIN, f64::max));
+            let ruling = if y1 - y0 <= 3.0 && x1 - x0 > y1 - y0 {
+                Ruling::between((x0, (y0 + y1) / 2.0), (x1, (y0 + y1) / 2.0))
+            } else if x1 - x0 <= 3.0 && y1 - y0 > x1 - x0 {
+                Ruling::between(((x0 + x1) / 2.0, y0), ((x0 + x1) / 2.0, y1))
+            } else {
+                None
+            };
+            self.rulings.extend(ruling);
+        }
+    }
+    // the glyphs from `start` on show `text`: the first one carries it and is
+    // widened to the end of the last one, the others are left without text
+    fn replace_text(&mut self, start: usize, text: String) {
+        let glyphs = match self.glyphs.get_mut(start..) {
+            Some(glyphs) if !glyphs.is_empty() => glyphs,
+            _ => return,
+        };
+        let (ex, ey) = glyphs[glyphs.len() - 1].end();
+        for glyph in glyphs[1..].iter_mut() {
+            glyph.text.clear();
+        }
+        let first = &mut glyphs[0];
+        if let (Some(inverse), None) = (invert(&first.matrix), first.vertical) {
+            let (x, y) = transform(&inverse, ex, ey);
+            if x > first.width && y.abs() < 0.5 {
+                first.width = x;
+            }
+        }
+        first.text = text;
+    }
+    // shows the string `s`, advancing the text matrix
+    fn show(
+        &mut self,
+        s: &[u8],
+        gs: &GraphicsState,
+        tm: &mut Matrix,
+        hidden: bool,
+        marked: Option<MarkedContentId>,
+    ) {
+        let font = match &gs.font {
+            Some(font) => font.clone(),
+            None => return,
+        };
+        let size = gs.font_size;
+        let vertical = font.is_vertical();
+        for (code, len) in font.decode(s) {
+            let width = font.width(code);
+            let params = [size * gs.scale, 0.0, 0.0, size, 0.0, gs.rise];
+            let mut matrix = multiply(&multiply(&params, tm), &gs.ctm);
+            let metrics = if vertical {
+                // the current point is the vertical origin, the glyph is drawn
+                // from its horizontal one
+                let (w1y, vx, vy) = font.vertical_metrics(code);
+                matrix = multiply(&[1.0, 0.0, 0.0, 1.0, -vx, -vy], &matrix);
+                Some((w1y, vx, vy))
+            } else {
+                None
+            };
+            if !hidden {
+                self.glyphs.push(Glyph {
+                    text: font.unicode(code),
+                    font: font.clone(),
+                    render_mode: gs.render_mode,
+                    // modes 1 and 5 only stroke the glyph outlines
+                    color: match gs.render_mode {
+                        1 | 5 => gs.stroke.clone(),
+                        _ => gs.fill.clone(),
+                    },
+                    matrix,
+                    width,
+                    vertical: metrics,
+                    marked,
+                });
+            }
+            let mut spacing = gs.char_spacing;
+            if font.is_space(code, len) {
+                spacing += gs.word_spacing;
+            }
+            let shift = match metrics {
+                Some((w1y, _, _)) => [1.0, 0.0, 0.0, 1.0, 0.0, w1y * size + spacing],
+                None => [1.0, 0.0, 0.0, 1.0, (width * size + spacing) * gs.scale, 0.0],
+            };
+            *tm = multiply(&shift, tm);
+        }
+    }
+}
+
+impl Pdf {
+    // glyphs of page `no` in content stream order and the lines it paints,
+    // content hidden by optional content in the default configuration is left out
+    pub(crate) fn page_graphics(&self, no: i32) -> io::Result<(Vec<Glyph>, Vec<Ruling>)> {
+        let id = self
+            .page_id(no)
+            .ok_or_else(|| Error::new(ErrorKind::Other, "page out of range"))?;
+        let mut runner = self.text_runner();
+        let resources = inherited_attr(self, id, PDF_NAME_Resources).and_then(|v| v.as_dict());
+        let ops = parse_content(&self.page_contents(id));
+        runner.run(&ops, resources, IDENTITY, 0, None, None);
+        Ok((runner.glyphs, runner.rulings))
+    }
+    // glyphs of the form XObject `id` run on its own, in form space
+    pub(crate) fn form_glyphs(&self, id: ObjectId) -> Vec<Glyph> {
+        let form = match self.get_object(id).and_then(|v| v.as_stream()) {
+            Some(form) => form,
+            None => return Vec::new(),
+        };
+        let mut runner = self.text_runner();
+        let resources = self.lookup(&form.dict, PDF_NAME_Resources).and_then(|v| v.as_dict());
+        runner.run(&parse_content(&form.data), resources, IDENTITY, 1, Some(id), None);
+        runner.glyphs
+    }
+    fn text_runner(&self) -> TextRunner<'_> {
+        TextRunner {
+            pdf: self,
+            fonts: HashMap::new(),
+            oc: self.optional_content().map(|oc| oc.state()),
+            glyphs: Vec::new(),
+            rulings: Vec::new(),
+        }
+    }
+    pub(crate) fn page_glyphs(&self, no: i32) -> io::Result<Vec<Glyph>> {
+        self.page_graphics(no).map(|(glyphs, _)| glyphs)
+    }
+    /// The characters of page `no` in content order, with their positions.
+    pub fn page_chars(&self, no: i32) -> io::Result<Vec<TextChar>> {
+        let glyphs = self.page_glyphs(no)?;
+        Ok(glyphs
+            .iter()
+            .filter(|g| !g.text.is_empty())
+            .map(|g| g.to_char())
+            .collect())
+    }
+    /// The characters of page `no` grouped into words and lines. White space
+    /// separates words and is not part of them.
+    pub fn page_lines(&self, no: i32) -> io::Result<Vec<TextLine>> {
+        let mut lines: Vec<TextLine> = Vec::new();
+        let mut words: Vec<TextWord> = Vec::new();
+        let mut chars: Vec<TextChar> = Vec::new();
+        let mut prev: Option<Glyph> = None;
+        for glyph in self.page_glyphs(no)? {
+            if glyph.text.is_empty() {
+                continue;
+            }
+            let brk = match &prev {
+                Some(p) => glyph.break_after(p),
+                None => TextBreak::NONE,
+            };
+            if brk != TextBreak::NONE || glyph.text.trim().is_empty() {
+                words.extend(text_word(std::mem::take(&mut chars)));
+            }
+            if brk == TextBreak::LINE {
+                lines.extend(text_line(std::mem::take(&mut words)));
+            }
+            if !glyph.text.trim().is_empty() {
+                chars.push(glyph.to_char());
+            }
+            prev = Some(glyph);
+        }
+        words.extend(text_word(chars));
+        lines.extend(text_line(words));
+        Ok(lines)
+    }
+    /// The lines, words and characters of page `no` as JSON, with the crop box
+    /// and rotation to place them.
+    pub fn page_text_json(&self, no: i32) -> io::Result<String> {
+        fn rect_json(r: &Rect) -> String {
+            numbers_json(&[r.llx, r.lly, r.urx, r.ury])
+        }
+        let page = self.page(no)?;
+        let mut out = format!(
+            "{{\"page\":{},\"crop_box\":{},\"rotate\":{},\"lines\":[",
+            no,
+            rect_json(&page.crop_box()),
+            page.rotate()
+        );
+        for (i, line) in self.page_lines(no)?.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            out.push_str(&format!(
+                "{{\"text\":{},\"bbox\":{},\"words\":[",
+                json_string(&line.text),
+                rect_json(&line.bbox)
+            ));
+            for (j, word) in line.words.iter().enumerate() {
+                if j > 0 {
+                    out.push(',');
+                }
+                out.push_str(&format!(
+                    "{{\"text\":{},\"bbox\":{},\"chars\":[",
+                    json_string(&word.text),
+                    rect_json(&word.bbox)
+                ));
+                let chars: Vec<String> = word
+                    .chars
+                    .iter()
+                    .map(|c| {
+                        format!(
+                            "{{\"text\":{},\"font\":{},\"size\":{},\"render_mode\":{},\
+                             \"color\":{},\"bbox\":{}}}",
+                            json_string(&c.text),
+                            json_string(&c.font),
+                            format_number(c.size),
+                            c.render_mode,
+                            numbers_json(&c.color),
+                            rect_json(&c.bbox)
+                        )
+                    })
+                    .collect();
+                out.push_str(&chars.join(","));
+                out.push_str("]}");
+            }
+            out.push_str("]}");
+        }
+        out.push_str("]}");
+        Ok(out)
+    }
+    /// The text of page `no` in content order, with spaces and line breaks
+    /// reconstructed from the glyph positions. Empty for a page out of range.
+    pub fn page_text(&self, no: i32) -> String {
+        glyphs_text(self.page_glyphs(no).unwrap_or_default().iter())
+    }
+}
+
+// the text of glyphs, with spaces and line breaks where their positions leave gaps
+pub(crate) fn glyphs_text<'a>(glyphs: impl Iterator<Item = &'a Glyph>) -> String {
+    let mut text = String::new();
+    let mut prev: Option<&Glyph> = None;
+    for glyph in glyphs.filter(|g| !g.text.is_empty()) {
+        match prev.map(|p| glyph.break_after(p)) {
+            Some(TextBreak::LINE) => text.push('\n'),
+            Some(TextBreak::SPACE) if !text.ends_with(' ') && !glyph.text.starts_with(' ') => {
+                text.push(' ')
+            }
+            _ => (),
+        }
+        text.push_str(&glyph.text);
+        prev = Some(glyph);
+    }
+    text
+}
+
+pub(crate) fn union(rects: impl Iterator<Item = Rect>) -> Rect {
+    rects
+        .reduce(|a, b| {
+            Rect::new(a.llx.min(b.llx), a.lly.min(b.lly), a.urx.max(b.urx), a.ury.max(b.ury))
+        })
+        .unwrap_or(Rect::new(0.0, 0.0, 0.0, 0.0))
+}
+
+pub(crate) fn text_word(chars: Vec<TextChar>) -> Option<TextWord> {
+    if chars.is_empty() {
+        return None;
+    }
+    Some(TextWord {
+        text: chars.iter().map(|c| c.text.as_str()).collect(),
+        bbox: union(chars.iter().map(|c| c.bbox)),
+        chars,
+    })
+}
+
+pub(crate) fn text_line(words: Vec<TextWord>) -> Option<TextLine> {
+    if words.is_empty() {
+        return None;
+    }
+    let text: Vec<&str> = words.iter().map(|w| w.text.as_str()).collect();
+    Some(TextLine {
+        text: text.join(" "),
+        bbox: union(words.iter().map(|w| w.bbox)),
+        words,
+    })
+}
+
+fn numbers_json(values: &[f64]) -> String {
+    let items: Vec<String> = values.iter().map(|n| format_number(*n)).collect();
+    format!("[{}]", items.join(","))
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // kerned and spaced text, actual text, hidden optional content, a form,
+    // vertical writing and rulings
+    const PAGE: &str = r"
+1 0 obj << /Type /Catalog /Pages 2 0 R
+  /OCProperties << /OCGs [6 0 R] /D << /OFF [6 0 R] >> >> >> endobj
+2 0 obj << /Type /Pages /Kids [3 0 R] /Count 1 >> endobj
+3 0 obj << /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] /Contents 4 0 R
+  /Resources << /Font << /F1 5 0 R /F2 8 0 R >> /Properties << /oc1 6 0 R >>
+  /XObject << /X1 7 0 R >> >> >> endobj
+4 0 obj << >> stream
+BT /F1 10 Tf 72 700 Td 1 0 0 rg [(Hel) -30 (lo) -500 (World)] TJ
+0 -20 Td 2 Tw 0 0 1 RG 1 Tr (a b) Tj
+/OC /oc1 BDC (secret) Tj EMC
+/Span << /ActualText <FEFF00E6> >> BDC (ae) Tj EMC ET
+BT /F2 10 Tf 500 700 Td <00220023> Tj ET
+q 2 0 0 2 0 0 cm /X1 Do Q
+0 0 m 100 0.2 l 100 50 l S 10 10 50 1 re f 200 200 m 300 300 l S
+endstream endobj
+5 0 obj << /Type /Font /Subtype /Type1 /BaseFont /Helvetica >> endobj
+6 0 obj << /Type /OCG /Name (Hidden) >> endobj
+7 0 obj << /Type /XObject /Subtype /Form /BBox [0 0 100 100] /Matrix [1 0 0 1 10 0] >>
+stream
+BT /F1 5 Tf 26 300 Td (form) Tj ET
+endstream endobj
+8 0 obj << /Type /Font /Subtype /Type0 /BaseFont /Mincho /Encoding /Identity-V
+  /DescendantFonts [<< /Subtype /CIDFontType0 /BaseFont /Mincho
+  /CIDSystemInfo << /Registry (Adobe) /Ordering (Japan1) /Supplement 0 >> >>] >> endobj
+";
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-6
+    }
+
+    #[test]
+    fn matrices() {
+        let m = multiply(&[2.0, 0.0, 0.0, 2.0, 1.0, 1.0], &[0.0, 1.0, -1.0, 0.0, 5.0, 0.0]);
+        assert_eq!(m, [0.0, 2.0, -2.0, 0.0, 4.0, 1.0]);
+        assert_eq!(transform(&m, 1.0, 1.0), (2.0, 3.0));
+        let inverse = invert(&m).unwrap();
+        assert_eq!(transform(&inverse, 2.0, 3.0), (1.0, 1.0));
+        assert_eq!(multiply(&m, &inverse), IDENTITY);
+        assert!(invert(&[1.0, 2.0, 2.0, 4.0, 0.0, 0.0]).is_none());
+    }
+
+    #[test]
+    fn chars() {
+        let pdf = test_pdf(PAGE);
+        let chars = pdf.page_chars(0).unwrap();
+        let h = &chars[0];
+        assert_eq!((h.text.as_str(), h.font.as_str(), h.size), ("H", "Helvetica", 10.0));
+        assert_eq!((h.render_mode, &h.color[..]), (0, &[1.0, 0.0, 0.0][..]));
+        let b = h.bbox;
+        assert!(close(b.llx, 72.0) && close(b.urx, 79.22));
+        assert!(close(b.lly, 697.93) && close(b.ury, 707.18));
+        // the -30 kerning moves "lo" right
+        assert!(close(chars[3].bbox.llx, 72.0 + 10.0 * (0.722 + 0.556 + 0.222 + 0.03)));
+        let a = chars.iter().find(|c| c.text == "a").unwrap();
+        assert_eq!((a.render_mode, &a.color[..]), (1, &[0.0, 0.0, 1.0][..]));
+        // word spacing widens the space
+        let b = chars.iter().find(|c| c.text == "b").unwrap();
+        assert!(close(b.bbox.llx - a.bbox.llx, 10.0 * (0.556 + 0.278) + 2.0));
+        // "secret" is hidden and "ae" shows a single character
+        let text: String = chars.iter().map(|c| c.text.as_str()).collect();
+        assert_eq!(text, "HelloWorlda bæABform");
+        let ae = chars.iter().find(|c| c.text == "æ").unwrap();
+        assert!(close(ae.bbox.urx - ae.bbox.llx, 10.0 * 0.556 * 2.0));
+        assert!(pdf.page_chars(1).is_err());
+    }
+
+    #[test]
+    fn text() {
+        let pdf = test_pdf(PAGE);
+        assert_eq!(pdf.page_text(0), "Hello World\na b æ\nAB\nform");
+        let lines = pdf.page_lines(0).unwrap();
+        let texts: Vec<&str> = lines.iter().map(|l| l.text.as_str()).collect();
+        assert_eq!(texts, ["Hello World", "a b æ", "AB", "form"]);
+        let words: Vec<&str> = lines[1].words.iter().map(|w| w.text.as_str()).collect();
+        assert_eq!(words, ["a", "b", "æ"]);
+        // the column runs down from the vertical origin, the glyphs hang 0.88
+        // em below it with the default ascent and descent of 0.75 and -0.25
+        let column = lines[2].bbox;
+        assert!(close(column.llx, 495.0) && close(column.urx, 505.0));
+        assert!(close(column.ury, 698.7) && close(column.lly, 678.7));
+        // the form is drawn scaled by the page and moved by its own matrix
+        let form = lines[3].words[0].chars[0].bbox;
+        assert!(close(form.llx, 72.0) && close(form.lly, 600.0 - 0.207 * 10.0));
+        assert_eq!(pdf.page_text(5), "");
+    }
+
+    #[test]
+    fn json() {
+        let pdf = test_pdf(PAGE);
+        let json = pdf.page_text_json(0).unwrap();
+        assert!(json.starts_with(
+            "{\"page\":0,\"crop_box\":[0,0,612,792],\"rotate\":0,\
+             \"lines\":[{\"text\":\"Hello World\""
+        ));
+        assert!(json.contains("{\"text\":\"H\",\"font\":\"Helvetica\",\"size\":10,"));
+        assert!(json.ends_with("]}]}]}]}"));
+        assert_eq!(json_string("a\"\\\n\u{1}é"), "\"a\\\"\\\\\\n\\u0001é\"");
+        assert_eq!(numbers_json(&[1.0, 0.5, -2.0]), "[1,0.5,-2]");
+    }
+
+    #[test]
+    fn rulings() {
+        let pdf = test_pdf(PAGE);
+        let (_, rulings) = pdf.page_graphics(0).unwrap();
+        let found: Vec<(bool, f64, f64, f64)> =
+            rulings.iter().map(|r| (r.horizontal, r.pos, r.start, r.end)).collect();
+        // the diagonal is not a ruling
+        let expected =
+            [(true, 0.1, 0.0, 100.0), (false, 100.0, 0.2, 50.0), (true, 10.5, 10.0, 60.0)];
+        assert_eq!(found, expected);
+    }
+}
@@ -0,0 +1,288 @@
+use super::*;
+use std::cmp::Ordering;
+use std::collections::HashSet;
+
+const PDF_NAME_Names: &str = "Names";
+const PDF_NAME_Nums: &str = "Nums";
+const PDF_NAME_Limits: &str = "Limits";
+
+// name trees and number trees, PDF 32000-1 7.9.6 and 7.9.7.
+// values are returned with references resolved
+impl Pdf {
+    /// All (key, value) pairs of a name tree in the order the tree lists them,
+    /// which is key order for a well formed tree.
+    pub fn name_tree<'a>(&'a self, root: &'a Value) -> Vec<(Vec<u8>, &'a Value)> {
+        let mut entries = Vec::new();
+        self.walk_tree(root, PDF_NAME_Names, 0, &mut tree_visited(root), &mut |k, v| {
+            if let Some(key) = k.as_bytes() {
+                entries.push((key.to_vec(), self.resolve(v)));
+            }
+        });
+        entries
+    }
+    /// Finds `key` in a name tree, descending only into kids whose /Limits cover it.
+    pub fn name_tree_lookup<'a>(&'a self, root: &'a Value, key: &[u8]) -> Option<&'a Value> {
+        let cmp = |k: &Value| k.as_bytes().map(|b| b.cmp(key));
+        self.tree_lookup(root, PDF_NAME_Names, 0, &mut tree_visited(root), &cmp)
+    }
+    /// All (key, value) pairs of a number tree in the order the tree lists them,
+    /// which is key order for a well formed tree.
+    pub fn number_tree<'a>(&'a self, root: &'a Value) -> Vec<(i32, &'a Value)> {
+        let mut entries = Vec::new();
+        self.walk_tree(root, PDF_NAME_Nums, 0, &mut tree_visited(root), &mut |k, v| {
+            if let Some(key) = k.as_int() {
+                entries.push((key, self.resolve(v)));
+            }
+        });
+        entries
+    }
+    pub fn number_tree_lookup<'a>(&'a self, root: &'a Value, key: i32) -> Option<&'a Value> {
+        let cmp = |k: &Value| k.as_int().map(|n| n.cmp(&key));
+        self.tree_lookup(root, PDF_NAME_Nums, 0, &mut tree_visited(root), &cmp)
+    }
+    // sets `key` of the name tree at `path` below the catalog, None removes it.
+    // the tree is created if missing and rewritten as a single root node.
+    // returns the previous value, unresolved
+    pub(crate) fn set_name_tree_entry(
+        &mut self,
+        path: &[&str],
+        key: &[u8],
+        value: Option<Value>,
+    ) -> Option<Value> {
+        let mut entries: Vec<(Vec<u8>, Value)> = Vec::new();
+        let mut kids = Vec::new();
+        let root_id = self.object_id(self.root_id);
+        let mut tree = self.get_object(root_id);
+        for name in path {
+            tree = tree
+                .and_then(|v| self.resolve(v).as_dict())
+                .and_then(|d| d.get(name));
+        }
+        if let Some(tree) = tree {
+            self.walk_tree(tree, PDF_NAME_Names, 0, &mut tree_visited(tree), &mut |k, v| {
+                if let Some(k) = k.as_bytes() {
+                    entries.push((k.to_vec(), v.clone()));
+                }
+            });
+            // the root node is kept
+            self.tree_kids(tree, 0, &mut tree_visited(tree), &mut kids);
+        }
+        // the first of repeated keys
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        entries.dedup_by(|a, b| a.0 == b.0);
+        let old = entries
+            .iter()
+            .position(|(k, _)| k == key)
+            .map(|i| entries.remove(i).1);
+        if let Some(value) = value {
+            entries.push((key.to_vec(), value));
+            entries.sort_by(|a, b| a.0.cmp(&b.0));
+        }
+        let dict = self.dict_path_mut(root_id, path)?;
+        dict.remove(PDF_NAME_Kids);
+        dict.remove(PDF_NAME_Limits);
+        let names = entries
+            .into_iter()
+            .flat_map(|(k, v)| vec![Value::STRING(k), v])
+            .collect();
+        dict.push(PDF_NAME_Names.to_string(), Value::ARRAY(names));
+        for kid in kids {
+            self.remove_object(kid);
+        }
+        old
+    }
+    // indirect intermediate and leaf nodes below `node`, each once
+    fn tree_kids(
+        &self,
+        node: &Value,
+        depth: i32,
+        visited: &mut HashSet<ObjectId>,
+        ids: &mut Vec<ObjectId>,
+    ) {
+        let node = match self.resolve(node).as_dict() {
+            Some(node) => node,
+            None => return,
+        };
+        if depth > 32 {
+            return;
+        }
+        if let Some(kids) = self.lookup(node, PDF_NAME_Kids).and_then(|v| v.as_array()) {
+            for kid in kids {
+                if let Some(id) = kid.as_object_id() {
+                    if !visited.insert(id) {
+                        continue;
+                    }
+                    ids.push(id);
+                }
+                self.tree_kids(kid, depth + 1, visited, ids);
+            }
+        }
+    }
+    // calls f with every key, resolved, and value, as written. kids in `visited`
+    // are skipped, a tree may loop back on itself
+    fn walk_tree<'a>(
+        &'a self,
+        node: &'a Value,
+        leaf_key: &str,
+        depth: i32,
+        visited: &mut HashSet<ObjectId>,
+        f: &mut dyn FnMut(&'a Value, &'a Value),
+    ) {
+        let node = match self.resolve(node).as_dict() {
+            Some(node) => node,
+            None => return,
+        };
+        if depth > 32 {
+            return;
+        }
+        if let Some(pairs) = self.lookup(node, leaf_key).and_then(|v| v.as_array()) {
+            for pair in pairs.chunks(2) {
+                if pair.len() == 2 {
+                    f(self.resolve(&pair[0]), &pair[1]);
+                }
+            }
+        }
+        if let Some(kids) = self.lookup(node, PDF_NAME_Kids).and_then(|v| v.as_array()) {
+            for kid in kids {
+                if kid.as_object_id().is_some_and(|id| !visited.insert(id)) {
+                    continue;
+                }
+                self.walk_tree(kid, leaf_key, depth + 1, visited, f);
+            }
+        }
+    }
+    // cmp compares a key of the tree with the wanted key
+    fn tree_lookup<'a>(
+        &'a self,
+        node: &'a Value,
+        leaf_key: &str,
+        depth: i32,
+        visited: &mut HashSet<ObjectId>,
+        cmp: &dyn Fn(&Value) -> Option<Ordering>,
+    ) -> Option<&'a Value> {
+        let node = self.resolve(node).as_dict()?;
+        if depth > 32 {
+            return None;
+        }
+        if let Some(pairs) = self.lookup(node, leaf_key).and_then(|v| v.as_array()) {
+            for pair in pairs.chunks(2) {
+                if pair.len() == 2 && cmp(self.resolve(&pair[0])) == Some(Ordering::Equal) {
+                    return Some(self.resolve(&pair[1]));
+                }
+            }
+        }
+        let kids = self.lookup(node, PDF_NAME_Kids).and_then(|v| v.as_array())?;
+        for kid in kids {
+            if kid.as_object_id().is_some_and(|id| !visited.insert(id)) {
+                continue;
+            }
+            let limits = self
+                .resolve(kid)
+                .as_dict()
+                .and_then(|k| self.lookup(k, PDF_NAME_Limits))
+                .and_then(|v| v.as_array());
+            if let Some(limits) = limits {
+                if limits.len() == 2 {
+                    let low = cmp(self.resolve(&limits[0]));
+                    let high = cmp(self.resolve(&limits[1]));
+                    if low == Some(Ordering::Greater) || high == Some(Ordering::Less) {
+                        continue;
+                    }
+                }
+            }
+            if let Some(v) = self.tree_lookup(kid, leaf_key, depth + 1, visited, cmp) {
+                return Some(v);
+            }
+        }
+        None
+    }
+}
+
+// the nodes of a tree walk seen so far, starting with an indirect root
+fn tree_visited(root: &Value) -> HashSet<ObjectId> {
+    root.as_object_id().into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a name tree split into two leaves, the second with a kid pointing back at
+    // the root, and a number tree whose leaf /Limits are wrong
+    const TREES: &str = r"
+1 0 obj << /Type /Catalog /Pages 2 0 R /Names << /Dests 3 0 R >> /PageLabels 6 0 R >> endobj
+2 0 obj << /Type /Pages /Kids [] /Count 0 >> endobj
+3 0 obj << /Kids [4 0 R 5 0 R] >> endobj
+4 0 obj << /Limits [(a) (c)] /Names [(a) 1 (c) 7 0 R] >> endobj
+5 0 obj << /Limits [(d) (f)] /Names [(d) 4 (f) 5] /Kids [3 0 R] >> endobj
+6 0 obj << /Kids [8 0 R] >> endobj
+7 0 obj 3 endobj
+8 0 obj << /Limits [0 1] /Nums [0 (zero) 10 (ten)] >> endobj
+";
+
+    #[test]
+    fn trees() {
+        let mut pdf = test_pdf(TREES);
+        let catalog = pdf.catalog().unwrap();
+        let dests = pdf.lookup(pdf.lookup(catalog, "Names").unwrap().as_dict().unwrap(), "Dests");
+        let dests = dests.unwrap();
+        let keys: Vec<(Vec<u8>, i32)> = pdf
+            .name_tree(dests)
+            .into_iter()
+            .map(|(k, v)| (k, v.as_int().unwrap()))
+            .collect();
+        // the loop back to the root is not followed
+        let expected = [(b"a", 1), (b"c", 3), (b"d", 4), (b"f", 5)];
+        assert_eq!(keys, expected.map(|(k, v)| (k.to_vec(), v)));
+        assert_eq!(pdf.name_tree_lookup(dests, b"c").and_then(|v| v.as_int()), Some(3));
+        assert_eq!(pdf.name_tree_lookup(dests, b"f").and_then(|v| v.as_int()), Some(5));
+        assert!(pdf.name_tree_lookup(dests, b"b").is_none());
+        assert!(pdf.name_tree_lookup(dests, b"z").is_none());
+
+        let labels = pdf.lookup(catalog, "PageLabels").unwrap();
+        let nums: Vec<i32> = pdf.number_tree(labels).into_iter().map(|(k, _)| k).collect();
+        assert_eq!(nums, [0, 10]);
+        assert!(pdf.number_tree_lookup(labels, 0).is_some());
+        // skipped by its /Limits
+        assert!(pdf.number_tree_lookup(labels, 10).is_none());
+
+        let old = pdf.set_name_tree_entry(&["Names", "Dests"], b"b", Some(Value::INTEGER(2)));
+        assert!(old.is_none());
+        let old = pdf.set_name_tree_entry(&["Names", "Dests"], b"d", None);
+        assert_eq!(old.and_then(|v| v.as_int()), Some(4));
+        // rewritten as one node, the kids removed
+        assert!(pdf.get_object(ObjectId::new(4, 0)).is_none());
+        let dests = pdf.get_object(ObjectId::new(3, 0)).unwrap();
+        let keys: Vec<Vec<u8>> = pdf.name_tree(dests).into_iter().map(|(k, _)| k).collect();
+        assert_eq!(keys, [b"a", b"b", b"c", b"f"]);
+    }
+
+    #[test]
+    fn new_name_tree() {
+        let mut pdf = test_pdf("1 0 obj << /Type /Catalog >> endobj");
+        pdf.set_name_tree_entry(&["Names", "JavaScript"], b"js", Some(Value::INTEGER(1)));
+        let catalog = pdf.catalog().unwrap();
+        let names = pdf.lookup(catalog, "Names").and_then(|v| v.as_dict()).unwrap();
+        let tree = pdf.lookup(names, "JavaScript").unwrap();
+        assert!(pdf.name_tree_lookup(tree, b"js").is_some());
+    }
+
+    #[test]
+    fn self_referencing_tree() {
+        // a root listing itself as a kid twice, with a leaf under it
+        let pdf = test_pdf(
+            r"
+1 0 obj << /Type /Catalog /Names << /EmbeddedFiles 4 0 R >> >> endobj
+4 0 obj << /Kids [4 0 R 4 0 R 5 0 R] /Names [(a) 1] >> endobj
+5 0 obj << /Limits [(b) (b)] /Names [(b) 2] /Kids [4 0 R 5 0 R] >> endobj
+",
+        );
+        let root = Value::REF(4, 0);
+        let keys: Vec<Vec<u8>> = pdf.name_tree(&root).into_iter().map(|(k, _)| k).collect();
+        assert_eq!(keys, [b"a", b"b"]);
+        assert!(pdf.name_tree_lookup(&root, b"z").is_none());
+        assert_eq!(pdf.name_tree_lookup(&root, b"b").and_then(|v| v.as_int()), Some(2));
+        assert!(pdf.embedded_files().is_empty());
+        assert_eq!(pdf.number_tree(&root).len(), 0);
+    }
+}
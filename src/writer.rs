@@ -0,0 +1,240 @@
+use super::*;
+
+const PDF_NAME_Size: &str = "Size";
+const PDF_NAME_Prev: &str = "Prev";
+const PDF_NAME_XRefStm: &str = "XRefStm";
+
+impl Pdf {
+    /// Writes the whole document to `path`, see write_to.
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let mut file = io::BufWriter::new(File::create(path)?);
+        self.write_to(&mut file)?;
+        file.flush()
+    }
+    /// Serializes every object followed by a new cross-reference table and trailer.
+    /// Streams held fully decoded are compressed again with FlateDecode, the ones that
+    /// kept a filter we could not apply are written as they are.
+    pub fn write_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let version = if self.version.is_empty() {
+            "1.7"
+        } else {
+            &self.version
+        };
+        let mut out = Vec::new();
+        out.extend_from_slice(format!("%PDF-{}\n", version).as_bytes());
+        // binary comment marking the file as 8-bit
+        out.extend_from_slice(b"%\xe2\xe3\xcf\xd3\n");
+        let mut offsets: Vec<(ObjectId, usize)> = Vec::new();
+        for (id, val) in self.objects() {
+            if id.id == 0 {
+                continue;
+            }
+            offsets.push((id, out.len()));
+            out.extend_from_slice(format!("{} {} obj\n", id.id, id.genid).as_bytes());
+            write_value(&mut out, val);
+            out.extend_from_slice(b"\nendobj\n");
+        }
+        let size = offsets.last().map_or(1, |(id, _)| id.id + 1);
+        let xref = out.len();
+        out.extend_from_slice(format!("xref\n0 {}\n", size).as_bytes());
+        // free entries form a linked list starting at object 0
+        let used: HashMap<i32, (i32, usize)> = offsets
+            .iter()
+            .map(|(id, offset)| (id.id, (id.genid, *offset)))
+            .collect();
+        let free: Vec<i32> = (1..size).filter(|n| !used.contains_key(n)).collect();
+        let next_free = |n: i32| free.iter().find(|f| **f > n).copied().unwrap_or(0);
+        out.extend_from_slice(format!("{:010} 65535 f \n", next_free(0)).as_bytes());
+        for n in 1..size {
+            match used.get(&n) {
+                Some((genid, offset)) => {
+                    out.extend_from_slice(format!("{:010} {:05} n \n", offset, genid).as_bytes())
+                }
+                None => out.extend_from_slice(format!("{:010} 00001 f \n", next_free(n)).as_bytes()),
+            }
+        }
+        let mut trailer = self.trailer.clone().unwrap_or_default();
+        trailer.remove(PDF_NAME_Prev);
+        trailer.remove(PDF_NAME_XRefStm);
+        trailer.push(PDF_NAME_Size.to_string(), Value::INTEGER(size));
+        trailer.push(
+            PDF_NAME_Root.to_string(),
+            Value::REF(self.root_id, self.obj_list.get(&self.root_id).map_or(0, |o| o.genid)),
+        );
+        out.extend_from_slice(b"trailer\n");
+        write_dict(&mut out, &trailer);
+        out.extend_from_slice(format!("\nstartxref\n{}\n%%EOF\n", xref).as_bytes());
+        w.write_all(&out)
+    }
+}
+
+/// Appends the PDF syntax of a value.
+pub(crate) fn write_value(out: &mut Vec<u8>, val: &Value) {
+    match val {
+        Value::INTEGER(n) => out.extend_from_slice(n.to_string().as_bytes()),
+        Value::FLOAT(f) => out.extend_from_slice(format_number(*f).as_bytes()),
+        Value::BOOL(b) => out.extend_from_slice(if *b { b"true" } else { b"false" }),
+        Value::NULL => out.extend_from_slice(b"null"),
+        Value::REF(n0, n1) => out.extend_from_slice(format!("{} {} R", n0, n1).as_bytes()),
+        Value::NAME(name) => write_name(out, name),
+        Value::STRING(s) => write_string(out, s),
+        Value::ARRAY(array) => {
+            out.push(b'[');
+            for (i, v) in array.iter().enumerate() {
+                if i > 0 {
+                    out.push(b' ');
+                }
+                write_value(out, v);
+            }
+            out.push(b']');
+        }
+        Value::DICT(dict) => write_dict(out, dict),
+        Value::STREAM(stream) => {
+            let mut dict = stream.dict.clone();
+            let encoded;
+            let data = if dict.get(PDF_NAME_Filter).is_none()
+                && dict.get(PDF_NAME_DecodeParms).is_none()
+                && !stream.data.is_empty()
+            {
+                encoded = encode_flate(&stream.data);
+                dict.push(
+                    PDF_NAME_Filter.to_string(),
+                    Value::NAME("FlateDecode".to_string()),
+                );
+                &encoded
+            } else {
+                &stream.data
+            };
+            dict.push(PDF_NAME_Length.to_string(), Value::INTEGER(data.len() as i32));
+            write_dict(out, &dict);
+            out.extend_from_slice(b"\nstream\n");
+            out.extend_from_slice(data);
+            out.extend_from_slice(b"\nendstream");
+        }
+    }
+}
+
+fn write_dict(out: &mut Vec<u8>, dict: &Dict) {
+    out.extend_from_slice(b"<<");
+    for (key, v) in dict.iter() {
+        write_name(out, key);
+        out.push(b' ');
+        write_value(out, v);
+    }
+    out.extend_from_slice(b">>");
+}
+
+fn write_name(out: &mut Vec<u8>, name: &str) {
+    out.push(b'/');
+    for &c in name.as_bytes() {
+        if c == b'#' || !(0x21..=0x7e).contains(&c) || is_delimiter(c) {
+            out.extend_from_slice(format!("#{:02X}", c).as_bytes());
+        } else {
+            out.push(c);
+        }
+    }
+}
+
+/// Appends a literal string, escaping the characters the lexer treats specially.
+pub(crate) fn write_string(out: &mut Vec<u8>, s: &[u8]) {
+    out.push(b'(');
+    for &c in s {
+        match c {
+            b'(' | b')' | b'\\' => {
+                out.push(b'\\');
+                out.push(c);
+            }
+            b'\r' => out.extend_from_slice(b"\\r"),
+            b'\n' => out.extend_from_slice(b"\\n"),
+            _ => out.push(c),
+        }
+    }
+    out.push(b')');
+}
+
+/// Real numbers without exponent and trailing zeros, as PDF requires.
+pub(crate) fn format_number(f: f64) -> String {
+    if !f.is_finite() {
+        return "0".to_string();
+    }
+    let s = format!("{:.5}", f);
+    let s = s.trim_end_matches('0').trim_end_matches('.');
+    match s {
+        "-0" | "" => "0".to_string(),
+        s => s.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values() {
+        let written = |v: &Value| {
+            let mut out = Vec::new();
+            write_value(&mut out, v);
+            String::from_utf8_lossy(&out).into_owned()
+        };
+        assert_eq!(format_number(1.5), "1.5");
+        assert_eq!(format_number(2.0), "2");
+        assert_eq!(format_number(-0.000001), "0");
+        assert_eq!(format_number(1e20), "100000000000000000000");
+        assert_eq!(format_number(f64::NAN), "0");
+        assert_eq!(written(&Value::NAME("A b#(c)".to_string())), "/A#20b#23#28c#29");
+        assert_eq!(written(&Value::STRING(b"(a)\\\r\n".to_vec())), "(\\(a\\)\\\\\\r\\n)");
+        let array = Value::ARRAY(vec![Value::REF(3, 0), Value::NULL, Value::BOOL(true)]);
+        assert_eq!(written(&array), "[3 0 R null true]");
+        let mut dict = Dict::new();
+        dict.push("Type".to_string(), Value::NAME("X".to_string()));
+        assert_eq!(written(&Value::DICT(dict.clone())), "<</Type /X>>");
+        let stream = Value::STREAM(Stream {
+            dict: dict.clone(),
+            data: b"abc".to_vec(),
+        });
+        let stream = written(&stream);
+        assert!(stream.starts_with("<</Filter /FlateDecode/Length 11/Type /X>>\nstream\nx"));
+        // data still encoded with a filter we can't apply is kept as it is
+        dict.push("Filter".to_string(), Value::NAME("DCTDecode".to_string()));
+        let stream = Value::STREAM(Stream {
+            dict,
+            data: b"abc".to_vec(),
+        });
+        let stream = written(&stream);
+        assert!(stream.contains("/Length 3") && stream.ends_with("\nstream\nabc\nendstream"));
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut pdf = test_pdf(
+            r"
+1 0 obj << /Type /Catalog /Pages 2 0 R >> endobj
+2 0 obj << /Type /Pages /Kids [3 0 R] /Count 1 >> endobj
+3 0 obj << /Type /Page /Parent 2 0 R /MediaBox [0 0 200 100] /Contents 5 0 R >> endobj
+5 0 obj << >> stream
+0 0 m 10 10 l S
+endstream endobj
+",
+        );
+        pdf.trailer = Some(Dict::new());
+        let mut out = Vec::new();
+        pdf.write_to(&mut out).unwrap();
+        let text = String::from_utf8_lossy(&out);
+        assert!(text.starts_with("%PDF-1.7\n"));
+        // object 4 is the only free entry, ending the list
+        assert!(text.contains("xref\n0 6\n0000000004 65535 f \n"));
+        assert!(text.contains("\n0000000000 00001 f \n"));
+        assert!(text.contains("/Size 6") && text.contains("/Root 1 0 R"));
+        assert!(text.contains("<</Filter /FlateDecode/Length ") && !text.contains("10 10 l"));
+
+        let path = std::env::temp_dir().join(format!("writer-{}.pdf", std::process::id()));
+        let path = path.to_str().unwrap();
+        pdf.save(path).unwrap();
+        let saved = Pdf::open(path);
+        std::fs::remove_file(path).unwrap();
+        let saved = saved.unwrap();
+        assert_eq!(saved.page_count(), 1);
+        assert_eq!(saved.page(0).unwrap().media_box(), Rect::new(0.0, 0.0, 200.0, 100.0));
+        assert_eq!(saved.page_operations(0).unwrap().len(), 3);
+    }
+}
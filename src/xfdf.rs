@@ -0,0 +1,587 @@
+use super::fdf::FieldNode;
+use super::xml::Element;
+use super::*;
+
+const XFDF_NS: &str = "http://ns.adobe.com/xfdf/";
+
+const PDF_NAME_Annots: &str = "Annots";
+const PDF_NAME_Type: &str = "Type";
+const PDF_NAME_Subtype: &str = "Subtype";
+const PDF_NAME_Contents: &str = "Contents";
+const PDF_NAME_DA: &str = "DA";
+const PDF_NAME_Q: &str = "Q";
+const PDF_NAME_F: &str = "F";
+const PDF_NAME_BS: &str = "BS";
+const PDF_NAME_W: &str = "W";
+const PDF_NAME_L: &str = "L";
+const PDF_NAME_RT: &str = "RT";
+const PDF_NAME_IRT: &str = "IRT";
+const PDF_NAME_NM: &str = "NM";
+const PDF_NAME_P: &str = "P";
+const PDF_NAME_Rect: &str = "Rect";
+const PDF_NAME_Vertices: &str = "Vertices";
+const PDF_NAME_InkList: &str = "InkList";
+const PDF_NAME_Popup: &str = "Popup";
+const PDF_NAME_Open: &str = "Open";
+
+// markup annotations carried in XFDF: element name and /Subtype
+const XFDF_ANNOTS: [(&str, &str); 14] = [
+    ("text", "Text"),
+    ("freetext", "FreeText"),
+    ("line", "Line"),
+    ("square", "Square"),
+    ("circle", "Circle"),
+    ("polygon", "Polygon"),
+    ("polyline", "PolyLine"),
+    ("highlight", "Highlight"),
+    ("underline", "Underline"),
+    ("squiggly", "Squiggly"),
+    ("strikeout", "StrikeOut"),
+    ("stamp", "Stamp"),
+    ("ink", "Ink"),
+    ("caret", "Caret"),
+];
+
+const XFDF_FLAGS: [(&str, i32); 9] = [
+    ("invisible", ANNOT_FLAG_INVISIBLE),
+    ("hidden", ANNOT_FLAG_HIDDEN),
+    ("print", ANNOT_FLAG_PRINT),
+    ("nozoom", ANNOT_FLAG_NO_ZOOM),
+    ("norotate", ANNOT_FLAG_NO_ROTATE),
+    ("noview", ANNOT_FLAG_NO_VIEW),
+    ("readonly", ANNOT_FLAG_READ_ONLY),
+    ("locked", ANNOT_FLAG_LOCKED),
+    ("togglenoview", ANNOT_FLAG_TOGGLE_NO_VIEW),
+];
+
+#[derive(Clone, Copy)]
+enum AttrKind {
+    TEXT,
+    NAME,
+    NUMBER,
+    // comma separated numbers
+    NUMBERS,
+    // #RRGGBB
+    COLOR,
+}
+
+// attributes that map one to one onto annotation dictionary entries
+const XFDF_ATTRS: [(&str, &str, AttrKind); 11] = [
+    ("rect", "Rect", AttrKind::NUMBERS),
+    ("name", "NM", AttrKind::TEXT),
+    ("title", "T", AttrKind::TEXT),
+    ("subject", "Subj", AttrKind::TEXT),
+    ("date", "M", AttrKind::TEXT),
+    ("creationdate", "CreationDate", AttrKind::TEXT),
+    ("color", "C", AttrKind::COLOR),
+    ("interior-color", "IC", AttrKind::COLOR),
+    ("opacity", "CA", AttrKind::NUMBER),
+    ("icon", "Name", AttrKind::NAME),
+    ("coords", "QuadPoints", AttrKind::NUMBERS),
+];
+
+fn numbers_text(values: &[f64]) -> String {
+    let items: Vec<String> = values.iter().map(|n| format_number(*n)).collect();
+    items.join(",")
+}
+
+fn parse_numbers(s: &str) -> Vec<f64> {
+    s.split(|c: char| c == ',' || c == ';' || c.is_whitespace())
+        .filter_map(|n| n.trim().parse().ok())
+        .collect()
+}
+
+// "x,y;x,y" point lists of <vertices> and <gesture>
+fn points_text(values: &[f64]) -> String {
+    let points: Vec<String> = values.chunks(2).map(numbers_text).collect();
+    points.join(";")
+}
+
+fn color_text(color: &[f64]) -> Option<String> {
+    let rgb = match color {
+        [g] => [*g, *g, *g],
+        [r, g, b] => [*r, *g, *b],
+        [c, m, y, k] => [(1.0 - c) * (1.0 - k), (1.0 - m) * (1.0 - k), (1.0 - y) * (1.0 - k)],
+        _ => return None,
+    };
+    let hex: Vec<String> = rgb
+        .iter()
+        .map(|c| format!("{:02X}", (c.clamp(0.0, 1.0) * 255.0).round() as u8))
+        .collect();
+    Some(format!("#{}", hex.concat()))
+}
+
+fn parse_color(s: &str) -> Option<Vec<f64>> {
+    let hex = s.trim().strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+    (0..3)
+        .map(|i| {
+            u8::from_str_radix(hex.get(i * 2..i * 2 + 2)?, 16)
+                .ok()
+                .map(|c| c as f64 / 255.0)
+        })
+        .collect()
+}
+
+fn float_array(values: &[f64]) -> Value {
+    Value::ARRAY(values.iter().map(|n| Value::FLOAT(*n)).collect())
+}
+
+impl Pdf {
+    /// Form field values and markup annotations as an XFDF document,
+    /// `file` names the PDF the data belongs to.
+    pub fn export_xfdf(&self, file: Option<&str>) -> String {
+        fn field_element(node: &FieldNode) -> Element {
+            let mut elem = Element::new("field").with_attr("name", &node.name);
+            let values = match &node.value {
+                Some(FieldValue::TEXT(s)) | Some(FieldValue::STATE(s)) => vec![s.clone()],
+                Some(FieldValue::LIST(items)) => items.clone(),
+                _ => Vec::new(),
+            };
+            for v in values {
+                let mut value = Element::new("value");
+                value.text = v;
+                elem.children.push(value);
+            }
+            elem.children.extend(node.kids.iter().map(field_element));
+            elem
+        }
+        let mut root = Element::new("xfdf")
+            .with_attr("xmlns", XFDF_NS)
+            .with_attr("xml:space", "preserve");
+        let mut annots = Element::new("annots");
+        for no in 0..self.page_count() {
+            for annot in self.annotations(no).unwrap_or_default() {
+                if let Some(elem) = self.annot_element(no, &annot) {
+                    annots.children.push(elem);
+                }
+            }
+        }
+        if let Some(file) = file {
+            root.children.push(Element::new("f").with_attr("href", file));
+        }
+        let mut fields = Element::new("fields");
+        fields.children = self.field_value_tree().iter().map(field_element).collect();
+        if !fields.children.is_empty() {
+            root.children.push(fields);
+        }
+        if !annots.children.is_empty() {
+            root.children.push(annots);
+        }
+        let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        root.write(&mut out, 0);
+        out
+    }
+    fn annot_element(&self, no: i32, annot: &Annotation) -> Option<Element> {
+        let (tag, _) = XFDF_ANNOTS.iter().find(|(_, subtype)| *subtype == annot.subtype)?;
+        let dict = self.get_object(annot.id?)?.as_dict()?;
+        let numbers = |v: &Value| -> Vec<f64> {
+            v.as_array()
+                .map(|a| a.iter().filter_map(|n| self.resolve(n).as_f64()).collect())
+                .unwrap_or_default()
+        };
+        let mut elem = Element::new(tag).with_attr("page", &no.to_string());
+        for (attr, key, kind) in XFDF_ATTRS.iter() {
+            let v = match self.lookup(dict, key) {
+                Some(v) => v,
+                None => continue,
+            };
+            let text = match kind {
+                AttrKind::TEXT => v.as_text(),
+                AttrKind::NAME => v.as_name().map(String::from),
+                AttrKind::NUMBER => v.as_f64().map(format_number),
+                AttrKind::NUMBERS => Some(numbers_text(&numbers(v))),
+                AttrKind::COLOR => color_text(&numbers(v)),
+            };
+            if let Some(text) = text {
+                elem.attrs.push((attr.to_string(), text));
+            }
+        }
+        let flags: Vec<&str> = XFDF_FLAGS
+            .iter()
+            .filter(|(_, flag)| annot.has_flag(*flag))
+            .map(|(name, _)| *name)
+            .collect();
+        if !flags.is_empty() {
+            elem.attrs.push(("flags".to_string(), flags.join(",")));
+        }
+        if let Some(w) = self
+            .lookup(dict, PDF_NAME_BS)
+            .and_then(|v| v.as_dict())
+            .and_then(|bs| self.lookup(bs, PDF_NAME_W))
+            .and_then(|v| v.as_f64())
+        {
+            elem.attrs.push(("width".to_string(), format_number(w)));
+        }
+        if let Some(l) = self.lookup(dict, PDF_NAME_L).map(numbers) {
+            if l.len() == 4 {
+                elem.attrs.push(("start".to_string(), numbers_text(&l[..2])));
+                elem.attrs.push(("end".to_string(), numbers_text(&l[2..])));
+            }
+        }
+        if let Some(q) = self.lookup(dict, PDF_NAME_Q).and_then(|v| v.as_int()) {
+            let justification = match q {
+                1 => "centered",
+                2 => "right",
+                _ => "left",
+            };
+            elem.attrs.push(("justification".to_string(), justification.to_string()));
+        }
+        if let Some(irt) = annot.in_reply_to {
+            let parent_name = self
+                .get_object(irt)
+                .and_then(|v| v.as_dict())
+                .and_then(|d| self.lookup(d, PDF_NAME_NM))
+                .and_then(|v| v.as_text());
+            if let Some(name) = parent_name {
+                elem.attrs.push(("inreplyto".to_string(), name));
+                if annot.reply_type.as_deref() == Some("Group") {
+                    elem.attrs.push(("replyType".to_string(), "group".to_string()));
+                }
+            }
+        }
+        if let Some(contents) = &annot.contents {
+            let mut child = Element::new("contents");
+            child.text = contents.clone();
+            elem.children.push(child);
+        }
+        if let Some(da) = self.lookup(dict, PDF_NAME_DA).and_then(|v| v.as_text()) {
+            let mut child = Element::new("defaultappearance");
+            child.text = da;
+            elem.children.push(child);
+        }
+        if let Some(vertices) = self.lookup(dict, PDF_NAME_Vertices).map(numbers) {
+            let mut child = Element::new("vertices");
+            child.text = points_text(&vertices);
+            elem.children.push(child);
+        }
+        if let Some(ink) = self.lookup(dict, PDF_NAME_InkList).and_then(|v| v.as_array()) {
+            let mut list = Element::new("inklist");
+            for path in ink {
+                let mut gesture = Element::new("gesture");
+                gesture.text = points_text(&numbers(self.resolve(path)));
+                list.children.push(gesture);
+            }
+            elem.children.push(list);
+        }
+        if let Some(popup) = annot
+            .popup
+            .and_then(|id| self.get_object(id))
+            .and_then(|v| v.as_dict())
+        {
+            let mut child = Element::new("popup");
+            if let Some(rect) = self.lookup(popup, PDF_NAME_Rect) {
+                child.attrs.push(("rect".to_string(), numbers_text(&numbers(rect))));
+            }
+            let open = self.lookup(popup, PDF_NAME_Open).and_then(|v| v.as_bool());
+            child.attrs.push((
+                "open".to_string(),
+                if open == Some(true) { "yes" } else { "no" }.to_string(),
+            ));
+            elem.children.push(child);
+        }
+        Some(elem)
+    }
+    /// Applies the field values of an XFDF document and adds its annotations to the
+    /// pages. An annotation whose name matches one already on its page replaces it.
+    pub fn import_xfdf(&mut self, text: &str) -> io::Result<()> {
+        let root = match xml::parse(text) {
+            Some(root) if root.name == "xfdf" => root,
+            _ => return Err(Error::new(ErrorKind::Other, "not a XFDF file")),
+        };
+        fn collect(elem: &Element, prefix: Option<&str>, values: &mut Vec<(String, FieldValue)>) {
+            for field in elem.children.iter().filter(|c| c.name == "field") {
+                let name = match (prefix, field.attr("name")) {
+                    (Some(p), Some(n)) => format!("{}.{}", p, n),
+                    (None, Some(n)) => n.to_string(),
+                    (Some(p), None) => p.to_string(),
+                    (None, None) => continue,
+                };
+                let items: Vec<String> = field
+                    .children
+                    .iter()
+                    .filter(|c| c.name == "value")
+                    .map(|c| c.text.clone())
+                    .collect();
+                match items.len() {
+                    0 => (),
+                    1 => values.push((name.clone(), FieldValue::TEXT(items[0].clone()))),
+                    _ => values.push((name.clone(), FieldValue::LIST(items))),
+                }
+                collect(field, Some(&name), values);
+            }
+        }
+        if let Some(fields) = root.child("fields") {
+            let mut values = Vec::new();
+            collect(fields, None, &mut values);
+            self.import_values(values)?;
+        }
+        let mut replies = Vec::new();
+        if let Some(annots) = root.child("annots") {
+            for elem in &annots.children {
+                if let Some(id) = self.import_annot(elem)? {
+                    if let Some(parent) = elem.attr("inreplyto") {
+                        replies.push((id, parent.to_string(), elem.attr("replyType") == Some("group")));
+                    }
+                }
+            }
+        }
+        for (id, parent, group) in replies {
+            let parent_id = (0..self.page_count())
+                .flat_map(|no| self.annotations(no).unwrap_or_default())
+                .find(|a| a.name.as_deref() == Some(parent.as_str()))
+                .and_then(|a| a.id);
+            if let (Some(parent_id), Some(dict)) = (
+                parent_id,
+                self.get_object_mut(id).and_then(|v| v.as_dict_mut()),
+            ) {
+                dict.push(PDF_NAME_IRT.to_string(), Value::REF(parent_id.id, parent_id.genid));
+                if group {
+                    dict.push(PDF_NAME_RT.to_string(), Value::NAME("Group".to_string()));
+                }
+            }
+        }
+        Ok(())
+    }
+    // adds or replaces one annotation, None for elements we don't carry
+    fn import_annot(&mut self, elem: &Element) -> io::Result<Option<ObjectId>> {
+        let subtype = match XFDF_ANNOTS.iter().find(|(tag, _)| *tag == elem.name) {
+            Some((_, subtype)) => *subtype,
+            None => return Ok(None),
+        };
+        let no = elem.attr("page").and_then(|p| p.parse().ok()).unwrap_or(0);
+        let page_id = match self.page_id(no) {
+            Some(id) => self.object_id(id),
+            None => return Ok(None),
+        };
+        let mut dict = Dict::new();
+        dict.push(PDF_NAME_Type.to_string(), Value::NAME("Annot".to_string()));
+        dict.push(PDF_NAME_Subtype.to_string(), Value::NAME(subtype.to_string()));
+        dict.push(PDF_NAME_P.to_string(), Value::REF(page_id.id, page_id.genid));
+        for (attr, key, kind) in XFDF_ATTRS.iter() {
+            let text = match elem.attr(attr) {
+                Some(text) => text,
+                None => continue,
+            };
+            let v = match kind {
+                AttrKind::TEXT => Some(Value::STRING(encode_text(text))),
+                AttrKind::NAME => Some(Value::NAME(text.to_string())),
+                AttrKind::NUMBER => text.trim().parse().ok().map(Value::FLOAT),
+                AttrKind::NUMBERS => Some(float_array(&parse_numbers(text))),
+                AttrKind::COLOR => parse_color(text).map(|c| float_array(&c)),
+            };
+            if let Some(v) = v {
+                dict.push(key.to_string(), v);
+            }
+        }
+        if !dict.contains_key(PDF_NAME_Rect) {
+            return Ok(None);
+        }
+        if let Some(flags) = elem.attr("flags") {
+            let f = flags
+                .split(',')
+                .filter_map(|name| XFDF_FLAGS.iter().find(|(n, _)| *n == name.trim()))
+                .fold(0, |f, (_, flag)| f | flag);
+            dict.push(PDF_NAME_F.to_string(), Value::INTEGER(f));
+        }
+        if let Some(w) = elem.attr("width").and_then(|w| w.trim().parse().ok()) {
+            let mut bs = Dict::new();
+            bs.push(PDF_NAME_W.to_string(), Value::FLOAT(w));
+            dict.push(PDF_NAME_BS.to_string(), Value::DICT(bs));
+        }
+        if let (Some(start), Some(end)) = (elem.attr("start"), elem.attr("end")) {
+            let mut l = parse_numbers(start);
+            l.extend(parse_numbers(end));
+            if l.len() == 4 {
+                dict.push(PDF_NAME_L.to_string(), float_array(&l));
+            }
+        }
+        if let Some(justification) = elem.attr("justification") {
+            let q = match justification {
+                "centered" => 1,
+                "right" => 2,
+                _ => 0,
+            };
+            dict.push(PDF_NAME_Q.to_string(), Value::INTEGER(q));
+        }
+        if let Some(contents) = elem.child("contents") {
+            dict.push(PDF_NAME_Contents.to_string(), Value::STRING(encode_text(&contents.text)));
+        }
+        if let Some(da) = elem.child("defaultappearance") {
+            dict.push(PDF_NAME_DA.to_string(), Value::STRING(encode_text(&da.text)));
+        }
+        if let Some(vertices) = elem.child("vertices") {
+            dict.push(PDF_NAME_Vertices.to_string(), float_array(&parse_numbers(&vertices.text)));
+        }
+        if let Some(ink) = elem.child("inklist") {
+            let paths = ink
+                .children
+                .iter()
+                .filter(|c| c.name == "gesture")
+                .map(|g| float_array(&parse_numbers(&g.text)))
+                .collect();
+            dict.push(PDF_NAME_InkList.to_string(), Value::ARRAY(paths));
+        }
+        // same name on the same page: update in place
+        let existing = elem.attr("name").and_then(|name| {
+            self.annotations(no)
+                .unwrap_or_default()
+                .into_iter()
+                .find(|a| a.name.as_deref() == Some(name))
+                .and_then(|a| a.id)
+        });
+        let id = match existing {
+            Some(id) => {
+                // keep the popup of the annotation being replaced
+                let old_popup = self
+                    .get_object(id)
+                    .and_then(|v| v.as_dict())
+                    .and_then(|d| d.get(PDF_NAME_Popup))
+                    .cloned();
+                if let Some(popup) = old_popup {
+                    dict.push(PDF_NAME_Popup.to_string(), popup);
+                }
+                self.set_object(id, Value::DICT(dict));
+                return Ok(Some(id));
+            }
+            None => {
+                let id = self.add_object(Value::DICT(dict));
+                self.push_page_annot(page_id, id)?;
+                id
+            }
+        };
+        if let Some(popup) = elem.child("popup") {
+            let mut dict = Dict::new();
+            dict.push(PDF_NAME_Type.to_string(), Value::NAME("Annot".to_string()));
+            dict.push(PDF_NAME_Subtype.to_string(), Value::NAME("Popup".to_string()));
+            dict.push(PDF_NAME_Parent.to_string(), Value::REF(id.id, id.genid));
+            dict.push(PDF_NAME_P.to_string(), Value::REF(page_id.id, page_id.genid));
+            if let Some(rect) = popup.attr("rect") {
+                dict.push(PDF_NAME_Rect.to_string(), float_array(&parse_numbers(rect)));
+            }
+            dict.push(PDF_NAME_Open.to_string(), Value::BOOL(popup.attr("open") == Some("yes")));
+            let popup_id = self.add_object(Value::DICT(dict));
+            self.push_page_annot(page_id, popup_id)?;
+            if let Some(annot) = self.get_object_mut(id).and_then(|v| v.as_dict_mut()) {
+                annot.push(PDF_NAME_Popup.to_string(), Value::REF(popup_id.id, popup_id.genid));
+            }
+        }
+        Ok(Some(id))
+    }
+    // appends an annotation reference to the page /Annots array
+    fn push_page_annot(&mut self, page: ObjectId, annot: ObjectId) -> io::Result<()> {
+        let annot = Value::REF(annot.id, annot.genid);
+        let page_dict = self
+            .get_object(page)
+            .and_then(|v| v.as_dict())
+            .ok_or_else(|| Error::new(ErrorKind::Other, "page out of range"))?;
+        // /Annots may be an indirect array
+        if let Some(Value::REF(n0, n1)) = page_dict.get(PDF_NAME_Annots) {
+            if let Some(Value::ARRAY(array)) = self.get_object_mut(ObjectId::new(*n0, *n1)) {
+                array.push(annot);
+                return Ok(());
+            }
+        }
+        let page_dict = self
+            .get_object_mut(page)
+            .and_then(|v| v.as_dict_mut())
+            .ok_or_else(|| Error::new(ErrorKind::Other, "page out of range"))?;
+        match page_dict.get_mut(PDF_NAME_Annots) {
+            Some(Value::ARRAY(array)) => array.push(annot),
+            _ => page_dict.push(PDF_NAME_Annots.to_string(), Value::ARRAY(vec![annot])),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a text field, a note with a popup, a group reply to it and an ink drawing
+    const MARKUP: &str = r"
+1 0 obj << /Type /Catalog /Pages 2 0 R /AcroForm << /Fields [20 0 R] >> >> endobj
+2 0 obj << /Type /Pages /Kids [3 0 R] /Count 1 >> endobj
+3 0 obj << /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792]
+  /Annots [10 0 R 11 0 R 12 0 R 13 0 R 20 0 R] >> endobj
+10 0 obj << /Type /Annot /Subtype /Text /Rect [100 100 120 120] /NM (n1) /T (Ann)
+  /Contents (Note & more) /C [1 0 0] /F 4 /Name /Comment /Popup 11 0 R >> endobj
+11 0 obj << /Type /Annot /Subtype /Popup /Rect [120 100 220 160] /Open true /Parent 10 0 R >>
+endobj
+12 0 obj << /Type /Annot /Subtype /Text /Rect [100 100 120 120] /NM (n2) /IRT 10 0 R
+  /RT /Group /Contents (Reply) >> endobj
+13 0 obj << /Type /Annot /Subtype /Ink /Rect [0 0 50 50] /InkList [[0 0 10 10.5]]
+  /BS << /W 2 >> /C [0 0 0 0] >> endobj
+20 0 obj << /FT /Tx /T (name) /V (Ann) /Subtype /Widget /Rect [0 0 100 20] /P 3 0 R >> endobj
+";
+
+    const BLANK: &str = r"
+1 0 obj << /Type /Catalog /Pages 2 0 R /AcroForm << /Fields [20 0 R] >> >> endobj
+2 0 obj << /Type /Pages /Kids [3 0 R] /Count 1 >> endobj
+3 0 obj << /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] /Annots 4 0 R >> endobj
+4 0 obj [20 0 R] endobj
+20 0 obj << /FT /Tx /T (name) /Subtype /Widget /Rect [0 0 100 20] /P 3 0 R >> endobj
+";
+
+    #[test]
+    fn export() {
+        let xfdf = test_pdf(MARKUP).export_xfdf(Some("form.pdf"));
+        assert!(xfdf.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<xfdf "));
+        assert!(xfdf.contains("<f href=\"form.pdf\"/>"));
+        assert!(xfdf.contains("<field name=\"name\">\n      <value>Ann</value>"));
+        assert!(xfdf.contains(
+            "<text page=\"0\" rect=\"100,100,120,120\" name=\"n1\" title=\"Ann\" \
+             color=\"#FF0000\" icon=\"Comment\" flags=\"print\">"
+        ));
+        assert!(xfdf.contains("<contents>Note &amp; more</contents>"));
+        assert!(xfdf.contains("<popup rect=\"120,100,220,160\" open=\"yes\"/>"));
+        assert!(xfdf.contains("inreplyto=\"n1\" replyType=\"group\""));
+        assert!(xfdf.contains("color=\"#FFFFFF\" width=\"2\""));
+        assert!(xfdf.contains("<gesture>0,0;10,10.5</gesture>"));
+        // widgets and popups are not carried as annotations
+        assert!(!xfdf.contains("<popup page"));
+        assert!(!xfdf.contains("widget"));
+    }
+
+    #[test]
+    fn import() {
+        let xfdf = test_pdf(MARKUP).export_xfdf(None);
+        let mut pdf = test_pdf(BLANK);
+        pdf.import_xfdf(&xfdf).unwrap();
+        let field = &pdf.form_fields()[0];
+        assert_eq!(field.value, FieldValue::TEXT("Ann".to_string()));
+        let annots = pdf.annotations(0).unwrap();
+        let subtypes: Vec<&str> = annots.iter().map(|a| a.subtype.as_str()).collect();
+        assert_eq!(subtypes, ["Widget", "Text", "Popup", "Text", "Ink"]);
+        let note = &annots[1];
+        assert_eq!(note.contents.as_deref(), Some("Note & more"));
+        assert_eq!(note.color.as_deref(), Some(&[1.0, 0.0, 0.0][..]));
+        assert_eq!(note.flags, ANNOT_FLAG_PRINT);
+        assert_eq!(note.popup, annots[2].id);
+        assert!(matches!(annots[2].kind, AnnotKind::POPUP { open: true, .. }));
+        assert_eq!(annots[3].in_reply_to, note.id);
+        assert_eq!(annots[3].reply_type.as_deref(), Some("Group"));
+        assert!(matches!(&annots[4].kind, AnnotKind::INK { ink_list } if ink_list[0].len() == 2));
+        // named annotations are replaced, keeping their popup
+        pdf.import_xfdf(&xfdf.replace("Note &amp; more", "Changed")).unwrap();
+        let again = pdf.annotations(0).unwrap();
+        assert_eq!(again.len(), 6);
+        assert_eq!(again[1].contents.as_deref(), Some("Changed"));
+        assert_eq!(again[1].popup, annots[2].id);
+        assert!(pdf.import_xfdf("<fdf/>").is_err());
+    }
+
+    #[test]
+    fn colors_and_numbers() {
+        assert_eq!(color_text(&[0.5]).as_deref(), Some("#808080"));
+        assert_eq!(color_text(&[0.0, 1.0, 0.0, 0.5]).as_deref(), Some("#800080"));
+        assert_eq!(color_text(&[]), None);
+        assert_eq!(parse_color("#00FF80"), Some(vec![0.0, 1.0, 128.0 / 255.0]));
+        assert_eq!(parse_color("00FF80"), None);
+        assert_eq!(parse_color("#0F8"), None);
+        assert_eq!(parse_numbers("1,2; 3 x 4.5"), [1.0, 2.0, 3.0, 4.5]);
+        assert_eq!(points_text(&[1.0, 2.0, 3.0, 4.0, 5.0]), "1,2;3,4;5");
+    }
+}
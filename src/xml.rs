@@ -0,0 +1,265 @@
+// a small non validating XML reader, enough for XMP packets and XFDF
+
+#[derive(Debug, Clone, Default)]
+pub struct Element {
+    pub name: String,
+    pub attrs: Vec<(String, String)>,
+    pub children: Vec<Element>,
+    // character data directly inside this element
+    pub text: String,
+}
+
+impl Element {
+    pub fn new(name: &str) -> Element {
+        Element {
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+    pub fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs
+            .iter()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.as_str())
+    }
+    pub fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|c| c.name == name)
+    }
+    pub fn with_attr(mut self, name: &str, value: &str) -> Element {
+        self.attrs.push((name.to_string(), value.to_string()));
+        self
+    }
+    /// Serializes the element, children indented by two spaces per level.
+    pub fn write(&self, out: &mut String, depth: usize) {
+        let indent = "  ".repeat(depth);
+        out.push_str(&indent);
+        out.push('<');
+        out.push_str(&self.name);
+        for (k, v) in &self.attrs {
+            out.push_str(&format!(" {}=\"{}\"", k, escape(v)));
+        }
+        if self.children.is_empty() && self.text.is_empty() {
+            out.push_str("/>\n");
+            return;
+        }
+        out.push('>');
+        out.push_str(&escape(&self.text));
+        if !self.children.is_empty() {
+            out.push('\n');
+            for c in &self.children {
+                c.write(out, depth + 1);
+            }
+            out.push_str(&indent);
+        }
+        out.push_str(&format!("</{}>\n", self.name));
+    }
+    /// Depth first search for elements with the given name.
+    pub fn find_all<'a>(&'a self, name: &str, out: &mut Vec<&'a Element>) {
+        for c in &self.children {
+            if c.name == name {
+                out.push(c);
+            }
+            c.find_all(name, out);
+        }
+    }
+}
+
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '&' => out.push_str("&amp;"),
+            '"' => out.push_str("&quot;"),
+            '\r' => out.push_str("&#13;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+fn unescape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(i) = rest.find('&') {
+        out.push_str(&rest[..i]);
+        rest = &rest[i..];
+        let end = match rest.find(';') {
+            Some(end) => end,
+            None => break,
+        };
+        let entity = &rest[1..end];
+        let c = match entity {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ if entity.starts_with("#x") => u32::from_str_radix(&entity[2..], 16)
+                .ok()
+                .and_then(char::from_u32),
+            _ if entity.starts_with('#') => entity[1..].parse().ok().and_then(char::from_u32),
+            _ => None,
+        };
+        match c {
+            Some(c) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Parses a document and returns its root element.
+pub fn parse(text: &str) -> Option<Element> {
+    let mut stack: Vec<Element> = vec![Element::new("")];
+    let mut rest = text;
+    while !rest.is_empty() {
+        let lt = match rest.find('<') {
+            Some(i) => i,
+            None => break,
+        };
+        if lt > 0 {
+            let top = stack.last_mut()?;
+            top.text.push_str(&unescape(&rest[..lt]));
+        }
+        rest = &rest[lt..];
+        if rest.starts_with("<?") {
+            rest = &rest[rest.find("?>")? + 2..];
+        } else if rest.starts_with("<!--") {
+            rest = &rest[rest.find("-->")? + 3..];
+        } else if rest.starts_with("<![CDATA[") {
+            let end = rest.find("]]>")?;
+            stack.last_mut()?.text.push_str(&rest[9..end]);
+            rest = &rest[end + 3..];
+        } else if rest.starts_with("<!") {
+            rest = &rest[rest.find('>')? + 1..];
+        } else if rest.starts_with("</") {
+            let end = rest.find('>')?;
+            rest = &rest[end + 1..];
+            if stack.len() > 1 {
+                let elem = stack.pop()?;
+                stack.last_mut()?.children.push(elem);
+            }
+        } else {
+            let end = tag_end(rest)?;
+            let tag = &rest[1..end];
+            rest = &rest[end + 1..];
+            let (tag, empty) = match tag.strip_suffix('/') {
+                Some(t) => (t, true),
+                None => (tag, false),
+            };
+            let elem = parse_tag(tag);
+            if empty {
+                stack.last_mut()?.children.push(elem);
+            } else {
+                stack.push(elem);
+            }
+        }
+    }
+    // close unterminated elements
+    while stack.len() > 1 {
+        let elem = stack.pop()?;
+        stack.last_mut()?.children.push(elem);
+    }
+    stack.pop()?.children.into_iter().next()
+}
+
+// position of the '>' closing a start tag, skipping quoted attribute values
+fn tag_end(s: &str) -> Option<usize> {
+    let mut quote: Option<char> = None;
+    for (i, c) in s.char_indices() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => (),
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == '>' => return Some(i),
+            None => (),
+        }
+    }
+    None
+}
+
+fn parse_tag(tag: &str) -> Element {
+    let tag = tag.trim();
+    let name_end = tag
+        .find(|c: char| c.is_whitespace())
+        .unwrap_or(tag.len());
+    let mut elem = Element::new(&tag[..name_end]);
+    let mut rest = tag[name_end..].trim_start();
+    while let Some(eq) = rest.find('=') {
+        let key = rest[..eq].trim().to_string();
+        rest = rest[eq + 1..].trim_start();
+        let q = match rest.chars().next() {
+            Some(q) if q == '"' || q == '\'' => q,
+            _ => break,
+        };
+        let end = match rest[1..].find(q) {
+            Some(end) => end + 1,
+            None => break,
+        };
+        elem.attrs.push((key, unescape(&rest[1..end])));
+        rest = rest[end + 1..].trim_start();
+    }
+    elem
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_document() {
+        let doc = parse(
+            r#"<?xml version="1.0"?><!DOCTYPE x><!-- c -->
+<root a="1 &lt; 2" b='it&apos;s "q"'><item id="x>y">one &amp; &#x41;&#66;</item>
+<empty/><![CDATA[<raw>]]><open>"#,
+        )
+        .unwrap();
+        assert_eq!(doc.name, "root");
+        assert_eq!(doc.attr("a"), Some("1 < 2"));
+        assert_eq!(doc.attr("b"), Some("it's \"q\""));
+        assert_eq!(doc.attr("c"), None);
+        let item = doc.child("item").unwrap();
+        assert_eq!(item.attr("id"), Some("x>y"));
+        assert_eq!(item.text, "one & AB");
+        assert!(doc.child("empty").unwrap().children.is_empty());
+        assert!(doc.text.contains("<raw>"));
+        // unterminated elements are closed at the end
+        assert!(doc.child("open").is_some());
+        assert!(parse("no markup").is_none());
+        assert!(parse("<a><!-- open").is_none());
+        assert_eq!(unescape("&bogus; &#xZZ; &"), "&bogus; &#xZZ; &");
+    }
+
+    #[test]
+    fn write_and_find() {
+        let mut root = Element::new("a").with_attr("k", "\"<&>\"");
+        let mut b = Element::new("b");
+        b.text = "x\r\ny".to_string();
+        let mut c = Element::new("c");
+        c.children.push(Element::new("b"));
+        root.children.push(b);
+        root.children.push(c);
+        let mut out = String::new();
+        root.write(&mut out, 0);
+        assert_eq!(
+            out,
+            "<a k=\"&quot;&lt;&amp;&gt;&quot;\">\n  <b>x&#13;\ny</b>\n  \
+             <c>\n    <b/>\n  </c>\n</a>\n"
+        );
+        let parsed = parse(&out).unwrap();
+        assert_eq!(parsed.attr("k"), Some("\"<&>\""));
+        assert_eq!(parsed.child("b").unwrap().text, "x\r\ny");
+        let mut found = Vec::new();
+        parsed.find_all("b", &mut found);
+        assert_eq!(found.len(), 2);
+    }
+}